@@ -1,6 +1,6 @@
 extern crate tokenizers as tk;
 
-use tk::tokenizer::PaddingDirection;
+use tk::tokenizer::{PaddingDirection, TruncationDirection};
 
 use crate::container::Container;
 use neon::prelude::*;
@@ -227,7 +227,9 @@ declare_types! {
             let mut this = cx.this();
             let guard = cx.lock();
             this.borrow_mut(&guard).encoding.execute_mut(|encoding| {
-                encoding.unwrap().truncate(length, stride);
+                encoding
+                    .unwrap()
+                    .truncate(length, stride, TruncationDirection::Right);
             });
 
             Ok(cx.undefined().upcast())
@@ -62,7 +62,7 @@ fn whitespace(mut cx: FunctionContext) -> JsResult<JsPreTokenizer> {
     pretok
         .borrow_mut(&guard)
         .pretok
-        .to_owned(Box::new(tk::pre_tokenizers::whitespace::Whitespace));
+        .to_owned(Box::new(tk::pre_tokenizers::whitespace::Whitespace::default()));
     Ok(pretok)
 }
 
@@ -73,7 +73,7 @@ fn whitespace_split(mut cx: FunctionContext) -> JsResult<JsPreTokenizer> {
     pretok
         .borrow_mut(&guard)
         .pretok
-        .to_owned(Box::new(tk::pre_tokenizers::whitespace::WhitespaceSplit));
+        .to_owned(Box::new(tk::pre_tokenizers::whitespace::WhitespaceSplit::default()));
     Ok(pretok)
 }
 
@@ -22,8 +22,8 @@ impl WorkingTokenizer {
 unsafe impl Send for WorkingTokenizer {}
 
 pub enum EncodeTask {
-    Single(WorkingTokenizer, Option<EncodeInput>, bool),
-    Batch(WorkingTokenizer, Option<Vec<EncodeInput>>, bool),
+    Single(WorkingTokenizer, Option<EncodeInput<'static>>, bool),
+    Batch(WorkingTokenizer, Option<Vec<EncodeInput<'static>>>, bool),
 }
 
 pub enum EncodeOutput {
@@ -121,9 +121,9 @@ declare_types! {
             let callback = cx.argument::<JsFunction>(3)?;
 
             let input = if let Some(pair) = pair {
-                tk::tokenizer::EncodeInput::Dual(sentence, pair)
+                tk::tokenizer::EncodeInput::Dual(sentence.into(), pair.into())
             } else {
-                tk::tokenizer::EncodeInput::Single(sentence)
+                tk::tokenizer::EncodeInput::Single(sentence.into())
             };
 
             let worker = {
@@ -148,7 +148,7 @@ declare_types! {
             let inputs = cx.argument::<JsArray>(0)?.to_vec(&mut cx)?;
             let inputs = inputs.into_iter().map(|value| {
                 if let Ok(s) = value.downcast::<JsString>() {
-                    Ok(tk::tokenizer::EncodeInput::Single(s.value()))
+                    Ok(tk::tokenizer::EncodeInput::Single(s.value().into()))
                 } else if let Ok(arr) = value.downcast::<JsArray>() {
                     if arr.len() != 2 {
                         cx.throw_error("Input must be an array of `String | [String, String]`")
@@ -157,11 +157,13 @@ declare_types! {
                             arr.get(&mut cx, 0)?
                                 .downcast::<JsString>()
                                 .or_throw(&mut cx)?
-                                .value(),
+                                .value()
+                                .into(),
                             arr.get(&mut cx, 1)?
                                 .downcast::<JsString>()
                                 .or_throw(&mut cx)?
-                                .value())
+                                .value()
+                                .into())
                         )
                     }
                 } else {
@@ -375,6 +377,7 @@ declare_types! {
                     max_length,
                     stride,
                     strategy,
+                    respect_word_boundaries: false,
                 }));
             }
 
@@ -135,6 +135,15 @@ impl PyDecoder {
     }
 }
 
+impl Clone for PyDecoder {
+    fn clone(&self) -> Self {
+        let gil = Python::acquire_gil();
+        PyDecoder {
+            class: self.class.clone_ref(gil.python()),
+        }
+    }
+}
+
 impl tk::tokenizer::Decoder for PyDecoder {
     fn decode(&self, tokens: Vec<String>) -> Result<String> {
         let gil = Python::acquire_gil();
@@ -4,7 +4,7 @@ use crate::error::PyError;
 use pyo3::prelude::*;
 use pyo3::types::*;
 use pyo3::{PyObjectProtocol, PySequenceProtocol};
-use tk::tokenizer::PaddingDirection;
+use tk::tokenizer::{PaddingDirection, TruncationDirection};
 
 #[pyclass(dict)]
 #[repr(transparent)]
@@ -118,17 +118,31 @@ impl Encoding {
     #[args(kwargs = "**")]
     fn truncate(&mut self, max_length: usize, kwargs: Option<&PyDict>) -> PyResult<()> {
         let mut stride = 0;
+        let mut direction = TruncationDirection::Right;
 
         if let Some(kwargs) = kwargs {
             for (key, value) in kwargs {
                 let key: &str = key.extract()?;
                 match key {
                     "stride" => stride = value.extract()?,
+                    "direction" => {
+                        let value: &str = value.extract()?;
+                        direction = match value {
+                            "left" => Ok(TruncationDirection::Left),
+                            "right" => Ok(TruncationDirection::Right),
+                            other => Err(PyError(format!(
+                                "Unknown `direction`: `{}`. Use \
+                                 one of `left` or `right`",
+                                other
+                            ))
+                            .into_pyerr()),
+                        }?;
+                    }
                     _ => println!("Ignored unknown kwarg option {}", key),
                 }
             }
         }
 
-        Ok(self.encoding.truncate(max_length, stride))
+        Ok(self.encoding.truncate(max_length, stride, direction))
     }
 }
@@ -74,7 +74,9 @@ impl Whitespace {
     #[new]
     fn new(obj: &PyRawObject) -> PyResult<()> {
         Ok(obj.init(PreTokenizer {
-            pretok: Container::Owned(Box::new(tk::pre_tokenizers::whitespace::Whitespace)),
+            pretok: Container::Owned(Box::new(
+                tk::pre_tokenizers::whitespace::Whitespace::default(),
+            )),
         }))
     }
 }
@@ -86,7 +88,9 @@ impl WhitespaceSplit {
     #[new]
     fn new(obj: &PyRawObject) -> PyResult<()> {
         Ok(obj.init(PreTokenizer {
-            pretok: Container::Owned(Box::new(tk::pre_tokenizers::whitespace::WhitespaceSplit)),
+            pretok: Container::Owned(Box::new(
+                tk::pre_tokenizers::whitespace::WhitespaceSplit::default(),
+            )),
         }))
     }
 }
@@ -169,6 +173,15 @@ impl PyPreTokenizer {
     }
 }
 
+impl Clone for PyPreTokenizer {
+    fn clone(&self) -> Self {
+        let gil = Python::acquire_gil();
+        PyPreTokenizer {
+            class: self.class.clone_ref(gil.python()),
+        }
+    }
+}
+
 impl tk::tokenizer::PreTokenizer for PyPreTokenizer {
     fn pre_tokenize(
         &self,
@@ -95,6 +95,7 @@ impl Tokenizer {
             max_length,
             stride,
             strategy,
+            respect_word_boundaries: false,
         }));
 
         Ok(())
@@ -179,9 +180,9 @@ impl Tokenizer {
             self.tokenizer
                 .encode(
                     if let Some(pair) = pair {
-                        tk::tokenizer::EncodeInput::Dual(sentence.to_owned(), pair.to_owned())
+                        tk::tokenizer::EncodeInput::Dual(sentence.into(), pair.into())
                     } else {
-                        tk::tokenizer::EncodeInput::Single(sentence.to_owned())
+                        tk::tokenizer::EncodeInput::Single(sentence.into())
                     },
                     add_special_tokens,
                 )
@@ -200,9 +201,9 @@ impl Tokenizer {
             .into_iter()
             .map(|item| {
                 if let Ok(s1) = item.extract::<String>() {
-                    Ok(tk::tokenizer::EncodeInput::Single(s1))
+                    Ok(tk::tokenizer::EncodeInput::Single(s1.into()))
                 } else if let Ok((s1, s2)) = item.extract::<(String, String)>() {
-                    Ok(tk::tokenizer::EncodeInput::Dual(s1, s2))
+                    Ok(tk::tokenizer::EncodeInput::Dual(s1.into(), s2.into()))
                 } else {
                     Err(exceptions::Exception::py_err(
                         "Input must be a list[str] or list[(str, str)]",
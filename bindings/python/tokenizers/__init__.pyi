@@ -78,7 +78,7 @@ class Encoding:
                 The pad token to be used when padding
         """
         pass
-    def truncate(self, max_length: int, stride: Optional[int] = 0):
+    def truncate(self, max_length: int, stride: Optional[int] = 0, direction: Optional[str] = "right"):
         """ Truncate the current Encoding at the given max_length
 
         Args:
@@ -88,6 +88,9 @@ class Encoding:
             stride: (`optional`) unsigned int:
                 The length of the previous first sequence to be included
                 in the overflowing sequence
+
+            direction: (`optional`) str:
+                Can be one of `right` or `left`, the side from which the tokens get removed
         """
         pass
 
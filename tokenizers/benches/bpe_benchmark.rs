@@ -29,8 +29,8 @@ fn create_gpt2_tokenizer(bpe: BPE) -> Tokenizer {
     tokenizer
 }
 
-fn line_to_input(line: io::Result<String>) -> EncodeInput {
-    EncodeInput::Single(line.unwrap())
+fn line_to_input(line: io::Result<String>) -> EncodeInput<'static> {
+    EncodeInput::Single(line.unwrap().into())
 }
 
 fn iter_bench_encode(iters: u64, tokenizer: &Tokenizer, lines: &[EncodeInput]) -> Duration {
@@ -44,6 +44,22 @@ fn iter_bench_encode(iters: u64, tokenizer: &Tokenizer, lines: &[EncodeInput]) -
         let start = Instant::now();
         let _ = black_box(tokenizer.encode(input, false));
         duration = duration.checked_add(start.elapsed()).unwrap();
+        line_index += 1;
+    }
+    duration
+}
+
+fn iter_bench_encode_str(iters: u64, tokenizer: &Tokenizer, lines: &[String]) -> Duration {
+    let mut duration = Duration::new(0, 0);
+    let mut line_index: usize = 0;
+    for _i in 0..iters {
+        if line_index >= lines.len() {
+            line_index = 0;
+        }
+        let start = Instant::now();
+        let _ = black_box(tokenizer.encode(lines[line_index].as_str().into(), false));
+        duration = duration.checked_add(start.elapsed()).unwrap();
+        line_index += 1;
     }
     duration
 }
@@ -106,6 +122,83 @@ fn bench_gpt2(c: &mut Criterion) {
     c.bench_function("BPE GPT2 encode batch, no cache", |b| {
         b.iter_custom(|iters| iter_bench_encode_batch(iters, &tokenizer, &batches))
     });
+
+    // Short inputs, either borrowed or copied before encoding
+    let short_lines = lines
+        .iter()
+        .filter_map(|line| match line {
+            EncodeInput::Single(s) => {
+                Some(s.split_whitespace().take(5).collect::<Vec<_>>().join(" "))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let short_inputs = short_lines
+        .iter()
+        .map(|line| EncodeInput::Single(line.clone().into()))
+        .collect::<Vec<_>>();
+
+    c.bench_function("BPE GPT2 encode short inputs, borrowed", |b| {
+        b.iter_custom(|iters| iter_bench_encode_str(iters, &tokenizer, &short_lines))
+    });
+
+    c.bench_function("BPE GPT2 encode short inputs, owned", |b| {
+        b.iter_custom(|iters| iter_bench_encode(iters, &tokenizer, &short_inputs))
+    });
+
+    // Without any whitespace, each input is a single long word, which stresses the merge loop
+    let long_words = lines
+        .iter()
+        .filter_map(|line| match line {
+            EncodeInput::Single(s) => {
+                let word = s.split_whitespace().collect::<String>();
+                if word.len() > 50 {
+                    Some(EncodeInput::Single(word.into()))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    c.bench_function("BPE GPT2 encode long words, no cache", |b| {
+        b.iter_custom(|iters| iter_bench_encode(iters, &tokenizer, &long_words))
+    });
+
+    // Short inputs, with a large added vocabulary that they don't use most of the time
+    let bpe = BPE::from_files("data/gpt2-vocab.json", "data/gpt2-merges.txt")
+        .build()
+        .unwrap();
+    let mut tokenizer = create_gpt2_tokenizer(bpe);
+    tokenizer.add_special_tokens(
+        &(0..1000)
+            .map(|i| format!("<extra_id_{}>", i))
+            .collect::<Vec<_>>(),
+    );
+    let short_batches = batches
+        .iter()
+        .map(|batch| {
+            batch
+                .iter()
+                .filter_map(|input| match input {
+                    EncodeInput::Single(s) => Some(EncodeInput::Single(
+                        s.split_whitespace()
+                            .take(5)
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                            .into(),
+                    )),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    c.bench_function(
+        "BPE GPT2 encode batch, short inputs, many added tokens",
+        |b| b.iter_custom(|iters| iter_bench_encode_batch(iters, &tokenizer, &short_batches)),
+    );
 }
 
 criterion_group! {
@@ -2,6 +2,7 @@ use crate::tokenizer::{Decoder, Result};
 
 /// Allows decoding Original BPE by joining all the tokens and then replacing
 /// the suffix used to identify end-of-words by whitespaces
+#[derive(Clone)]
 pub struct BPEDecoder {
     suffix: String,
 }
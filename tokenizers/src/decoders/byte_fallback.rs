@@ -0,0 +1,79 @@
+use crate::tokenizer::{Decoder, Result};
+
+/// The ByteFallback decoder reassembles the byte tokens (like `<0xE2>`) used by the models
+/// with byte-fallback for the chars missing from their vocabulary. Consecutive byte tokens are
+/// decoded together as UTF-8, and the invalid sequences are replaced by `U+FFFD`. Any other
+/// token is kept as is, and all of them are concatenated.
+#[derive(Clone, Default)]
+pub struct ByteFallback;
+
+impl ByteFallback {
+    pub fn new() -> Self {
+        ByteFallback
+    }
+
+    /// Parse a byte token like `<0xE2>`
+    fn parse_byte(token: &str) -> Option<u8> {
+        if token.len() == 6 && token.starts_with("<0x") && token.ends_with('>') {
+            let hex = &token[3..5];
+            if hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                u8::from_str_radix(hex, 16).ok()
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+}
+
+impl Decoder for ByteFallback {
+    fn decode(&self, tokens: Vec<String>) -> Result<String> {
+        let mut output = String::new();
+        let mut bytes = vec![];
+        for token in &tokens {
+            if let Some(byte) = Self::parse_byte(token) {
+                bytes.push(byte);
+            } else {
+                if !bytes.is_empty() {
+                    output.push_str(&String::from_utf8_lossy(&bytes));
+                    bytes.clear();
+                }
+                output.push_str(token);
+            }
+        }
+        output.push_str(&String::from_utf8_lossy(&bytes));
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(tokens: &[&str]) -> String {
+        ByteFallback
+            .decode(tokens.iter().map(|t| t.to_string()).collect())
+            .unwrap()
+    }
+
+    #[test]
+    fn decode_bytes() {
+        assert_eq!(decode(&["<0x61>", "b", "c"]), "abc");
+        assert_eq!(decode(&["Hey", "<0xE2>", "<0x96>", "<0x81>", "!"]), "Hey▁!");
+        assert_eq!(decode(&["<0xF0>", "<0x9F>", "<0xA4>", "<0x97>"]), "🤗");
+        // Not byte tokens
+        assert_eq!(
+            decode(&["<0x6>", "<0xZZ>", "<0x610>"]),
+            "<0x6><0xZZ><0x610>"
+        );
+    }
+
+    #[test]
+    fn decode_invalid_bytes() {
+        assert_eq!(decode(&["<0xE2>", "<0x96>", "a"]), "\u{FFFD}a");
+        assert_eq!(decode(&["a", "<0xFF>"]), "a\u{FFFD}");
+        assert_eq!(decode(&["<0xE2>", "a", "<0x96>"]), "\u{FFFD}a\u{FFFD}");
+    }
+}
@@ -1,4 +1,5 @@
 pub mod bpe;
+pub mod byte_fallback;
 pub mod wordpiece;
 
 // Re-export these as decoders
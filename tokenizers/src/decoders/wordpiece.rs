@@ -2,6 +2,7 @@ use crate::tokenizer::{Decoder, Result};
 
 /// The WordPiece decoder takes care of decoding a list of wordpiece tokens
 /// back into a readable string.
+#[derive(Clone)]
 pub struct WordPiece {
     /// The prefix to be used for continuing subwords
     prefix: String,
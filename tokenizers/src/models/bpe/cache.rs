@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::RwLock;
 
 /// The default capacity for a `BPE`'s internal cache.
@@ -16,6 +17,8 @@ where
 {
     map: RwLock<HashMap<K, V>>,
     pub capacity: usize,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
 }
 
 impl<K, V> Default for Cache<K, V>
@@ -36,7 +39,12 @@ where
     /// Create new `Cache` with the given capacity.
     pub(super) fn new(capacity: usize) -> Self {
         let map = RwLock::new(HashMap::with_capacity(capacity));
-        Cache { map, capacity }
+        Cache {
+            map,
+            capacity,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
     }
 
     /// Create a fresh `Cache` with the same configuration.
@@ -44,9 +52,20 @@ where
         Self::new(self.capacity)
     }
 
-    /// Clear the cache.
+    /// Clear the cache, along with its statistics.
     pub(super) fn clear(&self) {
         self.map.write().unwrap().clear();
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    /// Get the number of hits and misses since the cache was created or last cleared. The
+    /// lookups that couldn't acquire the lock count as misses.
+    pub(super) fn stats(&self) -> (usize, usize) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
     }
 
     pub(super) fn get_values<I>(&self, keys_iter: I) -> Option<Vec<Option<V>>>
@@ -54,8 +73,16 @@ where
         I: Iterator<Item = K>,
     {
         if let Ok(ref mut cache) = self.map.try_read() {
-            Some(keys_iter.map(|k| cache.get(&k).cloned()).collect())
+            let values = keys_iter
+                .map(|k| cache.get(&k).cloned())
+                .collect::<Vec<_>>();
+            let hits = values.iter().filter(|v| v.is_some()).count();
+            self.hits.fetch_add(hits, Ordering::Relaxed);
+            self.misses
+                .fetch_add(values.len() - hits, Ordering::Relaxed);
+            Some(values)
         } else {
+            self.misses.fetch_add(keys_iter.count(), Ordering::Relaxed);
             None
         }
     }
@@ -89,3 +116,26 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let cache: Cache<String, u32> = Cache::new(10);
+        assert_eq!(
+            cache.get_values(vec!["a".into()].into_iter()),
+            Some(vec![None])
+        );
+        cache.set_values(vec!["a".into()].into_iter(), vec![Some(1)].into_iter());
+        assert_eq!(
+            cache.get_values(vec!["a".into(), "b".into()].into_iter()),
+            Some(vec![Some(1), None])
+        );
+        assert_eq!(cache.stats(), (1, 2));
+
+        cache.clear();
+        assert_eq!(cache.stats(), (0, 0));
+    }
+}
@@ -1,8 +1,9 @@
 use super::{
-    super::OrderedVocabIter, Cache, Error, Pair, WithFirstLastIterator, Word,
-    DEFAULT_CACHE_CAPACITY,
+    super::{build_vocab, OrderedVocabIter, VocabEntries},
+    Cache, Error, Pair, WithFirstLastIterator, Word, DEFAULT_CACHE_CAPACITY,
 };
 use crate::tokenizer::{Model, Offsets, Result, Token};
+use rayon::prelude::*;
 use serde_json::Value;
 use std::{
     collections::HashMap,
@@ -210,19 +211,19 @@ impl BPE {
 
         let mut buffer = String::new();
         vocab_file.read_to_string(&mut buffer)?;
-        let json: Value = serde_json::from_str(&buffer)?;
-        let mut vocab = HashMap::new();
-        match json {
-            Value::Object(m) => {
-                for (token, id) in m {
-                    if let Value::Number(id) = id {
-                        let id = id.as_u64().ok_or(Error::BadVocabulary)? as u32;
-                        vocab.insert(token, id);
-                    }
-                }
-            }
-            _ => return Err(Box::new(Error::BadVocabulary)),
+        let entries = match serde_json::from_str::<VocabEntries>(&buffer) {
+            Ok(entries) => entries.0,
+            Err(e) if e.is_data() => return Err(Box::new(Error::BadVocabulary)),
+            Err(e) => return Err(Box::new(Error::JsonError(e))),
         };
+        let mut ids = Vec::with_capacity(entries.len());
+        for (token, id) in entries {
+            if let Value::Number(id) = id {
+                let id = id.as_u64().ok_or(Error::BadVocabulary)? as u32;
+                ids.push((token, id));
+            }
+        }
+        let vocab = build_vocab(ids)?;
 
         // Read merges file
         let merge_file = File::open(merges)?;
@@ -265,6 +266,27 @@ impl BPE {
         }
     }
 
+    /// Get the number of hits and misses of the cache since it was created or last cleared.
+    /// Returns `None` if the cache is disabled.
+    pub fn cache_stats(&self) -> Option<(usize, usize)> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Get the merges, in order of priority. Two merges with the same rank are sorted by their
+    /// pair of ids, so the order never depends on the `HashMap` one.
+    pub fn get_merges(&self) -> Vec<(String, String)> {
+        let mut merges = self
+            .merges
+            .iter()
+            .map(|(pair, (rank, _))| (*rank, *pair))
+            .collect::<Vec<_>>();
+        merges.sort_unstable();
+        merges
+            .into_iter()
+            .map(|(_, pair)| (self.vocab_r[&pair.0].clone(), self.vocab_r[&pair.1].clone()))
+            .collect()
+    }
+
     pub fn get_vocab(&self) -> &Vocab {
         &self.vocab
     }
@@ -329,6 +351,10 @@ impl BPE {
 }
 
 impl Model for BPE {
+    fn get_vocab(&self) -> &HashMap<String, u32> {
+        &self.vocab
+    }
+
     fn get_vocab_size(&self) -> usize {
         self.vocab.len()
     }
@@ -398,6 +424,27 @@ impl Model for BPE {
         self.vocab.get(token).copied()
     }
 
+    fn get_unk_id(&self) -> Option<u32> {
+        self.unk_token
+            .as_ref()
+            .and_then(|unk| self.vocab.get(unk).copied())
+    }
+
+    fn get_continuing_subword_prefix(&self) -> Option<&str> {
+        self.continuing_subword_prefix.as_deref()
+    }
+
+    fn get_end_of_word_suffix(&self) -> Option<&str> {
+        self.end_of_word_suffix.as_deref()
+    }
+
+    fn tokenize_batch(&self, batches: Vec<Vec<(String, Offsets)>>) -> Result<Vec<Vec<Token>>> {
+        batches
+            .into_par_iter()
+            .map(|batch| self.tokenize(batch))
+            .collect()
+    }
+
     fn id_to_token(&self, id: u32) -> Option<String> {
         self.vocab_r.get(&id).cloned()
     }
@@ -427,19 +474,12 @@ impl Model for BPE {
             .iter()
             .collect();
         let mut merges_file = File::create(&merges_path)?;
-        let mut merges: Vec<(&Pair, &u32)> = self
-            .merges
-            .iter()
-            .map(|(pair, (rank, _))| (pair, rank))
-            .collect();
-        merges.sort_unstable_by_key(|k| *k.1);
         merges_file.write_all(b"#version: 0.2 - Trained by `huggingface/tokenizers`\n")?;
         merges_file.write_all(
-            &merges
+            &self
+                .get_merges()
                 .into_iter()
-                .flat_map(|(pair, _)| {
-                    format!("{} {}\n", self.vocab_r[&pair.0], self.vocab_r[&pair.1]).into_bytes()
-                })
+                .flat_map(|(a, b)| format!("{} {}\n", a, b).into_bytes())
                 .collect::<Vec<_>>()[..],
         )?;
 
@@ -450,6 +490,7 @@ impl Model for BPE {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::InvalidVocab;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -466,6 +507,14 @@ mod tests {
         let order_vocab_iter = OrderedVocabIter::new(&vocab_r);
         let serialized = serde_json::to_string(&order_vocab_iter).unwrap();
         assert_eq!(serialized, "{\"a\":0,\"b\":1,\"c\":2,\"ab\":3}");
+
+        // The ids may not be contiguous
+        let vocab_r: VocabR = [(5, "b".into()), (0, "a".into()), (12, "c".into())]
+            .iter()
+            .cloned()
+            .collect();
+        let serialized = serde_json::to_string(&OrderedVocabIter::new(&vocab_r)).unwrap();
+        assert_eq!(serialized, "{\"a\":0,\"b\":5,\"c\":12}");
     }
 
     #[test]
@@ -570,6 +619,44 @@ mod tests {
         assert_eq!(bpe.vocab.get("ab").unwrap(), &3u32);
     }
 
+    #[test]
+    fn test_save_is_reproducible() {
+        let vocab: Vocab = ["a", "b", "c", "d", "ab", "cd", "abcd", "bc"]
+            .iter()
+            .enumerate()
+            .map(|(id, token)| (token.to_string(), id as u32))
+            .collect();
+        let merges: Merges = [((0, 1), (0, 4)), ((2, 3), (1, 5)), ((4, 5), (2, 6))]
+            .iter()
+            .cloned()
+            .collect();
+        let bpe = BPE::new(vocab, merges);
+        assert_eq!(
+            bpe.get_merges(),
+            vec![
+                ("a".to_string(), "b".to_string()),
+                ("c".to_string(), "d".to_string()),
+                ("ab".to_string(), "cd".to_string())
+            ]
+        );
+
+        let read_saved = |bpe: &BPE| {
+            let folder = tempfile::tempdir().unwrap();
+            bpe.save(folder.path(), None)
+                .unwrap()
+                .into_iter()
+                .map(|path| std::fs::read(path).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let saved = read_saved(&bpe);
+        assert_eq!(saved, read_saved(&bpe));
+        assert_eq!(saved, read_saved(&bpe.clone()));
+        assert_eq!(
+            String::from_utf8(saved[1].clone()).unwrap(),
+            "#version: 0.2 - Trained by `huggingface/tokenizers`\na b\nc d\nab cd\n"
+        );
+    }
+
     #[test]
     // Ensure `MergeTokenOutOfVocabulary` error is returned when it should be.
     fn test_bpe_from_files_merge_token_oov() {
@@ -628,4 +715,36 @@ mod tests {
             },
         }
     }
+
+    #[test]
+    // Ensure `InvalidVocab` error is returned when the vocab.json file has collisions.
+    fn test_bpe_from_files_duplicated_vocab() {
+        // Set up vocab file with a duplicated token and a duplicated id.
+        let mut vocab_file = NamedTempFile::new().unwrap();
+        vocab_file
+            .write_all(b"{\"a\": 0, \"b\": 1, \"c\": 1, \"a\": 2}")
+            .unwrap();
+
+        let mut merges_file = NamedTempFile::new().unwrap();
+        merges_file.write_all(b"#version: 0.2\n").unwrap();
+
+        match BPE::from_files(
+            vocab_file.path().to_str().unwrap(),
+            merges_file.path().to_str().unwrap(),
+        )
+        .build()
+        {
+            Ok(_) => unreachable!(),
+            Err(err) => match err.downcast_ref::<InvalidVocab>() {
+                Some(err) => {
+                    assert_eq!(err.duplicated_tokens, vec![String::from("a")]);
+                    assert_eq!(
+                        err.duplicated_ids,
+                        vec![(1, vec![String::from("b"), String::from("c")])]
+                    );
+                }
+                _ => unreachable!(),
+            },
+        }
+    }
 }
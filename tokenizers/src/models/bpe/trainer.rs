@@ -2,7 +2,9 @@
 
 use super::{Pair, WithFirstLastIterator, Word, BPE};
 use crate::tokenizer::{Model, Result, Trainer};
+use crate::utils::hash;
 use indicatif::{ProgressBar, ProgressStyle};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use rayon::prelude::*;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
@@ -11,17 +13,21 @@ use std::collections::{BinaryHeap, HashMap, HashSet};
 struct Merge {
     pair: Pair,
     count: u32,
-    pos: HashSet<usize>,
+    /// Breaks the ties between pairs with the same count. Always 0 without a seed.
+    tie: u64,
+    pos: hash::HashSet<usize>,
 }
 impl PartialEq for Merge {
     fn eq(&self, other: &Self) -> bool {
-        self.count == other.count && self.pair == other.pair
+        self.count == other.count && self.tie == other.tie && self.pair == other.pair
     }
 }
 impl PartialOrd for Merge {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         if self.count != other.count {
             Some(self.count.cmp(&other.count))
+        } else if self.tie != other.tie {
+            Some(self.tie.cmp(&other.tie))
         } else {
             // Here we want ascending order
             Some(other.pair.cmp(&self.pair))
@@ -43,6 +49,7 @@ struct Config {
     initial_alphabet: HashSet<char>,
     continuing_subword_prefix: Option<String>,
     end_of_word_suffix: Option<String>,
+    seed: Option<u64>,
 }
 
 /// A `BpeTrainerBuilder` can be used to create a `BpeTrainer` with a custom
@@ -63,6 +70,7 @@ impl Default for BpeTrainerBuilder {
                 initial_alphabet: HashSet::new(),
                 continuing_subword_prefix: None,
                 end_of_word_suffix: None,
+                seed: None,
             },
         }
     }
@@ -122,6 +130,14 @@ impl BpeTrainerBuilder {
         self
     }
 
+    /// Set the seed used to break the ties between pairs with the same count. Without it, the
+    /// pair with the smallest ids is merged first. Training twice on the same corpus with the
+    /// same seed always gives the same model.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
     /// Constructs the final BpeTrainer
     pub fn build(self) -> BpeTrainer {
         BpeTrainer {
@@ -133,6 +149,7 @@ impl BpeTrainerBuilder {
             initial_alphabet: self.config.initial_alphabet,
             continuing_subword_prefix: self.config.continuing_subword_prefix,
             end_of_word_suffix: self.config.end_of_word_suffix,
+            seed: self.config.seed,
         }
     }
 }
@@ -171,6 +188,8 @@ pub struct BpeTrainer {
     continuing_subword_prefix: Option<String>,
     /// An optional suffix to caracterize and end-of-word subword
     end_of_word_suffix: Option<String>,
+    /// An optional seed to break the ties between pairs with the same count
+    seed: Option<u64>,
 }
 
 impl Default for BpeTrainer {
@@ -192,6 +211,21 @@ impl BpeTrainer {
         BpeTrainerBuilder::new()
     }
 
+    /// The special tokens added at the beginning of the trained vocabulary
+    pub fn get_special_tokens(&self) -> &[String] {
+        &self.special_tokens
+    }
+
+    /// The prefix added to the subwords that don't start a word, also used by the trained model
+    pub fn get_continuing_subword_prefix(&self) -> Option<&str> {
+        self.continuing_subword_prefix.as_deref()
+    }
+
+    /// The suffix added to the subwords that end a word, also used by the trained model
+    pub fn get_end_of_word_suffix(&self) -> Option<&str> {
+        self.end_of_word_suffix.as_deref()
+    }
+
     /// Setup a progress bar if asked to show progress
     fn setup_progress(&self) -> Option<ProgressBar> {
         if self.show_progress {
@@ -242,7 +276,7 @@ impl BpeTrainer {
         id2w: &mut Vec<String>,
     ) {
         // Compute the alphabet from seen words
-        let mut alphabet: HashMap<char, usize> = HashMap::new();
+        let mut alphabet: hash::HashMap<char, usize> = hash::HashMap::default();
         for (word, count) in wc {
             for c in word.chars() {
                 alphabet
@@ -304,7 +338,11 @@ impl BpeTrainer {
         let mut words: Vec<Word> = Vec::with_capacity(wc.len());
         let mut counts: Vec<u32> = Vec::with_capacity(wc.len());
 
-        for (word, count) in wc {
+        // Sort the words, so the ids given to the subwords don't depend on the HashMap order
+        let mut sorted_wc = wc.iter().collect::<Vec<_>>();
+        sorted_wc.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        for (word, count) in sorted_wc {
             let mut current_word = Word::new();
             counts.push(*count);
 
@@ -349,10 +387,14 @@ impl BpeTrainer {
         words: &[Word],
         counts: &[u32],
         p: &Option<ProgressBar>,
-    ) -> (HashMap<Pair, i32>, HashMap<Pair, HashSet<usize>>) {
-        let mut pair_counts: HashMap<Pair, i32> = HashMap::with_capacity(self.vocab_size * 2);
-        let mut where_to_update: HashMap<Pair, HashSet<usize>> =
-            HashMap::with_capacity(self.vocab_size * 2);
+    ) -> (
+        hash::HashMap<Pair, i32>,
+        hash::HashMap<Pair, hash::HashSet<usize>>,
+    ) {
+        let mut pair_counts: hash::HashMap<Pair, i32> =
+            hash::HashMap::with_capacity_and_hasher(self.vocab_size * 2, Default::default());
+        let mut where_to_update: hash::HashMap<Pair, hash::HashSet<usize>> =
+            hash::HashMap::with_capacity_and_hasher(self.vocab_size * 2, Default::default());
         let n_threads = if words.len() > rayon::current_num_threads() * 5 {
             rayon::current_num_threads()
         } else {
@@ -366,8 +408,9 @@ impl BpeTrainer {
         let results = (0..n_threads)
             .into_par_iter()
             .map(|n| {
-                let mut pair_counts = HashMap::new();
-                let mut where_to_update: HashMap<Pair, HashSet<usize>> = HashMap::new();
+                let mut pair_counts = hash::HashMap::default();
+                let mut where_to_update: hash::HashMap<Pair, hash::HashSet<usize>> =
+                    hash::HashMap::default();
 
                 let mut done = 0;
                 for (i, word) in words.chunks(batch).nth(n).unwrap().iter().enumerate() {
@@ -388,7 +431,7 @@ impl BpeTrainer {
                                 h.insert(index);
                             })
                             .or_insert_with(|| {
-                                let mut h = HashSet::new();
+                                let mut h = hash::HashSet::default();
                                 h.insert(index);
                                 h
                             });
@@ -455,6 +498,20 @@ impl BpeTrainer {
             self.tokenize_words(&word_counts, &mut word_to_id, &mut id_to_word, &progress);
         self.finalize_progress(&progress, words.len());
 
+        // With a seed, each token gets a random priority used to break the ties between pairs
+        let mut rng = self.seed.map(StdRng::seed_from_u64);
+        let mut priorities: Vec<u64> = match &mut rng {
+            Some(rng) => (0..id_to_word.len()).map(|_| rng.gen()).collect(),
+            None => vec![],
+        };
+        let tie = |priorities: &[u64], pair: &Pair| -> u64 {
+            if priorities.is_empty() {
+                0
+            } else {
+                priorities[pair.0 as usize] ^ priorities[pair.1 as usize].rotate_left(32)
+            }
+        };
+
         //
         // 4. Count pairs in words
         //
@@ -468,6 +525,7 @@ impl BpeTrainer {
                 queue.push(Merge {
                     pair,
                     count: count as u32,
+                    tie: tie(&priorities, &pair),
                     pos,
                 });
             }
@@ -517,6 +575,9 @@ impl BpeTrainer {
             id_to_word.push(new_token.clone());
             word_to_id.insert(new_token.clone(), new_token_id);
             merges.push((top.pair, new_token_id));
+            if let Some(rng) = &mut rng {
+                priorities.push(rng.gen());
+            }
 
             // Merge the new pair in every words
             let changes = top
@@ -550,7 +611,7 @@ impl BpeTrainer {
                             h.insert(iw);
                         })
                         .or_insert_with(|| {
-                            let mut h = HashSet::new();
+                            let mut h = hash::HashSet::default();
                             h.insert(iw);
                             h
                         });
@@ -562,6 +623,7 @@ impl BpeTrainer {
                     queue.push(Merge {
                         pair,
                         count: count as u32,
+                        tie: tie(&priorities, &pair),
                         pos,
                     });
                 }
@@ -625,7 +687,8 @@ impl Trainer for BpeTrainer {
 #[cfg(test)]
 mod tests {
     use super::{BpeTrainer, Pair};
-    use std::collections::HashMap;
+    use crate::tokenizer::Model;
+    use std::collections::{HashMap, HashSet};
 
     #[test]
     fn test_train() {
@@ -699,4 +762,100 @@ mod tests {
         .collect();
         assert_eq!(model.merges, expected_merges);
     }
+
+    #[test]
+    fn test_train_special_tokens_and_alphabet() {
+        let word_counts: HashMap<String, u32> = [("abab".into(), 3), ("ba".into(), 1)]
+            .iter()
+            .cloned()
+            .collect();
+        let trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .special_tokens(vec!["<unk>".into(), "<pad>".into()])
+            .initial_alphabet(['z'].iter().copied().collect::<HashSet<_>>())
+            .vocab_size(7)
+            .build();
+        let (model, special_tokens) = trainer.train(word_counts).unwrap();
+
+        assert_eq!(
+            special_tokens,
+            vec!["<unk>".to_string(), "<pad>".to_string()]
+        );
+        // The special tokens come first, then the alphabet (including the initial one), and the
+        // merges stop as soon as the vocab size is reached
+        let expected_vocab: HashMap<String, u32> = [
+            ("<unk>".into(), 0),
+            ("<pad>".into(), 1),
+            ("a".into(), 2),
+            ("b".into(), 3),
+            ("z".into(), 4),
+            ("ab".into(), 5),
+            ("abab".into(), 6),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(model.vocab, expected_vocab);
+    }
+
+    #[test]
+    fn test_train_prefix_and_suffix() {
+        let word_counts: HashMap<String, u32> = [("hugs".into(), 3), ("hug".into(), 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let trainer = BpeTrainer::builder()
+            .show_progress(false)
+            .continuing_subword_prefix("##".into())
+            .end_of_word_suffix("</w>".into())
+            .build();
+        assert_eq!(trainer.get_continuing_subword_prefix(), Some("##"));
+        assert_eq!(trainer.get_end_of_word_suffix(), Some("</w>"));
+        let (model, _) = trainer.train(word_counts).unwrap();
+
+        // The merges are built from the prefixed and suffixed subwords
+        let mut vocab = model.vocab.keys().map(|t| t.as_str()).collect::<Vec<_>>();
+        vocab.sort_unstable();
+        assert_eq!(
+            vocab,
+            vec![
+                "##g", "##g</w>", "##gs</w>", "##s</w>", "##u", "g", "h", "hu", "hug</w>",
+                "hugs</w>", "s", "u"
+            ]
+        );
+
+        // And the trained model uses them
+        assert_eq!(Model::get_continuing_subword_prefix(&model), Some("##"));
+        assert_eq!(model.get_end_of_word_suffix(), Some("</w>"));
+        let tokens = model
+            .tokenize(vec![("hugs".into(), (0, 4)), ("gs".into(), (5, 7))])
+            .unwrap()
+            .into_iter()
+            .map(|t| t.value)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec!["hugs</w>", "g", "##s</w>"]);
+    }
+
+    #[test]
+    fn test_train_with_seed() {
+        // Every pair appears once, so all the merges are ties
+        let word_counts: HashMap<String, u32> = ["roses", "are", "red", "voilets", "blue"]
+            .iter()
+            .map(|w| (w.to_string(), 1))
+            .collect();
+        let train = |seed: Option<u64>| {
+            let mut builder = BpeTrainer::builder().show_progress(false).vocab_size(30);
+            if let Some(seed) = seed {
+                builder = builder.seed(seed);
+            }
+            let (model, _) = builder.build().train(word_counts.clone()).unwrap();
+            (model.get_vocab().clone(), model.get_merges())
+        };
+
+        // The same seed always gives the same model
+        assert_eq!(train(Some(42)), train(Some(42)));
+        assert_eq!(train(None), train(None));
+        // And different seeds break the ties differently
+        assert_ne!(train(Some(42)).1, train(Some(1337)).1);
+    }
 }
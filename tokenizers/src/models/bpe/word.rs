@@ -227,19 +227,9 @@ impl Word {
             }
         }
 
-        // Filter out the removed symbols
-        let mut i = 0;
-        loop {
-            if self.symbols.len() <= i {
-                break;
-            }
-
-            if self.symbols[i].len == 0 {
-                self.symbols.remove(i);
-            } else {
-                i += 1;
-            }
-        }
+        // Filter out the removed symbols. This must stay linear, long words can contain
+        // thousands of symbols
+        self.symbols.retain(|s| s.len != 0);
     }
 
     pub(super) fn get_chars(&self) -> Vec<u32> {
@@ -304,4 +294,28 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_merge_all_leftmost_first() {
+        // With the vocab {'a': 0, 'aa': 1, 'aaaa': 2}, and the merges
+        // ('a', 'a') -> 'aa' then ('aa', 'aa') -> 'aaaa'
+        let merges: HashMap<Pair, (u32, u32)> = [((0, 0), (0, 1)), ((1, 1), (1, 2))]
+            .iter()
+            .cloned()
+            .collect();
+
+        // Equal ranks are merged from left to right, so 'aaa' becomes 'aa' 'a'
+        let mut word = Word::new();
+        (0..3).for_each(|_| word.add(0));
+        word.merge_all(&merges, None);
+        assert_eq!(word.get_chars(), &[1u32, 0u32]);
+        assert_eq!(word.get_offsets(), &[(0, 2), (2, 3)]);
+
+        // And 'aaaaa' becomes 'aaaa' 'a'
+        let mut word = Word::new();
+        (0..5).for_each(|_| word.add(0));
+        word.merge_all(&merges, None);
+        assert_eq!(word.get_chars(), &[2u32, 0u32]);
+        assert_eq!(word.get_offsets(), &[(0, 4), (4, 5)]);
+    }
 }
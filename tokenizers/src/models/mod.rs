@@ -1,11 +1,15 @@
 //! Popular tokenizer models.
 
 pub mod bpe;
+pub mod unigram;
 pub mod wordlevel;
 pub mod wordpiece;
 
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
 use serde::{Serialize, Serializer};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt;
 
 /// Wraps a vocab mapping (ID -> token) to a struct that will be serialized in order
 /// of token ID, smallest to largest.
@@ -24,7 +28,215 @@ impl<'a> Serialize for OrderedVocabIter<'a> {
     where
         S: Serializer,
     {
-        let iter = (0u32..(self.vocab_r.len() as u32)).map(|i| (&self.vocab_r[&i], i));
-        serializer.collect_map(iter)
+        // The ids are not always contiguous, so we can't just look them up one after the other
+        let mut entries = self.vocab_r.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(id, _)| **id);
+        serializer.collect_map(entries.into_iter().map(|(id, token)| (token, id)))
+    }
+}
+
+/// The entries of a vocab.json file, in their order of appearance. Unlike a `serde_json::Map`,
+/// this keeps the duplicated tokens, so that we can report them.
+pub(crate) struct VocabEntries(pub Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for VocabEntries {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct VocabEntriesVisitor;
+        impl<'de> Visitor<'de> for VocabEntriesVisitor {
+            type Value = VocabEntries;
+
+            fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                write!(fmt, "a map of tokens to ids")
+            }
+
+            fn visit_map<V>(self, mut map: V) -> std::result::Result<Self::Value, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(VocabEntries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(VocabEntriesVisitor)
+    }
+}
+
+/// Error returned when loading a vocabulary in which tokens and ids don't map one to one.
+#[derive(Debug)]
+pub struct InvalidVocab {
+    /// The tokens that appear more than once
+    pub duplicated_tokens: Vec<String>,
+    /// The ids shared by several tokens, along with these tokens
+    pub duplicated_ids: Vec<(u32, Vec<String>)>,
+}
+
+impl fmt::Display for InvalidVocab {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid vocabulary:")?;
+        if !self.duplicated_tokens.is_empty() {
+            write!(f, " duplicated tokens {:?}", self.duplicated_tokens)?;
+        }
+        if !self.duplicated_ids.is_empty() {
+            write!(f, " ids used by several tokens {:?}", self.duplicated_ids)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidVocab {}
+
+/// Build a vocab from the given entries, checking that there is no collision between them.
+pub(crate) fn build_vocab<I>(entries: I) -> std::result::Result<HashMap<String, u32>, InvalidVocab>
+where
+    I: IntoIterator<Item = (String, u32)>,
+{
+    let mut vocab = HashMap::new();
+    let mut duplicated_tokens = vec![];
+    for (token, id) in entries {
+        if let Some(previous) = vocab.insert(token.clone(), id) {
+            // Keep the first id, for the error message
+            vocab.insert(token.clone(), previous);
+            duplicated_tokens.push(token);
+        }
+    }
+
+    let mut tokens_by_id: HashMap<u32, Vec<String>> = HashMap::new();
+    for (token, id) in &vocab {
+        tokens_by_id.entry(*id).or_default().push(token.clone());
+    }
+    let mut duplicated_ids = tokens_by_id
+        .into_iter()
+        .filter(|(_, tokens)| tokens.len() > 1)
+        .map(|(id, mut tokens)| {
+            tokens.sort();
+            (id, tokens)
+        })
+        .collect::<Vec<_>>();
+
+    if duplicated_tokens.is_empty() && duplicated_ids.is_empty() {
+        Ok(vocab)
+    } else {
+        duplicated_tokens.sort();
+        duplicated_tokens.dedup();
+        duplicated_ids.sort();
+        Err(InvalidVocab {
+            duplicated_tokens,
+            duplicated_ids,
+        })
+    }
+}
+
+/// Check that the given entries can be added to a vocab without any collision, either with the
+/// existing entries or between themselves.
+pub(crate) fn check_new_entries(
+    vocab: &HashMap<String, u32>,
+    vocab_r: &HashMap<u32, String>,
+    entries: &[(String, u32)],
+) -> std::result::Result<(), InvalidVocab> {
+    let mut new_tokens: HashMap<&str, u32> = HashMap::new();
+    let mut new_ids: HashMap<u32, &str> = HashMap::new();
+    let mut duplicated_tokens = vec![];
+    let mut duplicated_ids: HashMap<u32, Vec<String>> = HashMap::new();
+    for (token, id) in entries {
+        if vocab.contains_key(token) || new_tokens.insert(token, *id).is_some() {
+            duplicated_tokens.push(token.clone());
+        }
+        let existing = vocab_r
+            .get(id)
+            .map(|t| t.as_str())
+            .or_else(|| new_ids.get(id).copied());
+        if let Some(existing) = existing {
+            duplicated_ids
+                .entry(*id)
+                .or_insert_with(|| vec![existing.to_owned()])
+                .push(token.clone());
+        } else {
+            new_ids.insert(*id, token);
+        }
+    }
+
+    if duplicated_tokens.is_empty() && duplicated_ids.is_empty() {
+        Ok(())
+    } else {
+        duplicated_tokens.sort();
+        duplicated_tokens.dedup();
+        let mut duplicated_ids = duplicated_ids
+            .into_iter()
+            .map(|(id, mut tokens)| {
+                tokens.sort();
+                (id, tokens)
+            })
+            .collect::<Vec<_>>();
+        duplicated_ids.sort();
+        Err(InvalidVocab {
+            duplicated_tokens,
+            duplicated_ids,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vocab_collisions() {
+        let entries = |e: &[(&str, u32)]| {
+            e.iter()
+                .map(|(t, id)| (t.to_string(), *id))
+                .collect::<Vec<_>>()
+        };
+
+        let vocab = build_vocab(entries(&[("a", 0), ("b", 1)])).unwrap();
+        assert_eq!(vocab.len(), 2);
+
+        let err = build_vocab(entries(&[("a", 0), ("b", 1), ("a", 2), ("c", 1)])).unwrap_err();
+        assert_eq!(err.duplicated_tokens, vec!["a".to_owned()]);
+        assert_eq!(
+            err.duplicated_ids,
+            vec![(1, vec!["b".to_owned(), "c".to_owned()])]
+        );
+    }
+
+    #[test]
+    fn new_entries_collisions() {
+        let vocab = build_vocab(vec![("a".to_owned(), 0), ("b".to_owned(), 1)]).unwrap();
+        let vocab_r = vocab.iter().map(|(t, id)| (*id, t.clone())).collect();
+
+        assert!(check_new_entries(&vocab, &vocab_r, &[("c".to_owned(), 2)]).is_ok());
+        let err = check_new_entries(
+            &vocab,
+            &vocab_r,
+            &[
+                ("a".to_owned(), 2),
+                ("c".to_owned(), 1),
+                ("d".to_owned(), 3),
+                ("d".to_owned(), 4),
+                ("e".to_owned(), 3),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(err.duplicated_tokens, vec!["a".to_owned(), "d".to_owned()]);
+        assert_eq!(
+            err.duplicated_ids,
+            vec![
+                (1, vec!["b".to_owned(), "c".to_owned()]),
+                (3, vec!["d".to_owned(), "e".to_owned()])
+            ]
+        );
+    }
+
+    #[test]
+    fn vocab_entries_keep_duplicates() {
+        let entries: VocabEntries = serde_json::from_str(r#"{"a": 0, "b": 1, "a": 2}"#).unwrap();
+        assert_eq!(entries.0.len(), 3);
+        assert_eq!(entries.0[2], ("a".to_owned(), Value::from(2)));
     }
 }
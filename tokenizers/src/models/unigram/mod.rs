@@ -0,0 +1,32 @@
+//! [Unigram](https://arxiv.org/abs/1804.10959) model, as used by SentencePiece.
+mod model;
+mod trainer;
+
+/// Errors that can be encountered while using or constructing a `Unigram` model.
+#[derive(Debug)]
+pub enum Error {
+    /// The vocabulary doesn't contain any piece
+    EmptyVocabulary,
+    /// The id of the unk token is not part of the vocabulary
+    UnkIdNotInVocabulary(usize),
+    /// When the unigram.json file is in the wrong format
+    BadVocabulary,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::EmptyVocabulary => write!(f, "The vocabulary is empty"),
+            Error::UnkIdNotInVocabulary(id) => {
+                write!(f, "Unk id `{}` is not part of the vocabulary", id)
+            }
+            Error::BadVocabulary => write!(f, "Bad unigram json file"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Re-export
+pub use model::*;
+pub use trainer::*;
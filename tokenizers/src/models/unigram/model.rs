@@ -0,0 +1,377 @@
+use super::Error;
+use crate::models::build_vocab;
+use crate::tokenizer::{Model, Offsets, Result, Token};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// The penalty given to the unknown chars, relative to the worst score of the vocabulary
+const UNK_PENALTY: f64 = 10.0;
+
+/// A piece of the vocabulary found in a word, between the `start` and `end` chars
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Node {
+    pub id: u32,
+    pub start: usize,
+    pub end: usize,
+    pub score: f64,
+}
+
+fn log_sum_exp(a: f64, b: f64) -> f64 {
+    if a == f64::NEG_INFINITY {
+        b
+    } else if b == f64::NEG_INFINITY {
+        a
+    } else {
+        let max = a.max(b);
+        max + ((a - max).exp() + (b - max).exp()).ln()
+    }
+}
+
+/// A Unigram model: each piece of the vocabulary has a score (its log-probability), and each
+/// word gets split in the most likely sequence of pieces.
+#[derive(Clone)]
+pub struct Unigram {
+    vocab: Vec<(String, f64)>,
+    token_to_ids: HashMap<String, u32>,
+    unk_id: Option<usize>,
+    max_piece_len: usize,
+    min_score: f64,
+}
+
+impl Unigram {
+    /// Create a `Unigram` model from the given pieces and their scores. The id of each piece is
+    /// its position in the vocabulary, and `unk_id` is the id of the piece used for the unknown
+    /// chars.
+    pub fn new(vocab: Vec<(String, f64)>, unk_id: usize) -> Result<Self> {
+        if vocab.is_empty() {
+            return Err(Box::new(Error::EmptyVocabulary));
+        }
+        if unk_id >= vocab.len() {
+            return Err(Box::new(Error::UnkIdNotInVocabulary(unk_id)));
+        }
+        Ok(Self::from_pieces(vocab, Some(unk_id)))
+    }
+
+    /// Build the model without any check. Without `unk_id`, the words containing some unknown
+    /// chars can't be tokenized.
+    pub(super) fn from_pieces(vocab: Vec<(String, f64)>, unk_id: Option<usize>) -> Self {
+        let token_to_ids = vocab
+            .iter()
+            .enumerate()
+            .map(|(id, (piece, _))| (piece.to_owned(), id as u32))
+            .collect();
+        let max_piece_len = vocab
+            .iter()
+            .map(|(piece, _)| piece.chars().count())
+            .max()
+            .unwrap_or(0);
+        let min_score = vocab
+            .iter()
+            .map(|(_, score)| *score)
+            .fold(f64::INFINITY, f64::min);
+
+        Unigram {
+            vocab,
+            token_to_ids,
+            unk_id,
+            max_piece_len,
+            min_score,
+        }
+    }
+
+    /// Load a `Unigram` model from a unigram.json file, as written by `Model::save`
+    pub fn from_file(path: &str) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut buffer = String::new();
+        file.read_to_string(&mut buffer)?;
+        let json: Value = serde_json::from_str(&buffer)?;
+
+        let unk_id = json["unk_id"].as_u64().ok_or(Error::BadVocabulary)? as usize;
+        let vocab = json["vocab"]
+            .as_array()
+            .ok_or(Error::BadVocabulary)?
+            .iter()
+            .map(|piece| match (piece[0].as_str(), piece[1].as_f64()) {
+                (Some(piece), Some(score)) => Ok((piece.to_owned(), score)),
+                _ => Err(Error::BadVocabulary),
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        build_vocab(
+            vocab
+                .iter()
+                .enumerate()
+                .map(|(id, (piece, _))| (piece.to_owned(), id as u32)),
+        )?;
+
+        Self::new(vocab, unk_id)
+    }
+
+    /// The pieces of the vocabulary, along with their score
+    pub fn get_pieces(&self) -> &[(String, f64)] {
+        &self.vocab
+    }
+
+    /// Tokenize the given pre-tokens like `Model::tokenize`, also returning the score of each
+    /// piece: its log-probability, as used to find the most likely segmentation. Summing them
+    /// gives the log-probability of the segmentation of each word, which tells how confident the
+    /// model is about it. The unknown pieces get a score lower than any piece of the vocabulary.
+    pub fn tokenize_with_scores(
+        &self,
+        sentence: Vec<(String, Offsets)>,
+    ) -> Result<Vec<(Token, f64)>> {
+        let mut tokens = Vec::with_capacity(sentence.len());
+        for (word, initial_offsets) in sentence {
+            tokens.extend(self.viterbi(&word, None).into_iter().map(|node| {
+                let token = Token::new(
+                    node.id,
+                    self.vocab[node.id as usize].0.clone(),
+                    (initial_offsets.0 + node.start, initial_offsets.0 + node.end),
+                );
+                (token, node.score)
+            }));
+        }
+
+        Ok(tokens)
+    }
+
+    /// Find all the pieces of the vocabulary in the given word, sorted by start position.
+    /// The piece with the given `excluded` id is ignored.
+    fn nodes(&self, word: &str, excluded: Option<u32>) -> (Vec<Node>, usize) {
+        let starts = word
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(word.len()))
+            .collect::<Vec<_>>();
+        let len = starts.len() - 1;
+
+        let mut nodes = vec![];
+        for start in 0..len {
+            let mut has_single_char = false;
+            for end in start + 1..=std::cmp::min(len, start + self.max_piece_len) {
+                if let Some(id) = self.token_to_ids.get(&word[starts[start]..starts[end]]) {
+                    if Some(*id) == excluded {
+                        continue;
+                    }
+                    has_single_char |= end == start + 1;
+                    nodes.push(Node {
+                        id: *id,
+                        start,
+                        end,
+                        score: self.vocab[*id as usize].1,
+                    });
+                }
+            }
+
+            if !has_single_char {
+                if let Some(unk_id) = self.unk_id {
+                    nodes.push(Node {
+                        id: unk_id as u32,
+                        start,
+                        end: start + 1,
+                        score: self.min_score - UNK_PENALTY,
+                    });
+                }
+            }
+        }
+
+        (nodes, len)
+    }
+
+    /// Find the most likely sequence of pieces for the given word. The result is empty if the
+    /// word can't be tokenized.
+    pub(super) fn viterbi(&self, word: &str, excluded: Option<u32>) -> Vec<Node> {
+        let (nodes, len) = self.nodes(word, excluded);
+
+        // For each position, the best score to get there, and the node used to do so
+        let mut best: Vec<(f64, Option<usize>)> = vec![(f64::NEG_INFINITY, None); len + 1];
+        best[0].0 = 0.0;
+        for (i, node) in nodes.iter().enumerate() {
+            let score = best[node.start].0 + node.score;
+            if best[node.start].0 > f64::NEG_INFINITY && score > best[node.end].0 {
+                best[node.end] = (score, Some(i));
+            }
+        }
+
+        let mut path = vec![];
+        let mut pos = len;
+        while pos > 0 {
+            match best[pos].1 {
+                Some(i) => {
+                    path.push(nodes[i]);
+                    pos = nodes[i].start;
+                }
+                None => return vec![],
+            }
+        }
+        path.reverse();
+        path
+    }
+
+    /// Add the expected number of occurrences of each piece in the given word, seen `freq` times,
+    /// to `expected`. Returns the log-likelihood of the word.
+    pub(super) fn populate_marginals(&self, word: &str, freq: f64, expected: &mut [f64]) -> f64 {
+        let (nodes, len) = self.nodes(word, None);
+
+        let mut alpha = vec![f64::NEG_INFINITY; len + 1];
+        alpha[0] = 0.0;
+        for node in &nodes {
+            alpha[node.end] = log_sum_exp(alpha[node.end], alpha[node.start] + node.score);
+        }
+        let mut beta = vec![f64::NEG_INFINITY; len + 1];
+        beta[len] = 0.0;
+        for node in nodes.iter().rev() {
+            beta[node.start] = log_sum_exp(beta[node.start], beta[node.end] + node.score);
+        }
+
+        let z = alpha[len];
+        if z == f64::NEG_INFINITY {
+            return 0.0;
+        }
+        for node in &nodes {
+            let marginal = (alpha[node.start] + node.score + beta[node.end] - z).exp();
+            expected[node.id as usize] += freq * marginal;
+        }
+
+        freq * z
+    }
+}
+
+impl Model for Unigram {
+    fn get_vocab(&self) -> &HashMap<String, u32> {
+        &self.token_to_ids
+    }
+
+    fn get_vocab_size(&self) -> usize {
+        self.vocab.len()
+    }
+
+    fn tokenize(&self, sentence: Vec<(String, Offsets)>) -> Result<Vec<Token>> {
+        Ok(self
+            .tokenize_with_scores(sentence)?
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect())
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.token_to_ids.get(token).copied()
+    }
+
+    fn get_unk_id(&self) -> Option<u32> {
+        self.unk_id.map(|id| id as u32)
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.vocab.get(id as usize).map(|(piece, _)| piece.clone())
+    }
+
+    fn save(&self, folder: &Path, name: Option<&str>) -> Result<Vec<PathBuf>> {
+        let file_name = match name {
+            Some(name) => format!("{}-unigram.json", name),
+            None => "unigram.json".to_string(),
+        };
+
+        let path: PathBuf = [folder, Path::new(file_name.as_str())].iter().collect();
+        let mut file = File::create(&path)?;
+        let serialized = serde_json::to_string(&json!({
+            "unk_id": self.unk_id,
+            "vocab": self.vocab,
+        }))?;
+        file.write_all(serialized.as_bytes())?;
+
+        Ok(vec![path])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_model() -> Unigram {
+        Unigram::new(
+            vec![
+                ("<unk>".into(), 0.0),
+                ("a".into(), -2.0),
+                ("b".into(), -2.0),
+                ("c".into(), -2.0),
+                ("ab".into(), -3.0),
+                ("abc".into(), -5.0),
+                ("bc".into(), -1.0),
+            ],
+            0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn tokenize() {
+        let model = get_model();
+        let tokens = model
+            .tokenize(vec![("abc".into(), (0, 3)), ("axb".into(), (4, 7))])
+            .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(1, "a".into(), (0, 1)),
+                Token::new(6, "bc".into(), (1, 3)),
+                Token::new(1, "a".into(), (4, 5)),
+                Token::new(0, "<unk>".into(), (5, 6)),
+                Token::new(2, "b".into(), (6, 7)),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_with_scores() {
+        let model = get_model();
+        let tokens = model
+            .tokenize_with_scores(vec![("abc".into(), (0, 3)), ("axb".into(), (4, 7))])
+            .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::new(1, "a".into(), (0, 1)), -2.0),
+                (Token::new(6, "bc".into(), (1, 3)), -1.0),
+                (Token::new(1, "a".into(), (4, 5)), -2.0),
+                (Token::new(0, "<unk>".into(), (5, 6)), -5.0 - UNK_PENALTY),
+                (Token::new(2, "b".into(), (6, 7)), -2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn marginals() {
+        let model = get_model();
+        let mut expected = vec![0.0; model.get_vocab_size()];
+        model.populate_marginals("ab", 2.0, &mut expected);
+
+        // "ab" can be split as "a b" (-4.0) or "ab" (-3.0)
+        let p_ab = 1.0 / (1.0 + (-1.0f64).exp());
+        assert!((expected[4] - 2.0 * p_ab).abs() < 1e-9);
+        assert!((expected[1] - 2.0 * (1.0 - p_ab)).abs() < 1e-9);
+        assert!((expected[1] - expected[2]).abs() < 1e-9);
+        assert_eq!(expected[3], 0.0);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let model = get_model();
+        let folder = tempfile::tempdir().unwrap();
+        let files = model.save(folder.path(), Some("test")).unwrap();
+
+        let loaded = Unigram::from_file(files[0].to_str().unwrap()).unwrap();
+        assert_eq!(loaded.get_pieces(), model.get_pieces());
+        assert_eq!(loaded.unk_id, Some(0));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Unigram::new(vec![], 0).is_err());
+        assert!(Unigram::new(vec![("a".into(), 0.0)], 1).is_err());
+    }
+}
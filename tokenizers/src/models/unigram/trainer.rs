@@ -0,0 +1,546 @@
+use super::Unigram;
+use crate::tokenizer::{Model, Result, Trainer};
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Pieces with a lower expected frequency get removed during the M step
+const EXPECTED_FREQUENCY_THRESHOLD: f64 = 0.5;
+
+struct Config {
+    vocab_size: usize,
+    show_progress: bool,
+    special_tokens: Vec<String>,
+    initial_alphabet: HashSet<char>,
+    unk_token: String,
+    n_sub_iterations: usize,
+    shrinking_factor: f64,
+    max_piece_length: usize,
+    seed_size: usize,
+}
+
+/// A `UnigramTrainerBuilder` can be used to create a `UnigramTrainer` with a custom
+/// configuration.
+pub struct UnigramTrainerBuilder {
+    config: Config,
+}
+
+impl Default for UnigramTrainerBuilder {
+    fn default() -> Self {
+        Self {
+            config: Config {
+                vocab_size: 8000,
+                show_progress: true,
+                special_tokens: vec![],
+                initial_alphabet: HashSet::new(),
+                unk_token: String::from("<unk>"),
+                n_sub_iterations: 2,
+                shrinking_factor: 0.75,
+                max_piece_length: 16,
+                seed_size: 1_000_000,
+            },
+        }
+    }
+}
+
+impl UnigramTrainerBuilder {
+    /// Constructs a new `UnigramTrainerBuilder`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the vocabulary size
+    pub fn vocab_size(mut self, size: usize) -> Self {
+        self.config.vocab_size = size;
+        self
+    }
+
+    /// Set whether to show progress
+    pub fn show_progress(mut self, show: bool) -> Self {
+        self.config.show_progress = show;
+        self
+    }
+
+    /// Set the special tokens
+    pub fn special_tokens(mut self, tokens: Vec<String>) -> Self {
+        self.config.special_tokens = tokens;
+        self
+    }
+
+    /// Set the initial alphabet
+    pub fn initial_alphabet(mut self, alphabet: HashSet<char>) -> Self {
+        self.config.initial_alphabet = alphabet;
+        self
+    }
+
+    /// Set the token used for the unknown chars. It is added to the special tokens if needed.
+    pub fn unk_token(mut self, token: String) -> Self {
+        self.config.unk_token = token;
+        self
+    }
+
+    /// Set the number of EM iterations between each pruning step
+    pub fn n_sub_iterations(mut self, n: usize) -> Self {
+        self.config.n_sub_iterations = n;
+        self
+    }
+
+    /// Set the proportion of pieces to keep at each pruning step
+    pub fn shrinking_factor(mut self, factor: f64) -> Self {
+        self.config.shrinking_factor = factor;
+        self
+    }
+
+    /// Set the maximum length of a piece, in chars
+    pub fn max_piece_length(mut self, length: usize) -> Self {
+        self.config.max_piece_length = length;
+        self
+    }
+
+    /// Set the number of pieces used to start the training
+    pub fn seed_size(mut self, size: usize) -> Self {
+        self.config.seed_size = size;
+        self
+    }
+
+    /// Constructs the final UnigramTrainer
+    pub fn build(self) -> UnigramTrainer {
+        UnigramTrainer {
+            vocab_size: self.config.vocab_size,
+            show_progress: self.config.show_progress,
+            special_tokens: self.config.special_tokens,
+            initial_alphabet: self.config.initial_alphabet,
+            unk_token: self.config.unk_token,
+            n_sub_iterations: self.config.n_sub_iterations,
+            shrinking_factor: self.config.shrinking_factor,
+            max_piece_length: self.config.max_piece_length,
+            seed_size: self.config.seed_size,
+        }
+    }
+}
+
+/// In charge of training a `Unigram` model from a mapping of words to word counts, using the
+/// SentencePiece algorithm: we start with a large set of pieces, and alternate between some EM
+/// iterations to estimate their probabilities, and pruning the ones whose removal reduces the
+/// likelihood of the corpus the least, until we reach the expected vocabulary size.
+pub struct UnigramTrainer {
+    /// The target vocabulary size
+    vocab_size: usize,
+    /// Whether to show progress while training
+    show_progress: bool,
+    /// A list of special tokens that the model should know of
+    special_tokens: Vec<String>,
+    /// The initial alphabet we want absolutely to include
+    initial_alphabet: HashSet<char>,
+    /// The token used for the unknown chars
+    unk_token: String,
+    /// The number of EM iterations between each pruning step
+    n_sub_iterations: usize,
+    /// The proportion of pieces kept at each pruning step
+    shrinking_factor: f64,
+    /// The maximum length of a piece, in chars
+    max_piece_length: usize,
+    /// The number of pieces used to start the training
+    seed_size: usize,
+}
+
+impl Default for UnigramTrainer {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl UnigramTrainer {
+    pub fn builder() -> UnigramTrainerBuilder {
+        UnigramTrainerBuilder::new()
+    }
+
+    /// Setup a progress bar if asked to show progress
+    fn setup_progress(&self) -> Option<ProgressBar> {
+        if self.show_progress {
+            let p = ProgressBar::new(0);
+            p.set_style(
+                ProgressStyle::default_bar()
+                    .template("[{elapsed_precise}] {msg:<40!} {wide_bar} {pos:<9!}/{len:>9!}"),
+            );
+            Some(p)
+        } else {
+            None
+        }
+    }
+
+    /// The special tokens, including the unk token
+    fn get_special_tokens(&self) -> Vec<String> {
+        let mut special_tokens = self.special_tokens.clone();
+        if !special_tokens.contains(&self.unk_token) {
+            special_tokens.push(self.unk_token.clone());
+        }
+        special_tokens
+    }
+
+    /// Build the seed pieces: all the chars, and the most frequent substrings of the words
+    fn make_seed_pieces(
+        &self,
+        words: &[(String, u32)],
+        required_chars: &HashSet<char>,
+    ) -> Vec<(String, f64)> {
+        let mut substrings: HashMap<&str, u64> = HashMap::new();
+        let mut chars: HashMap<char, u64> = required_chars.iter().map(|c| (*c, 1)).collect();
+        for (word, count) in words {
+            let starts = word
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(word.len()))
+                .collect::<Vec<_>>();
+            for c in word.chars() {
+                *chars.entry(c).or_insert(0) += *count as u64;
+            }
+            for start in 0..starts.len() - 1 {
+                for end in start + 2..std::cmp::min(starts.len(), start + self.max_piece_length + 1)
+                {
+                    *substrings
+                        .entry(&word[starts[start]..starts[end]])
+                        .or_insert(0) += *count as u64;
+                }
+            }
+        }
+
+        // Keep the substrings that cover the most chars
+        let mut substrings = substrings
+            .into_iter()
+            .filter(|(_, freq)| *freq > 1)
+            .map(|(piece, freq)| (piece, freq * piece.chars().count() as u64))
+            .collect::<Vec<_>>();
+        substrings.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        substrings.truncate(self.seed_size.saturating_sub(chars.len()));
+
+        let mut chars = chars.into_iter().collect::<Vec<_>>();
+        chars.sort_unstable();
+        let pieces = chars
+            .into_iter()
+            .map(|(c, freq)| (c.to_string(), freq))
+            .chain(substrings.into_iter().map(|(s, freq)| (s.to_owned(), freq)))
+            .collect::<Vec<_>>();
+
+        let total = pieces
+            .iter()
+            .map(|(_, freq)| *freq as f64)
+            .sum::<f64>()
+            .ln();
+        pieces
+            .into_iter()
+            .map(|(piece, freq)| (piece, (freq as f64).ln() - total))
+            .collect()
+    }
+
+    /// Compute the expected frequency of each piece over all the words
+    fn run_e_step(&self, pieces: &[(String, f64)], words: &[(String, u32)]) -> Vec<f64> {
+        let model = Unigram::from_pieces(pieces.to_vec(), None);
+        words
+            .par_iter()
+            .fold(
+                || vec![0.0; pieces.len()],
+                |mut expected, (word, count)| {
+                    model.populate_marginals(word, *count as f64, &mut expected);
+                    expected
+                },
+            )
+            .reduce(
+                || vec![0.0; pieces.len()],
+                |mut a, b| {
+                    a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                    a
+                },
+            )
+    }
+
+    /// Re-estimate the scores from the expected frequencies, removing the rare pieces
+    fn run_m_step(
+        &self,
+        pieces: &[(String, f64)],
+        expected: &[f64],
+        required_chars: &HashSet<char>,
+    ) -> Vec<(String, f64)> {
+        let kept = pieces
+            .iter()
+            .zip(expected)
+            .filter_map(|((piece, _), freq)| {
+                if *freq >= EXPECTED_FREQUENCY_THRESHOLD {
+                    Some((piece.to_owned(), *freq))
+                } else if is_required(piece, required_chars) {
+                    Some((piece.to_owned(), EXPECTED_FREQUENCY_THRESHOLD))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Like SentencePiece, we use the digamma function rather than the log, which acts as a
+        // sparse prior and favors the most frequent pieces
+        let total = digamma(kept.iter().map(|(_, freq)| freq).sum::<f64>());
+        kept.into_iter()
+            .map(|(piece, freq)| (piece, digamma(freq) - total))
+            .collect()
+    }
+
+    /// Remove the pieces whose removal reduces the likelihood of the corpus the least, keeping
+    /// at least `desired_size` pieces.
+    fn prune_pieces(
+        &self,
+        pieces: &[(String, f64)],
+        words: &[(String, u32)],
+        desired_size: usize,
+        required_chars: &HashSet<char>,
+    ) -> Vec<(String, f64)> {
+        let model = Unigram::from_pieces(pieces.to_vec(), None);
+
+        // The frequency of each piece in the best tokenization of the words
+        let mut freq = vec![0.0; pieces.len()];
+        let paths = words
+            .par_iter()
+            .map(|(word, _)| model.viterbi(word, None))
+            .collect::<Vec<_>>();
+        for (path, (_, count)) in paths.into_iter().zip(words) {
+            for node in path {
+                freq[node.id as usize] += *count as f64;
+            }
+        }
+        let sum = freq.iter().sum::<f64>();
+        let log_sum = sum.ln();
+
+        let mut always_keep = vec![];
+        let mut candidates = vec![];
+        for (id, (piece, _)) in pieces.iter().enumerate() {
+            if is_required(piece, required_chars) {
+                always_keep.push(id);
+                continue;
+            }
+            if freq[id] == 0.0 {
+                continue;
+            }
+
+            // How this piece would be tokenized without it
+            let alternatives = model.viterbi(piece, Some(id as u32));
+            if alternatives.is_empty() {
+                always_keep.push(id);
+                continue;
+            }
+
+            let log_prob = freq[id].ln() - log_sum;
+            let log_sum_alt = (sum + freq[id] * (alternatives.len() - 1) as f64).ln();
+            let log_prob_alt = alternatives
+                .iter()
+                .map(|node| (freq[node.id as usize] + freq[id]).ln() - log_sum_alt)
+                .sum::<f64>();
+            let loss = freq[id] / sum * (log_prob - log_prob_alt);
+            candidates.push((id, loss));
+        }
+
+        let pruned_size = std::cmp::max(
+            desired_size,
+            (self.shrinking_factor * pieces.len() as f64) as usize,
+        );
+        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        let kept = pruned_size.saturating_sub(always_keep.len());
+        let mut kept = always_keep
+            .into_iter()
+            .chain(candidates.into_iter().take(kept).map(|(id, _)| id))
+            .collect::<Vec<_>>();
+        kept.sort_unstable();
+
+        kept.into_iter().map(|id| pieces[id].clone()).collect()
+    }
+
+    /// Keep the best `size` pieces (and all the required chars), sorted by score
+    fn finalize(
+        &self,
+        pieces: Vec<(String, f64)>,
+        size: usize,
+        required_chars: &HashSet<char>,
+    ) -> Vec<(String, f64)> {
+        let (mut final_pieces, mut others): (Vec<_>, Vec<_>) = pieces
+            .into_iter()
+            .partition(|(piece, _)| is_required(piece, required_chars));
+        others.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(&b.0)));
+        final_pieces.extend(
+            others
+                .into_iter()
+                .take(size.saturating_sub(final_pieces.len())),
+        );
+        final_pieces.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(&b.0)));
+        final_pieces
+    }
+
+    pub fn train(&self, word_counts: HashMap<String, u32>) -> Result<(Unigram, Vec<String>)> {
+        let progress = self.setup_progress();
+
+        let mut words = word_counts.into_iter().collect::<Vec<_>>();
+        words.sort_unstable();
+        let special_tokens = self.get_special_tokens();
+        let desired_size = self.vocab_size.saturating_sub(special_tokens.len());
+
+        //
+        // 1. Build the seed pieces, always including every char
+        //
+        let mut required_chars = self.initial_alphabet.clone();
+        words
+            .iter()
+            .for_each(|(word, _)| required_chars.extend(word.chars()));
+        let mut pieces = self.make_seed_pieces(&words, &required_chars);
+
+        //
+        // 2. Alternate between EM and pruning until we are close to the desired size
+        //
+        if let Some(p) = &progress {
+            p.set_message("Prune pieces");
+            p.set_length(pieces.len().saturating_sub(desired_size) as u64);
+        }
+        let seed_len = pieces.len();
+        loop {
+            for _ in 0..self.n_sub_iterations {
+                let expected = self.run_e_step(&pieces, &words);
+                pieces = self.run_m_step(&pieces, &expected, &required_chars);
+            }
+
+            if pieces.len() <= desired_size * 11 / 10 {
+                break;
+            }
+            let pruned = self.prune_pieces(&pieces, &words, desired_size, &required_chars);
+            if pruned.len() == pieces.len() {
+                break;
+            }
+            pieces = pruned;
+
+            if let Some(p) = &progress {
+                p.set_position(seed_len.saturating_sub(pieces.len()) as u64);
+            }
+        }
+        if let Some(p) = &progress {
+            p.finish();
+            println!();
+        }
+
+        //
+        // 3. Keep the best pieces, after the special tokens
+        //
+        let pieces = self.finalize(pieces, desired_size, &required_chars);
+        let vocab = special_tokens
+            .iter()
+            .map(|token| (token.to_owned(), 0.0))
+            .chain(
+                pieces
+                    .into_iter()
+                    .filter(|(piece, _)| !special_tokens.contains(piece)),
+            )
+            .collect::<Vec<_>>();
+        let unk_id = special_tokens
+            .iter()
+            .position(|token| *token == self.unk_token)
+            .unwrap();
+
+        Ok((Unigram::new(vocab, unk_id)?, special_tokens))
+    }
+}
+
+/// An approximation of the digamma function
+fn digamma(mut x: f64) -> f64 {
+    let mut result = 0.0;
+    while x < 7.0 {
+        result -= 1.0 / x;
+        x += 1.0;
+    }
+    x -= 0.5;
+    let xx = 1.0 / x;
+    let xx2 = xx * xx;
+    let xx4 = xx2 * xx2;
+    result + x.ln() + xx2 / 24.0 - 7.0 * xx4 / 960.0 + 31.0 * xx4 * xx2 / 8064.0
+        - 127.0 * xx4 * xx4 / 30720.0
+}
+
+/// Whether the given piece is a single char that must be part of the vocabulary
+fn is_required(piece: &str, required_chars: &HashSet<char>) -> bool {
+    let mut chars = piece.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => required_chars.contains(&c),
+        _ => false,
+    }
+}
+
+impl Trainer for UnigramTrainer {
+    /// Train a Unigram model
+    fn train(
+        &self,
+        word_counts: HashMap<String, u32>,
+    ) -> Result<(Box<dyn Model + Sync>, Vec<String>)> {
+        let (unigram, tokens) = self.train(word_counts)?;
+        Ok((Box::new(unigram), tokens))
+    }
+
+    /// Process a bunch of tokens, counting them
+    fn process_tokens(&self, words: &mut HashMap<String, u32>, tokens: Vec<String>) {
+        for token in tokens {
+            words.entry(token).and_modify(|c| *c += 1).or_insert(1);
+        }
+    }
+
+    /// Whether we should show progress
+    fn should_show_progress(&self) -> bool {
+        self.show_progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digamma_approximation() {
+        assert!((digamma(1.0) + 0.577_215_664_9).abs() < 1e-9);
+        assert!((digamma(10.0) - 2.251_752_589_1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn train() {
+        let word_counts: HashMap<String, u32> = [
+            ("hug".into(), 10),
+            ("pug".into(), 5),
+            ("pun".into(), 12),
+            ("bun".into(), 4),
+            ("hugs".into(), 5),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let trainer = UnigramTrainer::builder()
+            .show_progress(false)
+            .vocab_size(12)
+            .special_tokens(vec!["<pad>".into()])
+            .initial_alphabet(['z'].iter().copied().collect())
+            .build();
+        let (model, special_tokens) = trainer.train(word_counts).unwrap();
+
+        assert_eq!(
+            special_tokens,
+            vec!["<pad>".to_string(), "<unk>".to_string()]
+        );
+        let pieces = model.get_pieces();
+        assert_eq!(pieces.len(), 12);
+        assert_eq!(pieces[0], ("<pad>".into(), 0.0));
+        assert_eq!(pieces[1], ("<unk>".into(), 0.0));
+        // All the chars are part of the vocabulary
+        for c in &["b", "g", "h", "n", "p", "s", "u", "z"] {
+            assert!(model.token_to_id(c).is_some());
+        }
+        // And the pieces are sorted by score
+        assert!(pieces[2..].windows(2).all(|w| w[0].1 >= w[1].1));
+
+        // The most frequent words get their own piece
+        let tokens = model
+            .tokenize(vec![("pun".into(), (0, 3)), ("hugs".into(), (4, 8))])
+            .unwrap()
+            .into_iter()
+            .map(|t| t.value)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec!["pun", "hug", "s"]);
+    }
+}
@@ -1,5 +1,6 @@
-use super::OrderedVocabIter;
-use crate::tokenizer::{Model, Result, Token};
+use super::{build_vocab, check_new_entries, OrderedVocabIter, VocabEntries};
+use crate::tokenizer::{Model, Offsets, Result, Token};
+use rayon::prelude::*;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fmt;
@@ -82,6 +83,7 @@ impl WordLevelBuilder {
     }
 }
 
+#[derive(Clone)]
 pub struct WordLevel {
     vocab: HashMap<String, u32>,
     vocab_r: HashMap<u32, String>,
@@ -99,22 +101,21 @@ impl WordLevel {
         let vocab_file = File::open(vocab_path)?;
         let mut vocab_file = BufReader::new(vocab_file);
         let mut buffer = String::new();
-        let mut vocab = HashMap::new();
 
         vocab_file.read_to_string(&mut buffer)?;
-        let json: Value = serde_json::from_str(&buffer)?;
-
-        match json {
-            Value::Object(m) => {
-                for (token, id) in m {
-                    if let Value::Number(id) = id {
-                        let id = id.as_u64().ok_or(Error::BadVocabulary)? as u32;
-                        vocab.insert(token, id);
-                    }
-                }
-            }
-            _ => return Err(Box::new(Error::BadVocabulary)),
+        let entries = match serde_json::from_str::<VocabEntries>(&buffer) {
+            Ok(entries) => entries.0,
+            Err(e) if e.is_data() => return Err(Box::new(Error::BadVocabulary)),
+            Err(e) => return Err(Box::new(e)),
         };
+        let mut ids = Vec::with_capacity(entries.len());
+        for (token, id) in entries {
+            if let Value::Number(id) = id {
+                let id = id.as_u64().ok_or(Error::BadVocabulary)? as u32;
+                ids.push((token, id));
+            }
+        }
+        let vocab = build_vocab(ids)?;
 
         Ok(Self::builder().vocab(vocab).unk_token(unk_token).build())
     }
@@ -131,6 +132,10 @@ impl Default for WordLevel {
 }
 
 impl Model for WordLevel {
+    fn get_vocab(&self) -> &HashMap<String, u32> {
+        &self.vocab
+    }
+
     fn tokenize(&self, tokens: Vec<(String, (usize, usize))>) -> Result<Vec<Token>> {
         let mut output_tokens = vec![];
 
@@ -155,6 +160,26 @@ impl Model for WordLevel {
         self.vocab.get(token).copied()
     }
 
+    fn get_unk_id(&self) -> Option<u32> {
+        self.vocab.get(&self.unk_token).copied()
+    }
+
+    fn tokenize_batch(&self, batches: Vec<Vec<(String, Offsets)>>) -> Result<Vec<Vec<Token>>> {
+        batches
+            .into_par_iter()
+            .map(|batch| self.tokenize(batch))
+            .collect()
+    }
+
+    fn extend_vocab(&mut self, tokens: Vec<(String, u32)>) -> Result<()> {
+        check_new_entries(&self.vocab, &self.vocab_r, &tokens)?;
+        for (token, id) in tokens {
+            self.vocab.insert(token.clone(), id);
+            self.vocab_r.insert(id, token);
+        }
+        Ok(())
+    }
+
     fn id_to_token(&self, id: u32) -> Option<String> {
         self.vocab_r.get(&id).cloned()
     }
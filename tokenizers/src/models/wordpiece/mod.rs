@@ -2,7 +2,9 @@
 //! model.
 
 use crate::models::bpe::BPE;
+use crate::models::{build_vocab, check_new_entries};
 use crate::tokenizer::{Model, Offsets, Result, Token};
+use rayon::prelude::*;
 use std::{
     collections::HashMap,
     fmt,
@@ -124,6 +126,7 @@ impl WordPieceBuilder {
 /// A
 /// [WordPiece](https://static.googleusercontent.com/media/research.google.com/en//pubs/archive/37842.pdf)
 /// model.
+#[derive(Clone)]
 pub struct WordPiece {
     vocab: Vocab,
     vocab_r: VocabR,
@@ -155,13 +158,13 @@ impl WordPiece {
         let file = File::open(vocab)?;
         let file = BufReader::new(file);
 
-        let mut vocab = HashMap::new();
+        let mut entries = vec![];
         for (index, line) in file.lines().enumerate() {
             let line = line?;
-            vocab.insert(line.trim_end().to_owned(), index as u32);
+            entries.push((line.trim_end().to_owned(), index as u32));
         }
 
-        Ok(vocab)
+        Ok(build_vocab(entries)?)
     }
 
     /// Initialize a `WordPiece` model from a vocab mapping file.
@@ -186,6 +189,10 @@ impl WordPiece {
 }
 
 impl Model for WordPiece {
+    fn get_vocab(&self) -> &HashMap<String, u32> {
+        &self.vocab
+    }
+
     fn get_vocab_size(&self) -> usize {
         self.vocab.len()
     }
@@ -262,6 +269,30 @@ impl Model for WordPiece {
         self.vocab.get(token).copied()
     }
 
+    fn get_unk_id(&self) -> Option<u32> {
+        self.vocab.get(&self.unk_token).copied()
+    }
+
+    fn extend_vocab(&mut self, tokens: Vec<(String, u32)>) -> Result<()> {
+        check_new_entries(&self.vocab, &self.vocab_r, &tokens)?;
+        for (token, id) in tokens {
+            self.vocab.insert(token.clone(), id);
+            self.vocab_r.insert(id, token);
+        }
+        Ok(())
+    }
+
+    fn get_continuing_subword_prefix(&self) -> Option<&str> {
+        Some(&self.continuing_subword_prefix)
+    }
+
+    fn tokenize_batch(&self, batches: Vec<Vec<(String, Offsets)>>) -> Result<Vec<Vec<Token>>> {
+        batches
+            .into_par_iter()
+            .map(|batch| self.tokenize(batch))
+            .collect()
+    }
+
     fn id_to_token(&self, id: u32) -> Option<String> {
         self.vocab_r.get(&id).cloned()
     }
@@ -298,4 +329,43 @@ mod tests {
     fn test_error_display() {
         assert!(format!("{}", Error::MissingUnkToken).contains("Missing [UNK] token"));
     }
+
+    #[test]
+    fn test_read_files_duplicated_vocab() {
+        let mut vocab_file = tempfile::NamedTempFile::new().unwrap();
+        vocab_file
+            .write_all(b"[UNK]\nhello\n##lo\nhello\n")
+            .unwrap();
+
+        let err = WordPiece::read_files(vocab_file.path().to_str().unwrap()).unwrap_err();
+        let err = err.downcast_ref::<crate::models::InvalidVocab>().unwrap();
+        assert_eq!(err.duplicated_tokens, vec![String::from("hello")]);
+        assert!(err.duplicated_ids.is_empty());
+    }
+
+    #[test]
+    fn test_extend_vocab_collisions() {
+        let vocab: Vocab = [("[UNK]", 0), ("hello", 1)]
+            .iter()
+            .map(|(t, id)| (t.to_string(), *id))
+            .collect();
+        let mut wp = WordPiece::builder().vocab(vocab).build().unwrap();
+
+        // An existing token, an existing id, or a collision within the new tokens
+        for tokens in &[
+            vec![("hello".to_owned(), 2)],
+            vec![("world".to_owned(), 1)],
+            vec![("world".to_owned(), 2), ("there".to_owned(), 2)],
+        ] {
+            assert!(wp.extend_vocab(tokens.clone()).is_err());
+            assert_eq!(wp.get_vocab_size(), 2);
+            assert_eq!(wp.token_to_id("hello"), Some(1));
+            assert_eq!(wp.id_to_token(1), Some("hello".into()));
+            assert_eq!(wp.id_to_token(2), None);
+        }
+
+        wp.extend_vocab(vec![("world".to_owned(), 2)]).unwrap();
+        assert_eq!(wp.token_to_id("world"), Some(2));
+        assert_eq!(wp.id_to_token(2), Some("world".into()));
+    }
 }
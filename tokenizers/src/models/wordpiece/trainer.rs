@@ -65,12 +65,19 @@ impl WordPieceTrainerBuilder {
         self
     }
 
-    /// Set the end_of_word_suffix
+    /// Set the end_of_word_suffix. `WordPiece` has no end-of-word suffix, so the trained model
+    /// only matches the words tokenized with it if they get the suffix some other way.
     pub fn end_of_word_suffix(mut self, suffix: String) -> Self {
         self.bpe_trainer_builder = self.bpe_trainer_builder.end_of_word_suffix(suffix);
         self
     }
 
+    /// Set the seed used to break the ties between pairs with the same count
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.bpe_trainer_builder = self.bpe_trainer_builder.seed(seed);
+        self
+    }
+
     /// Constructs the final BpeTrainer
     pub fn build(self) -> WordPieceTrainer {
         let bpe_trainer = self.bpe_trainer_builder.build();
@@ -78,7 +85,8 @@ impl WordPieceTrainerBuilder {
     }
 }
 
-/// Trains a `WordPiece` model.
+/// Trains a `WordPiece` model. This uses the `BpeTrainer` to select the most frequent pairs,
+/// with the `##` continuing subword prefix by default.
 #[derive(Default)]
 pub struct WordPieceTrainer {
     bpe_trainer: BpeTrainer,
@@ -89,6 +97,16 @@ impl WordPieceTrainer {
         WordPieceTrainerBuilder::default()
     }
 
+    /// The special tokens added at the beginning of the trained vocabulary
+    pub fn get_special_tokens(&self) -> &[String] {
+        self.bpe_trainer.get_special_tokens()
+    }
+
+    /// The prefix added to the subwords that don't start a word, also used by the trained model
+    pub fn get_continuing_subword_prefix(&self) -> Option<&str> {
+        self.bpe_trainer.get_continuing_subword_prefix()
+    }
+
     pub fn train(&self, word_counts: HashMap<String, u32>) -> Result<(WordPiece, Vec<String>)> {
         let (bpe, tokens) = self.bpe_trainer.train(word_counts)?;
         Ok((WordPiece::from_bpe(&bpe), tokens))
@@ -112,3 +130,59 @@ impl Trainer for WordPieceTrainer {
         self.bpe_trainer.should_show_progress()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn train() {
+        let word_counts: HashMap<String, u32> = [("hugs".into(), 3), ("hug".into(), 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let trainer = WordPieceTrainer::builder()
+            .show_progress(false)
+            .special_tokens(vec!["[UNK]".into()])
+            .min_frequency(3)
+            .build();
+        let (model, special_tokens) = trainer.train(word_counts).unwrap();
+
+        assert_eq!(special_tokens, vec!["[UNK]".to_string()]);
+        let mut vocab = model.get_vocab().iter().collect::<Vec<_>>();
+        vocab.sort_by_key(|(_, id)| **id);
+        assert_eq!(
+            vocab
+                .into_iter()
+                .map(|(t, _)| t.as_str())
+                .collect::<Vec<_>>(),
+            vec!["[UNK]", "g", "h", "s", "u", "##u", "##g", "##s", "hu", "hug", "hugs"]
+        );
+    }
+
+    #[test]
+    fn train_custom_prefix() {
+        let word_counts: HashMap<String, u32> = [("hugs".into(), 3), ("hug".into(), 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let trainer = WordPieceTrainer::builder()
+            .show_progress(false)
+            .special_tokens(vec!["[UNK]".into()])
+            .continuing_subword_prefix("@@".into())
+            .vocab_size(9)
+            .build();
+        assert_eq!(trainer.get_continuing_subword_prefix(), Some("@@"));
+        assert_eq!(trainer.get_special_tokens(), &["[UNK]".to_string()]);
+        let (model, _) = trainer.train(word_counts).unwrap();
+
+        assert_eq!(model.get_continuing_subword_prefix(), Some("@@"));
+        let tokens = model
+            .tokenize(vec![("hugs".into(), (0, 4))])
+            .unwrap()
+            .into_iter()
+            .map(|t| t.value)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec!["hu", "@@g", "@@s"]);
+    }
+}
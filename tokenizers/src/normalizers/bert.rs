@@ -33,7 +33,7 @@ fn is_control(c: char) -> bool {
 /// as is Japanese Hiragana and Katakana. Those alphabets are used to write
 /// space-separated words, so they are not treated specially and handled
 /// like for all of the other languages.
-fn is_chinese_char(c: char) -> bool {
+pub(crate) fn is_chinese_char(c: char) -> bool {
     match c as usize {
         0x4E00..=0x9FFF => true,
         0x3400..=0x4DBF => true,
@@ -47,6 +47,7 @@ fn is_chinese_char(c: char) -> bool {
     }
 }
 
+#[derive(Clone)]
 pub struct BertNormalizer {
     /// Whether to do the bert basic cleaning:
     ///   1. Remove any control characters
@@ -0,0 +1,133 @@
+use crate::tokenizer::{NormalizedString, Normalizer, Result};
+use std::collections::HashMap;
+
+/// Replaces some chars with others, in a single pass. Each char can be replaced by any string:
+/// the alignments are kept when a char gets expanded, and an empty string removes the char.
+/// This is a lot faster than a regex when only a handful of chars need to be fixed.
+#[derive(Clone)]
+pub struct Map {
+    replacements: HashMap<char, String>,
+}
+
+impl Map {
+    pub fn new(replacements: HashMap<char, String>) -> Self {
+        Map { replacements }
+    }
+
+    /// Create a `Map` replacing each char by a single other char
+    pub fn from_chars(replacements: HashMap<char, char>) -> Self {
+        Self::new(
+            replacements
+                .into_iter()
+                .map(|(c, r)| (c, r.to_string()))
+                .collect(),
+        )
+    }
+
+    /// Replaces the typographic quotes and apostrophes by the ASCII ones
+    pub fn quotes() -> Self {
+        Self::from_chars(
+            [
+                ('\u{2018}', '\''),
+                ('\u{2019}', '\''),
+                ('\u{201A}', '\''),
+                ('\u{201B}', '\''),
+                ('\u{2032}', '\''),
+                ('\u{201C}', '"'),
+                ('\u{201D}', '"'),
+                ('\u{201E}', '"'),
+                ('\u{201F}', '"'),
+                ('\u{2033}', '"'),
+                ('\u{00AB}', '"'),
+                ('\u{00BB}', '"'),
+            ]
+            .iter()
+            .copied()
+            .collect(),
+        )
+    }
+
+    /// Replaces the unicode spaces (like the non-breaking space) by a regular space
+    pub fn spaces() -> Self {
+        Self::from_chars(
+            ['\u{00A0}', '\u{202F}', '\u{205F}', '\u{3000}']
+                .iter()
+                .copied()
+                .chain('\u{2000}'..='\u{200A}')
+                .map(|c| (c, ' '))
+                .collect(),
+        )
+    }
+
+    /// Replaces the typographic dashes by an hyphen, and the ellipsis by three dots
+    pub fn punctuation() -> Self {
+        Self::new(
+            [
+                ('\u{2012}', "-"),
+                ('\u{2013}', "-"),
+                ('\u{2014}', "-"),
+                ('\u{2015}', "-"),
+                ('\u{2026}', "..."),
+            ]
+            .iter()
+            .map(|(c, r)| (*c, r.to_string()))
+            .collect(),
+        )
+    }
+}
+
+impl Normalizer for Map {
+    fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
+        // Either the char itself, or its replacement, without allocating
+        normalized.transform_chars(|c| match self.replacements.get(&c) {
+            Some(replacement) => None.into_iter().chain(replacement.chars()),
+            None => Some(c).into_iter().chain("".chars()),
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Range;
+
+    #[test]
+    fn presets() {
+        let mut n =
+            NormalizedString::from("\u{201C}Wait\u{2026}\u{201D} \u{2014} it\u{2019}s\u{00A0}late");
+        Map::quotes().normalize(&mut n).unwrap();
+        Map::spaces().normalize(&mut n).unwrap();
+        Map::punctuation().normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "\"Wait...\" - it's late");
+        assert_eq!(
+            n.get_range_original(Range::Normalized(5..8)),
+            Some("\u{2026}")
+        );
+        assert_eq!(
+            n.get_range_original(Range::Normalized(8..9)),
+            Some("\u{201D}")
+        );
+        assert!(n.validate().is_ok());
+    }
+
+    #[test]
+    fn removal_and_expansion() {
+        let map = Map::new(
+            [('-', ""), ('&', " and ")]
+                .iter()
+                .map(|(c, r)| (*c, r.to_string()))
+                .collect(),
+        );
+        let mut n = NormalizedString::from("-salt&pep-per");
+        map.normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "salt and pepper");
+        assert_eq!(
+            n.get_range_original(Range::Normalized(9..15)),
+            Some("pep-per")
+        );
+        assert_eq!(n.removed_ranges(), vec![0..1, 9..10]);
+        assert!(n.validate().is_ok());
+    }
+}
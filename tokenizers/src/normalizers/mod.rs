@@ -1,4 +1,6 @@
 pub mod bert;
+pub mod map;
 pub mod strip;
 pub mod unicode;
 pub mod utils;
+pub mod width;
@@ -1,5 +1,6 @@
 use crate::tokenizer::{NormalizedString, Normalizer, Result};
 
+#[derive(Clone)]
 pub struct Strip {
     strip_left: bool,
     strip_right: bool,
@@ -1,5 +1,6 @@
 use crate::tokenizer::{NormalizedString, Normalizer, Result};
 
+#[derive(Clone)]
 pub struct NFD;
 impl Normalizer for NFD {
     fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
@@ -8,6 +9,7 @@ impl Normalizer for NFD {
     }
 }
 
+#[derive(Clone)]
 pub struct NFKD;
 impl Normalizer for NFKD {
     fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
@@ -16,6 +18,7 @@ impl Normalizer for NFKD {
     }
 }
 
+#[derive(Clone)]
 pub struct NFC;
 impl Normalizer for NFC {
     fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
@@ -24,6 +27,7 @@ impl Normalizer for NFC {
     }
 }
 
+#[derive(Clone)]
 pub struct NFKC;
 impl Normalizer for NFKC {
     fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
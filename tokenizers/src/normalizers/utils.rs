@@ -2,6 +2,8 @@ use crate::tokenizer::{NormalizedString, Normalizer, Result};
 
 /// Allows concatenating multiple other Normalizer as a Sequence.
 /// All the normalizers run in sequence in the given order against the same NormalizedString.
+/// In debug builds, the alignments of the NormalizedString are validated after each of them.
+#[derive(Clone)]
 pub struct Sequence {
     normalizers: Vec<Box<dyn Normalizer + Sync>>,
 }
@@ -16,12 +18,16 @@ impl Normalizer for Sequence {
     fn normalize(&self, mut normalized: &mut NormalizedString) -> Result<()> {
         for normalizer in &self.normalizers {
             normalizer.normalize(&mut normalized)?;
+            if cfg!(debug_assertions) {
+                normalized.validate()?;
+            }
         }
         Ok(())
     }
 }
 
 /// Lowercases the input
+#[derive(Clone)]
 pub struct Lowercase;
 impl Normalizer for Lowercase {
     fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
@@ -0,0 +1,126 @@
+use crate::tokenizer::{NormalizedString, Normalizer, Result};
+
+/// The full-width katakana corresponding to each half-width one, from U+FF61 to U+FF9F
+const KATAKANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+
+/// Half-width voiced sound mark (dakuten)
+const VOICED_MARK: char = '\u{FF9E}';
+/// Half-width semi-voiced sound mark (handakuten)
+const SEMI_VOICED_MARK: char = '\u{FF9F}';
+
+/// Converts a full-width ASCII character (or the ideographic space) to its half-width version
+fn to_half_width(c: char) -> char {
+    match c as u32 {
+        0xFF01..=0xFF5E => std::char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        0x3000 => ' ',
+        _ => c,
+    }
+}
+
+/// Converts a half-width katakana to its full-width version
+fn to_full_width_katakana(c: char) -> char {
+    match c as u32 {
+        0xFF61..=0xFF9F => KATAKANA
+            .chars()
+            .nth((c as u32 - 0xFF61) as usize)
+            .unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Combines a full-width katakana with the given (semi-)voiced sound mark, if they can be combined
+fn combine_katakana(c: char, mark: char) -> Option<char> {
+    let offset = match mark {
+        VOICED_MARK if c == 'ウ' => return Some('ヴ'),
+        VOICED_MARK if "カキクケコサシスセソタチツテトハヒフヘホ".contains(c) => {
+            1
+        }
+        SEMI_VOICED_MARK if "ハヒフヘホ".contains(c) => 2,
+        _ => return None,
+    };
+    std::char::from_u32(c as u32 + offset)
+}
+
+/// Converts the full-width ASCII characters (like `ＡＢＣ１２３`) and the ideographic space to
+/// their half-width equivalent. It can optionally also convert the half-width katakana to their
+/// full-width version, combining them with any following (semi-)voiced sound mark.
+#[derive(Clone)]
+pub struct FullWidthToHalfWidth {
+    katakana: bool,
+}
+
+impl FullWidthToHalfWidth {
+    pub fn new(katakana: bool) -> Self {
+        FullWidthToHalfWidth { katakana }
+    }
+}
+
+impl Default for FullWidthToHalfWidth {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl Normalizer for FullWidthToHalfWidth {
+    fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
+        let mut new_chars: Vec<(char, isize)> = vec![];
+        normalized.for_each(|c| {
+            if self.katakana {
+                if let Some((last, _)) = new_chars.last() {
+                    if let Some(combined) = combine_katakana(*last, c) {
+                        // The mark gets removed, merged in the previous char
+                        new_chars.pop();
+                        new_chars.push((combined, -1));
+                        return;
+                    }
+                }
+                new_chars.push((to_half_width(to_full_width_katakana(c)), 0));
+            } else {
+                new_chars.push((to_half_width(c), 0));
+            }
+        });
+        normalized.transform(new_chars.into_iter(), 0);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Range;
+
+    #[test]
+    fn full_width_to_half_width() {
+        let mut n = NormalizedString::from("ＡＢＣ１２３　abc！");
+        FullWidthToHalfWidth::default().normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "ABC123 abc!");
+        assert_eq!(
+            n.get_range_original(Range::Normalized(3..6)),
+            Some("１２３")
+        );
+    }
+
+    #[test]
+    fn katakana() {
+        let mut n = NormalizedString::from("ｶﾞｲﾄﾞﾌﾞｯｸ ﾊﾟﾝ");
+        FullWidthToHalfWidth::new(false).normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "ｶﾞｲﾄﾞﾌﾞｯｸ ﾊﾟﾝ");
+
+        FullWidthToHalfWidth::new(true).normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "ガイドブック パン");
+        assert_eq!(n.get_range_original(Range::Normalized(0..2)), Some("ｶﾞｲ"));
+        assert_eq!(n.get_range_original(Range::Normalized(7..9)), Some("ﾊﾟﾝ"));
+    }
+
+    #[test]
+    fn mixed_width() {
+        let mut n = NormalizedString::from("ﾃｽﾄ：Ｔｏｋｅｎｉｚｅｒｓ 2020ﾞ");
+        FullWidthToHalfWidth::new(true).normalize(&mut n).unwrap();
+        assert_eq!(n.get(), "テスト:Tokenizers 2020゛");
+        assert_eq!(
+            n.get_range_original(Range::Normalized(4..14)),
+            Some("Ｔｏｋｅｎｉｚｅｒｓ")
+        );
+    }
+}
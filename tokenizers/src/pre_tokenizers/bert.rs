@@ -37,6 +37,7 @@ fn split_on<F: Fn(&char) -> bool>(
     words
 }
 
+#[derive(Clone)]
 pub struct BertPreTokenizer;
 
 impl PreTokenizer for BertPreTokenizer {
@@ -37,21 +37,58 @@ lazy_static! {
         bytes_char().into_iter().map(|(c, b)| (b, c)).collect();
 }
 
+/// Find the ranges (in bytes) of the splits of the given string
+fn split_positions(s: &str) -> Vec<std::ops::Range<usize>> {
+    RE.captures_iter(s)
+        .map(|capture| {
+            let capture = capture.get(0).unwrap();
+            let start = capture.start();
+            let end = capture.end();
+
+            // if our last character is a whitespace, followed by a non whitespace,
+            // we don't want to return it
+            let last = s[start..end].chars().last();
+            let next = s[end..].chars().next();
+            if let (Some(last), Some(next)) = (last, next) {
+                if last.is_separator_space() && !next.is_separator_space() {
+                    return start..end - last.len_utf8();
+                }
+            }
+            // if our first char is not a whitespace but the previous one was, we return
+            // a whitespace before our match
+            let prev = s[0..start].chars().last();
+            let current = s[start..end].chars().next().map(|c| c.is_whitespace());
+            if let (Some(prev), Some(current)) = (prev, current) {
+                if prev.is_separator_space() && !current {
+                    return start - prev.len_utf8()..end;
+                }
+            }
+
+            start..end
+        })
+        .collect()
+}
+
 /// Provides all the necessary steps to handle the BPE tokenization at the byte-level. Takes care
 /// of all the required processing steps to transform a UTF-8 string as needed before and after the
 /// BPE model does its job.
+#[derive(Clone)]
 pub struct ByteLevel {
     /// Whether to add a leading space to the first word. This allows to treat the leading word
     /// just as any other word.
     add_prefix_space: bool,
     /// Whether the post processing step should trim offsets to avoid including whitespaces.
     trim_offsets: bool,
+    /// Whether the decoding step should remove the leading space, usually added by
+    /// `add_prefix_space`.
+    strip_leading_space: bool,
 }
 impl Default for ByteLevel {
     fn default() -> Self {
         Self {
             add_prefix_space: true,
             trim_offsets: true,
+            strip_leading_space: false,
         }
     }
 }
@@ -61,6 +98,7 @@ impl ByteLevel {
         ByteLevel {
             add_prefix_space,
             trim_offsets,
+            strip_leading_space: false,
         }
     }
 
@@ -77,6 +115,14 @@ impl ByteLevel {
         self.trim_offsets = v;
         self
     }
+
+    /// Remove a single leading space when decoding, so that decoding what was encoded with
+    /// `add_prefix_space` gives back the original text. Note that the input could also start
+    /// with a space on its own, in which case no space was added but one is still removed.
+    pub fn strip_leading_space(mut self, v: bool) -> Self {
+        self.strip_leading_space = v;
+        self
+    }
 }
 
 /// As a `PreTokenizer`, `ByteLevel` is in charge of transforming all the unicode characters into
@@ -88,36 +134,7 @@ impl PreTokenizer for ByteLevel {
             normalized.prepend(" ");
         }
 
-        let positions = RE
-            .captures_iter(normalized.get())
-            .map(|capture| {
-                let s = normalized.get();
-                let capture = capture.get(0).unwrap();
-                let start = capture.start();
-                let end = capture.end();
-
-                // if our last character is a whitespace, followed by a non whitespace,
-                // we don't want to return it
-                let last = s[start..end].chars().last();
-                let next = s[end..].chars().next();
-                if let (Some(last), Some(next)) = (last, next) {
-                    if last.is_separator_space() && !next.is_separator_space() {
-                        return start..end - last.len_utf8();
-                    }
-                }
-                // if our first char is not a whitespace but the previous one was, we return
-                // a whitespace before our match
-                let prev = s[0..start].chars().last();
-                let current = s[start..end].chars().next().map(|c| c.is_whitespace());
-                if let (Some(prev), Some(current)) = (prev, current) {
-                    if prev.is_separator_space() && !current {
-                        return start - prev.len_utf8()..end;
-                    }
-                }
-
-                start..end
-            })
-            .collect::<Vec<_>>();
+        let positions = split_positions(normalized.get());
 
         let splits = positions
             .into_par_iter()
@@ -170,20 +187,88 @@ impl PreTokenizer for ByteLevel {
             })
             .collect())
     }
+
+    fn pre_tokenize_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<(NormalizedString, Vec<(String, Offsets)>)> {
+        // Each byte is represented by the char with the same value in the original string, so
+        // that the offsets end up in bytes
+        let mut normalized =
+            NormalizedString::from(&bytes.iter().map(|b| *b as char).collect::<String>());
+        let mut input = bytes.to_vec();
+        if self.add_prefix_space && !bytes.starts_with(b" ") {
+            normalized.prepend(" ");
+            input.insert(0, b' ');
+        }
+        normalized.transform_chars(|c| Some(BYTES_CHAR[&(c as u8)]));
+
+        // The valid UTF-8 parts get split as usual, and each run of invalid bytes is kept as
+        // its own split
+        let mut positions = vec![];
+        let mut start = 0;
+        let mut last_invalid = false;
+        while start < input.len() {
+            let (valid, invalid) = match std::str::from_utf8(&input[start..]) {
+                Ok(s) => (s, 0),
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    let invalid = e.error_len().unwrap_or(input.len() - start - valid);
+                    let s = std::str::from_utf8(&input[start..start + valid]).unwrap();
+                    (s, invalid)
+                }
+            };
+            if !valid.is_empty() {
+                positions.extend(
+                    split_positions(valid)
+                        .into_iter()
+                        .map(|r| r.start + start..r.end + start),
+                );
+                last_invalid = false;
+            }
+            start += valid.len();
+
+            if invalid > 0 {
+                match positions.last_mut() {
+                    Some(range) if last_invalid => range.end += invalid,
+                    _ => positions.push(start..start + invalid),
+                }
+                last_invalid = true;
+                start += invalid;
+            }
+        }
+
+        let pre_tokenized = positions
+            .into_iter()
+            .map(|range| {
+                let s = input[range.clone()]
+                    .iter()
+                    .map(|b| BYTES_CHAR[b])
+                    .collect::<String>();
+                (s, (range.start, range.end))
+            })
+            .collect();
+
+        Ok((normalized, pre_tokenized))
+    }
 }
 
 /// As a `Decoder`, `ByteLevel` is in charge of converting any byte-level characters to their
 /// unicode counterpart, before merging everything back into a single String.
 impl Decoder for ByteLevel {
     fn decode(&self, tokens: Vec<String>) -> Result<String> {
-        Ok(String::from_utf8_lossy(
+        let mut decoded = String::from_utf8_lossy(
             &tokens
                 .join("")
                 .chars()
                 .map(|c| CHAR_BYTES[&c])
                 .collect::<Vec<_>>(),
         )
-        .into_owned())
+        .into_owned();
+        if self.strip_leading_space && decoded.starts_with(' ') {
+            decoded.remove(0);
+        }
+        Ok(decoded)
     }
 }
 
@@ -255,6 +340,33 @@ mod tests {
         Decoder, Encoding, NormalizedString, PostProcessor, PreTokenizer, Range,
     };
 
+    #[test]
+    fn pre_tokenization_bytes() {
+        let bytelevel = ByteLevel::default();
+        let (normalized, pre_tokenized) = bytelevel
+            .pre_tokenize_bytes(b"Hi \xff\xfe\xe2\x82 there")
+            .unwrap();
+        assert_eq!(
+            pre_tokenized,
+            vec![
+                ("ĠHi".into(), (0, 3)),
+                ("Ġ".into(), (3, 4)),
+                ("ÿþâĤ".into(), (4, 8)),
+                ("Ġthere".into(), (8, 14))
+            ]
+        );
+        assert_eq!(normalized.get(), "ĠHiĠÿþâĤĠthere");
+        // The offsets of the original string are in bytes
+        assert_eq!(
+            normalized.convert_offsets(Range::Normalized(4..8)),
+            Some(3..7)
+        );
+        assert_eq!(
+            normalized.convert_offsets(Range::Normalized(0..3)),
+            Some(0..2)
+        );
+    }
+
     #[test]
     fn pre_tokenization() {
         let bytelevel = ByteLevel::default().add_prefix_space(false);
@@ -323,6 +435,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn decode_strip_leading_space() {
+        let tokens = || vec!["ĠHello".into(), "Ġmy".into(), "Ġfriend".into()];
+        let bytelevel = ByteLevel::default();
+        assert_eq!(bytelevel.decode(tokens()).unwrap(), " Hello my friend");
+
+        let bytelevel = bytelevel.strip_leading_space(true);
+        assert_eq!(bytelevel.decode(tokens()).unwrap(), "Hello my friend");
+        // Only a single space gets removed
+        let tokens = vec!["ĠĠHello".into()];
+        assert_eq!(bytelevel.decode(tokens).unwrap(), " Hello");
+    }
+
     #[test]
     fn decode_works_on_separated_tokens() {
         let samples = vec![
@@ -411,6 +536,8 @@ mod tests {
             vec![],
             vec![],
             vec![],
+            vec![],
+            vec![],
         );
         let expected = Encoding::new(
             vec![],
@@ -425,6 +552,8 @@ mod tests {
             vec![],
             vec![],
             vec![],
+            vec![],
+            vec![],
         );
 
         let bytelevel = ByteLevel::default().trim_offsets(true);
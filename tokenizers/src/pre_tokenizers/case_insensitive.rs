@@ -0,0 +1,73 @@
+use crate::tokenizer::{NormalizedString, Offsets, PreTokenizer, Range, Result};
+
+/// Wraps another `PreTokenizer` so that it decides where to split on a lowercased version of
+/// the input, while the pre-tokens keep their original case. This way, "Hello" and "hello"
+/// always get split the same way.
+///
+/// The wrapped `PreTokenizer` works on a copy of the input, so any modification it makes to
+/// the `NormalizedString` (like the byte-level mapping) is not kept: the pre-tokens are always
+/// substrings of the given input.
+#[derive(Clone)]
+pub struct CaseInsensitiveSplit(Box<dyn PreTokenizer + Sync>);
+
+impl CaseInsensitiveSplit {
+    pub fn new(pre_tokenizer: Box<dyn PreTokenizer + Sync>) -> Self {
+        CaseInsensitiveSplit(pre_tokenizer)
+    }
+}
+
+impl PreTokenizer for CaseInsensitiveSplit {
+    fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>> {
+        // Lowercasing may change the number of chars, so we keep the alignments of the
+        // lowercased copy to map its offsets back on our input
+        let mut lowercased = NormalizedString::from(normalized.get());
+        lowercased.lowercase();
+
+        Ok(self
+            .0
+            .pre_tokenize(&mut lowercased)?
+            .into_iter()
+            .filter_map(|(_, (start, end))| {
+                let range = lowercased.convert_offsets(Range::Normalized(start..end))?;
+                let token = normalized.get_range(Range::Normalized(range.clone()))?;
+                Some((token.to_owned(), (range.start, range.end)))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pre_tokenizers::delimiter::CharDelimiterSplit;
+    use crate::pre_tokenizers::whitespace::WhitespaceSplit;
+
+    #[test]
+    fn keeps_original_case() {
+        let pretok = CaseInsensitiveSplit::new(Box::new(CharDelimiterSplit::new('x')));
+        let mut input = NormalizedString::from("aXbxC");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![
+                ("a".into(), (0, 1)),
+                ("b".into(), (2, 3)),
+                ("C".into(), (4, 5))
+            ]
+        );
+    }
+
+    #[test]
+    fn lowercase_changing_length() {
+        // 'İ' gets lowercased into two chars: "i̇"
+        let pretok = CaseInsensitiveSplit::new(Box::new(WhitespaceSplit::new()));
+        let mut input = NormalizedString::from("İSTANBUL İzmir ok");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![
+                ("İSTANBUL".into(), (0, 8)),
+                ("İzmir".into(), (9, 14)),
+                ("ok".into(), (15, 17))
+            ]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::normalizers::bert::is_chinese_char;
+use crate::tokenizer::{NormalizedString, Offsets, PreTokenizer, Result};
+use unicode_categories::UnicodeCategories;
+
+/// How the runs of non-CJK chars (like Latin words) get split by `CjkAware`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LatinSplit {
+    /// Split on whitespace only
+    Whitespace,
+    /// Split on whitespace, and isolate each punctuation char
+    Punctuation,
+}
+
+/// Splits mixed CJK and Latin text in a single pass: each Chinese char and each Japanese kana
+/// becomes its own pre-token, while the other chars are split as configured with `latin_split`
+/// (on whitespace and punctuation by default).
+#[derive(Clone)]
+pub struct CjkAware {
+    latin_split: LatinSplit,
+}
+
+impl Default for CjkAware {
+    fn default() -> Self {
+        CjkAware {
+            latin_split: LatinSplit::Punctuation,
+        }
+    }
+}
+
+impl CjkAware {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How to split the runs of non-CJK chars
+    pub fn latin_split(mut self, latin_split: LatinSplit) -> Self {
+        self.latin_split = latin_split;
+        self
+    }
+}
+
+/// Whether the given char is a Chinese char (as defined by BERT), or a Japanese kana
+fn is_cjk_char(c: char) -> bool {
+    match c as usize {
+        // Hiragana, Katakana and Katakana Phonetic Extensions
+        0x3040..=0x309F | 0x30A0..=0x30FF | 0x31F0..=0x31FF => true,
+        // Halfwidth Katakana
+        0xFF66..=0xFF9F => true,
+        _ => is_chinese_char(c),
+    }
+}
+
+impl PreTokenizer for CjkAware {
+    fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>> {
+        let mut pre_tokens = vec![];
+        let mut word = String::new();
+        let mut word_start = 0;
+
+        for (offset, c) in normalized.get().chars().enumerate() {
+            let isolated = is_cjk_char(c)
+                || (self.latin_split == LatinSplit::Punctuation
+                    && (c.is_ascii_punctuation() || c.is_punctuation()));
+            if c.is_whitespace() || isolated {
+                if !word.is_empty() {
+                    pre_tokens.push((std::mem::take(&mut word), (word_start, offset)));
+                }
+                if isolated {
+                    pre_tokens.push((c.to_string(), (offset, offset + 1)));
+                }
+            } else {
+                if word.is_empty() {
+                    word_start = offset;
+                }
+                word.push(c);
+            }
+        }
+        if !word.is_empty() {
+            let end = word_start + word.chars().count();
+            pre_tokens.push((word, (word_start, end)));
+        }
+
+        Ok(pre_tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Range;
+
+    #[test]
+    fn mixed_scripts() {
+        let pretok = CjkAware::new();
+        let mut input = NormalizedString::from("我爱Rust, ひらがな and カタカナ!");
+        let res = pretok.pre_tokenize(&mut input).unwrap();
+        assert_eq!(
+            res,
+            vec![
+                ("我".into(), (0, 1)),
+                ("爱".into(), (1, 2)),
+                ("Rust".into(), (2, 6)),
+                (",".into(), (6, 7)),
+                ("ひ".into(), (8, 9)),
+                ("ら".into(), (9, 10)),
+                ("が".into(), (10, 11)),
+                ("な".into(), (11, 12)),
+                ("and".into(), (13, 16)),
+                ("カ".into(), (17, 18)),
+                ("タ".into(), (18, 19)),
+                ("カ".into(), (19, 20)),
+                ("ナ".into(), (20, 21)),
+                ("!".into(), (21, 22)),
+            ]
+        );
+        for (token, (start, end)) in res {
+            assert_eq!(
+                input.get_range(Range::Normalized(start..end)),
+                Some(&token[..])
+            );
+        }
+    }
+
+    #[test]
+    fn latin_split_on_whitespace() {
+        let pretok = CjkAware::new().latin_split(LatinSplit::Whitespace);
+        let mut input = NormalizedString::from("Hello, 世界! don't");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![
+                ("Hello,".into(), (0, 6)),
+                ("世".into(), (7, 8)),
+                ("界".into(), (8, 9)),
+                ("!".into(), (9, 10)),
+                ("don't".into(), (11, 16)),
+            ]
+        );
+
+        let pretok = CjkAware::new();
+        let mut input = NormalizedString::from("Hello, 世界! don't");
+        assert_eq!(
+            pretok
+                .pre_tokenize(&mut input)
+                .unwrap()
+                .into_iter()
+                .map(|(token, _)| token)
+                .collect::<Vec<_>>(),
+            vec!["Hello", ",", "世", "界", "!", "don", "'", "t"]
+        );
+    }
+}
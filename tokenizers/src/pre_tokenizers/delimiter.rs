@@ -1,5 +1,6 @@
 use crate::tokenizer::{NormalizedString, Offsets, PreTokenizer, Result};
 
+#[derive(Clone)]
 pub struct CharDelimiterSplit {
     delimiter: char,
 }
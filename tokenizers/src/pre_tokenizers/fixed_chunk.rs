@@ -0,0 +1,64 @@
+use crate::tokenizer::{NormalizedString, Offsets, PreTokenizer, Result};
+
+/// Splits the input in consecutive chunks of a fixed number of chars, the last one being
+/// shorter if needed. Useful for the sequences where the whitespaces don't mean anything,
+/// like DNA or code.
+#[derive(Clone)]
+pub struct FixedChunk(usize);
+
+impl FixedChunk {
+    /// Panics if `size == 0`
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "FixedChunk size must be greater than 0");
+        FixedChunk(size)
+    }
+}
+
+impl PreTokenizer for FixedChunk {
+    fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>> {
+        let chars = normalized.get().chars().collect::<Vec<_>>();
+        Ok(chars
+            .chunks(self.0)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let start = i * self.0;
+                (chunk.iter().collect(), (start, start + chunk.len()))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_chunk() {
+        let pretok = FixedChunk::new(3);
+        let mut input = NormalizedString::from("ACGTACGT");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![
+                ("ACG".into(), (0, 3)),
+                ("TAC".into(), (3, 6)),
+                ("GT".into(), (6, 8))
+            ]
+        );
+
+        // Offsets are in chars
+        let mut input = NormalizedString::from("é a ñ");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![("é a".into(), (0, 3)), (" ñ".into(), (3, 5))]
+        );
+
+        let mut input = NormalizedString::from("");
+        assert!(pretok.pre_tokenize(&mut input).unwrap().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_size() {
+        FixedChunk::new(0);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::tokenizer::{NormalizedString, Offsets, PreTokenizer, Result};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Splits the input on each extended grapheme cluster: what is perceived as a single char,
+/// even when made of several code points (like a letter with combining marks, or an emoji
+/// sequence such as "👨‍👩‍👧"). Useful for the character-level models.
+#[derive(Clone, Default)]
+pub struct GraphemeSplit;
+
+impl GraphemeSplit {
+    pub fn new() -> Self {
+        GraphemeSplit
+    }
+}
+
+impl PreTokenizer for GraphemeSplit {
+    fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>> {
+        let mut offset = 0;
+        Ok(normalized
+            .get()
+            .graphemes(true)
+            .map(|grapheme| {
+                let len = grapheme.chars().count();
+                offset += len;
+                (grapheme.to_owned(), (offset - len, offset))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grapheme_split() {
+        let pretok = GraphemeSplit::new();
+
+        // A family, made of 3 emojis joined by ZWJ, and a flag made of 2 regional indicators
+        let mut input = NormalizedString::from("a👨‍👩‍👧🇫🇷");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![
+                ("a".into(), (0, 1)),
+                ("👨‍👩‍👧".into(), (1, 6)),
+                ("🇫🇷".into(), (6, 8)),
+            ]
+        );
+
+        // Devanagari consonant with a vowel sign, and a conjunct with virama
+        let mut input = NormalizedString::from("नमस्ते");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![
+                ("न".into(), (0, 1)),
+                ("म".into(), (1, 2)),
+                ("स्ते".into(), (2, 6)),
+            ]
+        );
+    }
+}
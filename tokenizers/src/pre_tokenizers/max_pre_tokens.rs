@@ -0,0 +1,58 @@
+use crate::tokenizer::{NormalizedString, Offsets, PreTokenizer, Result};
+
+/// Wraps another `PreTokenizer` to keep only its first pre-tokens. When the inputs are going
+/// to be truncated anyway, this avoids spending time in the `Model` for words that would end up
+/// removed. With the models that give at least one token per pre-token (like `BPE` or
+/// `WordPiece`), keeping as many pre-tokens as the truncation `max_length` doesn't change the
+/// result, except for the overflowing parts.
+#[derive(Clone)]
+pub struct MaxPreTokens(Box<dyn PreTokenizer + Sync>, usize);
+
+impl MaxPreTokens {
+    pub fn new(pre_tokenizer: Box<dyn PreTokenizer + Sync>, max_pre_tokens: usize) -> Self {
+        MaxPreTokens(pre_tokenizer, max_pre_tokens)
+    }
+}
+
+impl PreTokenizer for MaxPreTokens {
+    fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>> {
+        let mut pre_tokenized = self.0.pre_tokenize(normalized)?;
+        pre_tokenized.truncate(self.1);
+        Ok(pre_tokenized)
+    }
+
+    fn pre_tokenize_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<(NormalizedString, Vec<(String, Offsets)>)> {
+        let (normalized, mut pre_tokenized) = self.0.pre_tokenize_bytes(bytes)?;
+        pre_tokenized.truncate(self.1);
+        Ok((normalized, pre_tokenized))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pre_tokenizers::whitespace::Whitespace;
+
+    #[test]
+    fn max_pre_tokens() {
+        let pretok = MaxPreTokens::new(Box::new(Whitespace::new()), 3);
+        let mut input = NormalizedString::from("Hey man, how are you?");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![
+                ("Hey".into(), (0, 3)),
+                ("man".into(), (4, 7)),
+                (",".into(), (7, 8))
+            ]
+        );
+
+        let mut input = NormalizedString::from("Hey");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![("Hey".into(), (0, 3))]
+        );
+    }
+}
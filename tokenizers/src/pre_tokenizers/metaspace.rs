@@ -2,6 +2,7 @@ use crate::tokenizer::{Decoder, NormalizedString, Offsets, PreTokenizer, Result}
 
 /// Replaces all the whitespaces by the provided meta character and then
 /// splits on this character
+#[derive(Clone)]
 pub struct Metaspace {
     replacement: char,
     add_prefix_space: bool,
@@ -114,4 +115,15 @@ mod tests {
             .unwrap();
         assert_eq!(&res, "Hey friend!")
     }
+
+    #[test]
+    fn decode_leading_space() {
+        let tokens = || vec!["▁Hey".into(), "▁friend!".into()];
+        // The space added by `add_prefix_space` is removed
+        let decoder = Metaspace::new('▁', true);
+        assert_eq!(decoder.decode(tokens()).unwrap(), "Hey friend!");
+
+        let decoder = Metaspace::new('▁', false);
+        assert_eq!(decoder.decode(tokens()).unwrap(), " Hey friend!");
+    }
 }
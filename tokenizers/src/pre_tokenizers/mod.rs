@@ -1,5 +1,11 @@
 pub mod bert;
 pub mod byte_level;
+pub mod case_insensitive;
+pub mod cjk;
 pub mod delimiter;
+pub mod fixed_chunk;
+pub mod grapheme;
+pub mod max_pre_tokens;
 pub mod metaspace;
+pub mod regex_split;
 pub mod whitespace;
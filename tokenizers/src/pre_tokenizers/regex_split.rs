@@ -0,0 +1,119 @@
+use crate::tokenizer::{NormalizedString, Offsets, PreTokenizer, Result};
+use regex::Regex;
+
+/// Splits the input using a regex. The matches are kept as pre-tokens, and the rest of the input
+/// is dropped, which is useful to keep some patterns (like URLs) intact. With `invert`, it's the
+/// opposite: the parts between the matches are kept, and the matches are dropped.
+#[derive(Clone)]
+pub struct RegexSplit {
+    pattern: String,
+    invert: bool,
+    re: Regex,
+}
+
+impl RegexSplit {
+    /// Returns an error if the pattern is not a valid regex
+    pub fn new(pattern: &str, invert: bool) -> Result<Self> {
+        Ok(RegexSplit {
+            pattern: pattern.to_owned(),
+            invert,
+            re: Regex::new(pattern)?,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn invert(&self) -> bool {
+        self.invert
+    }
+}
+
+impl PreTokenizer for RegexSplit {
+    fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>> {
+        let s = normalized.get();
+
+        // The byte offsets of the parts to keep
+        let mut parts = vec![];
+        let mut last = 0;
+        for m in self.re.find_iter(s) {
+            if self.invert {
+                parts.push((last, m.start()));
+            } else {
+                parts.push((m.start(), m.end()));
+            }
+            last = m.end();
+        }
+        if self.invert {
+            parts.push((last, s.len()));
+        }
+
+        // The regex gives us byte offsets, but we need to provide char offsets
+        let mut last_byte = 0;
+        let mut last_char = 0;
+        Ok(parts
+            .into_iter()
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| {
+                let start_char = last_char + s[last_byte..start].chars().count();
+                let end_char = start_char + s[start..end].chars().count();
+                last_byte = end;
+                last_char = end_char;
+                (s[start..end].to_owned(), (start_char, end_char))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Range;
+
+    #[test]
+    fn matches() {
+        let pretok = RegexSplit::new(r"\d+", false).unwrap();
+        let mut input = NormalizedString::from("a12b345");
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![("12".into(), (1, 3)), ("345".into(), (4, 7))]
+        );
+
+        let pretok = RegexSplit::new(r"\d+", true).unwrap();
+        assert_eq!(
+            pretok.pre_tokenize(&mut input).unwrap(),
+            vec![("a".into(), (0, 1)), ("b".into(), (3, 4))]
+        );
+    }
+
+    #[test]
+    fn unicode_offsets() {
+        let input = "voir https://exemple.fr/été et ça";
+        let urls = RegexSplit::new(r"https?://\S+", false).unwrap();
+        let gaps = RegexSplit::new(r"https?://\S+", true).unwrap();
+
+        let mut normalized = NormalizedString::from(input);
+        let pre_tokenized = urls.pre_tokenize(&mut normalized).unwrap();
+        assert_eq!(
+            pre_tokenized,
+            vec![("https://exemple.fr/été".into(), (5, 27))]
+        );
+        let pre_tokenized = gaps.pre_tokenize(&mut normalized).unwrap();
+        assert_eq!(
+            pre_tokenized,
+            vec![("voir ".into(), (0, 5)), (" et ça".into(), (27, 33))]
+        );
+        for (token, (start, end)) in pre_tokenized {
+            assert_eq!(
+                normalized.get_range(Range::Normalized(start..end)),
+                Some(&token[..])
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(RegexSplit::new(r"(\d+", false).is_err());
+    }
+}
@@ -1,31 +1,130 @@
 use crate::tokenizer::{NormalizedString, Offsets, PreTokenizer, Result};
 use regex::Regex;
+use std::collections::HashSet;
+
+/// Splits on whitespace, and separates the words from the punctuation. Words are made of any
+/// Unicode letter, mark, number or connector punctuation, so accented letters and scripts like
+/// Cyrillic or Devanagari stay in one piece.
+///
+/// By default, the whitespace chars are the Unicode `White_Space` ones, but another set can be
+/// provided with `with_whitespace_chars`. The other chars are then treated as punctuation.
+#[derive(Clone, Default)]
+pub struct Whitespace {
+    re: Option<Regex>,
+    keep_newlines: bool,
+}
+
+impl Whitespace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to keep each newline (`\n`) as its own pre-token, instead of splitting on it
+    /// like any other whitespace. Useful for the models that need to know about line breaks.
+    pub fn keep_newlines(mut self, v: bool) -> Self {
+        self.keep_newlines = v;
+        self
+    }
+
+    /// Use the given chars as whitespace, instead of the Unicode `White_Space` ones
+    pub fn with_whitespace_chars(whitespace: HashSet<char>) -> Self {
+        let mut chars = whitespace
+            .into_iter()
+            .map(|c| regex::escape(&c.to_string()))
+            .collect::<Vec<_>>();
+        chars.sort();
+        let chars = chars.concat();
+
+        let re = if chars.is_empty() {
+            Regex::new(r"[\p{L}\p{M}\p{N}\p{Pc}]+|[^\p{L}\p{M}\p{N}\p{Pc}]+")
+        } else {
+            Regex::new(&format!(
+                r"[[\p{{L}}\p{{M}}\p{{N}}\p{{Pc}}]--[{0}]]+|[^\p{{L}}\p{{M}}\p{{N}}\p{{Pc}}{0}]+",
+                chars
+            ))
+        };
+        Whitespace {
+            re: Some(re.unwrap()),
+            keep_newlines: false,
+        }
+    }
+}
 
-pub struct Whitespace;
 impl PreTokenizer for Whitespace {
     fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>> {
         lazy_static! {
-            static ref RE: Regex = Regex::new(r"\w+|[^\w\s]+").unwrap();
+            static ref RE: Regex =
+                Regex::new(r"[\p{L}\p{M}\p{N}\p{Pc}]+|[^\p{L}\p{M}\p{N}\p{Pc}\s]+").unwrap();
+        }
+        let re = self.re.as_ref().unwrap_or(&RE);
+        let s = normalized.get();
+        let lines = if self.keep_newlines {
+            s.split('\n').collect()
+        } else {
+            vec![s]
+        };
+
+        let mut pre_tokens = vec![];
+        let mut offset = 0;
+        for (i, line) in lines.into_iter().enumerate() {
+            if i > 0 {
+                pre_tokens.push(("\n".to_owned(), (offset, offset + 1)));
+                offset += 1;
+            }
+
+            // The regex gives us byte offsets, but we need to provide char offsets
+            let mut last_byte = 0;
+            let mut last_char = offset;
+            pre_tokens.extend(re.find_iter(line).map(|m| {
+                let start = last_char + line[last_byte..m.start()].chars().count();
+                let end = start + m.as_str().chars().count();
+                last_byte = m.end();
+                last_char = end;
+                (m.as_str().to_owned(), (start, end))
+            }));
+            offset += line.chars().count();
+        }
+
+        Ok(pre_tokens)
+    }
+}
+
+/// Splits on whitespace only. By default, the whitespace chars are the Unicode `White_Space`
+/// ones, but another set can be provided with `with_whitespace_chars`.
+#[derive(Clone, Default)]
+pub struct WhitespaceSplit {
+    whitespace: Option<HashSet<char>>,
+    keep_newlines: bool,
+}
+
+impl WhitespaceSplit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given chars as whitespace, instead of the Unicode `White_Space` ones
+    pub fn with_whitespace_chars(whitespace: HashSet<char>) -> Self {
+        WhitespaceSplit {
+            whitespace: Some(whitespace),
+            keep_newlines: false,
+        }
+    }
+
+    /// Whether to keep each newline (`\n`) as its own pre-token, instead of splitting on it
+    /// like any other whitespace. Useful for the models that need to know about line breaks.
+    pub fn keep_newlines(mut self, v: bool) -> Self {
+        self.keep_newlines = v;
+        self
+    }
+
+    fn is_whitespace(&self, c: char) -> bool {
+        match &self.whitespace {
+            Some(whitespace) => whitespace.contains(&c),
+            None => c.is_whitespace(),
         }
-        Ok(RE
-            .captures_iter(normalized.get())
-            .flat_map(|captures| {
-                captures
-                    .iter()
-                    .map(|m| {
-                        m.map(|capture| {
-                            let (start, end) = (capture.start(), capture.end());
-                            (normalized.get()[start..end].to_owned(), (start, end))
-                        })
-                        .unwrap_or_else(|| (String::from(""), (0, 0)))
-                    })
-                    .collect::<Vec<(String, Offsets)>>()
-            })
-            .collect())
     }
 }
 
-pub struct WhitespaceSplit;
 impl PreTokenizer for WhitespaceSplit {
     fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>> {
         let mut words = vec![];
@@ -33,11 +132,14 @@ impl PreTokenizer for WhitespaceSplit {
         let mut offset = 0;
 
         normalized.get().chars().for_each(|c| {
-            if c.is_whitespace() {
+            if self.is_whitespace(c) || (self.keep_newlines && c == '\n') {
                 if !word.is_empty() {
                     let offsets = (offset - word.len(), offset);
                     words.push((word.drain(0..).collect::<String>(), offsets));
                 }
+                if self.keep_newlines && c == '\n' {
+                    words.push(("\n".to_owned(), (offset, offset + 1)));
+                }
             } else {
                 word.push(c);
             }
@@ -55,7 +157,7 @@ impl PreTokenizer for WhitespaceSplit {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tokenizer::PreTokenizer;
+    use crate::tokenizer::{PreTokenizer, Range};
 
     #[test]
     fn basic() {
@@ -79,13 +181,61 @@ mod tests {
                 ],
             ),
         ];
-        let pretok = Whitespace;
+        let pretok = Whitespace::new();
         for (s, res) in tests {
             let mut input = NormalizedString::from(s);
             assert_eq!(pretok.pre_tokenize(&mut input).unwrap(), res);
         }
     }
 
+    #[test]
+    fn unicode_words() {
+        let tests = vec![
+            (
+                "Un café, s'il vous plaît",
+                vec![
+                    ("Un".into(), (0, 2)),
+                    ("café".into(), (3, 7)),
+                    (",".into(), (7, 8)),
+                    ("s".into(), (9, 10)),
+                    ("'".into(), (10, 11)),
+                    ("il".into(), (11, 13)),
+                    ("vous".into(), (14, 18)),
+                    ("plaît".into(), (19, 24)),
+                ],
+            ),
+            (
+                "Привет, мир!",
+                vec![
+                    ("Привет".into(), (0, 6)),
+                    (",".into(), (6, 7)),
+                    ("мир".into(), (8, 11)),
+                    ("!".into(), (11, 12)),
+                ],
+            ),
+            (
+                "नमस्ते दुनिया।",
+                vec![
+                    ("नमस्ते".into(), (0, 6)),
+                    ("दुनिया".into(), (7, 13)),
+                    ("।".into(), (13, 14)),
+                ],
+            ),
+        ];
+        let pretok = Whitespace::new();
+        for (s, res) in tests {
+            let mut input = NormalizedString::from(s);
+            let pre_tokenized = pretok.pre_tokenize(&mut input).unwrap();
+            assert_eq!(pre_tokenized, res);
+            for (token, (start, end)) in pre_tokenized {
+                assert_eq!(
+                    input.get_range(Range::Normalized(start..end)),
+                    Some(&token[..])
+                );
+            }
+        }
+    }
+
     #[test]
     fn whitespace_split() {
         let tests = vec![
@@ -102,10 +252,111 @@ mod tests {
                 ],
             ),
         ];
-        let pretok = WhitespaceSplit;
+        let pretok = WhitespaceSplit::new();
         for (s, res) in tests {
             let mut input = NormalizedString::from(s);
             assert_eq!(pretok.pre_tokenize(&mut input).unwrap(), res);
         }
     }
+
+    #[test]
+    fn custom_whitespace() {
+        let input = "a\tb\u{00A0}c d";
+        let pre_tokenize = |pretok: &dyn PreTokenizer| {
+            pretok
+                .pre_tokenize(&mut NormalizedString::from(input))
+                .unwrap()
+                .into_iter()
+                .map(|(s, _)| s)
+                .collect::<Vec<_>>()
+        };
+
+        // By default, the tab and the non-breaking space are both whitespace
+        assert_eq!(pre_tokenize(&Whitespace::new()), vec!["a", "b", "c", "d"]);
+        assert_eq!(
+            pre_tokenize(&WhitespaceSplit::new()),
+            vec!["a", "b", "c", "d"]
+        );
+
+        let space_and_tab = [' ', '\t'].iter().copied().collect::<HashSet<_>>();
+        assert_eq!(
+            pre_tokenize(&Whitespace::with_whitespace_chars(space_and_tab.clone())),
+            vec!["a", "b", "\u{00A0}", "c", "d"]
+        );
+        assert_eq!(
+            pre_tokenize(&WhitespaceSplit::with_whitespace_chars(space_and_tab)),
+            vec!["a", "b\u{00A0}c", "d"]
+        );
+
+        let space = [' '].iter().copied().collect::<HashSet<_>>();
+        assert_eq!(
+            pre_tokenize(&Whitespace::with_whitespace_chars(space.clone())),
+            vec!["a", "\t", "b", "\u{00A0}", "c", "d"]
+        );
+        assert_eq!(
+            pre_tokenize(&WhitespaceSplit::with_whitespace_chars(space)),
+            vec!["a\tb\u{00A0}c", "d"]
+        );
+
+        // The offsets stay correct
+        let mut input = NormalizedString::from("a\tb");
+        assert_eq!(
+            Whitespace::with_whitespace_chars(HashSet::new())
+                .pre_tokenize(&mut input)
+                .unwrap(),
+            vec![
+                ("a".into(), (0, 1)),
+                ("\t".into(), (1, 2)),
+                ("b".into(), (2, 3))
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_newlines() {
+        let input = "fn main() {\n    foo();\n\n}";
+        let pretok = Whitespace::default().keep_newlines(true);
+        let res = pretok
+            .pre_tokenize(&mut NormalizedString::from(input))
+            .unwrap();
+        assert_eq!(
+            res,
+            vec![
+                ("fn".into(), (0, 2)),
+                ("main".into(), (3, 7)),
+                ("()".into(), (7, 9)),
+                ("{".into(), (10, 11)),
+                ("\n".into(), (11, 12)),
+                ("foo".into(), (16, 19)),
+                ("();".into(), (19, 22)),
+                ("\n".into(), (22, 23)),
+                ("\n".into(), (23, 24)),
+                ("}".into(), (24, 25)),
+            ]
+        );
+
+        let pretok = WhitespaceSplit::default().keep_newlines(true);
+        let res = pretok
+            .pre_tokenize(&mut NormalizedString::from(input))
+            .unwrap();
+        assert_eq!(
+            res,
+            vec![
+                ("fn".into(), (0, 2)),
+                ("main()".into(), (3, 9)),
+                ("{".into(), (10, 11)),
+                ("\n".into(), (11, 12)),
+                ("foo();".into(), (16, 22)),
+                ("\n".into(), (22, 23)),
+                ("\n".into(), (23, 24)),
+                ("}".into(), (24, 25)),
+            ]
+        );
+
+        // Without it, the newlines are just whitespace
+        let res = WhitespaceSplit::default()
+            .pre_tokenize(&mut NormalizedString::from("a\nb"))
+            .unwrap();
+        assert_eq!(res, vec![("a".into(), (0, 1)), ("b".into(), (2, 3))]);
+    }
 }
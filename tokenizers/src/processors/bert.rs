@@ -1,5 +1,6 @@
 use crate::tokenizer::{Encoding, PostProcessor, Result};
 
+#[derive(Clone)]
 pub struct BertProcessing {
     sep: (String, u32),
     cls: (String, u32),
@@ -41,6 +42,8 @@ impl PostProcessor for BertProcessing {
         let offsets = [&[(0, 0)], &encoding.get_offsets()[..], &[(0, 0)]].concat();
         let special_tokens = [&[1u32], &vec![0; encoding.get_ids().len()][..], &[1]].concat();
         let attention_mask = vec![1; ids.len()];
+        let word_starts = [&[true], encoding.get_word_starts(), &[true]].concat();
+        let unknown_flags = [&[false], encoding.get_unknown_flags(), &[false]].concat();
 
         let mut new_encoding = Encoding::new(
             ids,
@@ -49,6 +52,8 @@ impl PostProcessor for BertProcessing {
             offsets,
             special_tokens,
             attention_mask,
+            word_starts,
+            unknown_flags,
             encoding
                 .take_overflowing()
                 .into_iter()
@@ -65,6 +70,8 @@ impl PostProcessor for BertProcessing {
                     let special_tokens =
                         [&[1u32], &vec![0; encoding.get_ids().len()][..], &[1]].concat();
                     let attention_mask = vec![1; ids.len()];
+                    let word_starts = [&[true], encoding.get_word_starts(), &[true]].concat();
+                    let unknown_flags = [&[false], encoding.get_unknown_flags(), &[false]].concat();
 
                     Encoding::new(
                         ids,
@@ -73,6 +80,8 @@ impl PostProcessor for BertProcessing {
                         offsets,
                         special_tokens,
                         attention_mask,
+                        word_starts,
+                        unknown_flags,
                         vec![],
                     )
                 })
@@ -87,6 +96,8 @@ impl PostProcessor for BertProcessing {
             let pair_special_tokens =
                 [&vec![0u32; encoding.get_type_ids().len()][..], &[1]].concat();
             let pair_attention_mask = vec![1; pair_ids.len()];
+            let pair_word_starts = [encoding.get_word_starts(), &[true]].concat();
+            let pair_unknown_flags = [encoding.get_unknown_flags(), &[false]].concat();
 
             let new_pair_encoding = Encoding::new(
                 pair_ids,
@@ -95,6 +106,8 @@ impl PostProcessor for BertProcessing {
                 pair_offsets,
                 pair_special_tokens,
                 pair_attention_mask,
+                pair_word_starts,
+                pair_unknown_flags,
                 encoding
                     .take_overflowing()
                     .into_iter()
@@ -107,6 +120,8 @@ impl PostProcessor for BertProcessing {
                         let pair_special_tokens =
                             [&vec![0u32; encoding.get_type_ids().len()][..], &[1]].concat();
                         let pair_attention_mask = vec![1; pair_ids.len()];
+                        let pair_word_starts = [encoding.get_word_starts(), &[true]].concat();
+                        let pair_unknown_flags = [encoding.get_unknown_flags(), &[false]].concat();
 
                         Encoding::new(
                             pair_ids,
@@ -115,6 +130,8 @@ impl PostProcessor for BertProcessing {
                             pair_offsets,
                             pair_special_tokens,
                             pair_attention_mask,
+                            pair_word_starts,
+                            pair_unknown_flags,
                             vec![],
                         )
                     })
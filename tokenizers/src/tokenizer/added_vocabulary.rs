@@ -0,0 +1,425 @@
+use super::{AddedToken, Error, Model, Offsets, Result};
+use crate::utils::hash;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// The outcome of adding some tokens to the `AddedVocabulary`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AddResult {
+    /// The tokens that have been added
+    pub added: Vec<String>,
+    /// The tokens that were ignored because they already exist, either in the added vocabulary
+    /// or in the `Model` one
+    pub ignored_existing: Vec<String>,
+    /// The number of empty tokens, which are always ignored
+    pub ignored_empty: usize,
+}
+
+/// The vocabulary added on top of the `Model` one: the added tokens and the special tokens.
+/// It takes care of finding these tokens in the inputs, so that they never get split by the
+/// rest of the pipeline.
+///
+/// The special tokens are usually part of the added tokens, but they can also come from the
+/// `Model` vocabulary, in which case they keep their original id.
+#[derive(Clone, Default)]
+pub struct AddedVocabulary {
+    added_tokens: hash::HashMap<AddedToken, u32>,
+    added_tokens_r: hash::HashMap<u32, AddedToken>,
+    special_tokens: hash::HashMap<String, u32>,
+    split_re: Option<regex::Regex>,
+    split_first_chars: hash::HashSet<char>,
+}
+
+impl AddedVocabulary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the number of added tokens
+    pub fn len(&self) -> usize {
+        self.added_tokens.len()
+    }
+
+    /// Whether no token has been added
+    pub fn is_empty(&self) -> bool {
+        self.added_tokens.is_empty()
+    }
+
+    /// Get the range of ids used by the added tokens, from the smallest to the largest one.
+    /// Returns `None` if no token has been added.
+    pub fn id_range(&self) -> Option<RangeInclusive<u32>> {
+        let min = self.added_tokens_r.keys().min()?;
+        let max = self.added_tokens_r.keys().max()?;
+        Some(*min..=*max)
+    }
+
+    /// Get the added tokens, along with their id
+    pub fn get_vocab(&self) -> HashMap<String, u32> {
+        self.added_tokens
+            .iter()
+            .map(|(token, id)| (token.content.clone(), *id))
+            .collect()
+    }
+
+    /// Converts an added or special token in the corresponding id.
+    pub fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.added_tokens
+            .get(&AddedToken::from(token.to_owned()))
+            .or_else(|| self.special_tokens.get(token))
+            .copied()
+    }
+
+    /// Converts an id to the corresponding added token.
+    pub fn id_to_token(&self, id: u32) -> Option<&str> {
+        self.added_tokens_r
+            .get(&id)
+            .map(|token| token.content.as_str())
+    }
+
+    /// Whether the given token has been registered as a special token
+    pub fn is_special(&self, token: &str) -> bool {
+        self.special_tokens.contains_key(token)
+    }
+
+    /// Add the given tokens, giving them the ids following the `Model` vocabulary. The tokens
+    /// already part of any of the vocabularies are ignored.
+    pub fn add_tokens(&mut self, tokens: &[AddedToken], model: &dyn Model) -> usize {
+        self.add_tokens_detailed(tokens, model).added.len()
+    }
+
+    /// Add the given tokens, reporting which ones were actually added, and which ones were
+    /// ignored.
+    pub fn add_tokens_detailed(&mut self, tokens: &[AddedToken], model: &dyn Model) -> AddResult {
+        let mut result = AddResult::default();
+        for token in tokens {
+            if token.content.is_empty() {
+                result.ignored_empty += 1;
+                continue;
+            }
+            if self
+                .token_to_id(&token.content)
+                .or_else(|| model.token_to_id(&token.content))
+                .is_some()
+            {
+                result.ignored_existing.push(token.content.clone());
+                continue;
+            }
+
+            // Some ids might have been given explicitly with `add_token_with_id`
+            let mut new_id = (model.get_vocab_size() + self.added_tokens.len()) as u32;
+            while self.added_tokens_r.contains_key(&new_id) {
+                new_id += 1;
+            }
+            let mut existing = false;
+            let id = self
+                .added_tokens
+                .entry(token.clone())
+                .and_modify(|_| existing = true)
+                .or_insert(new_id);
+            if existing {
+                result.ignored_existing.push(token.content.clone());
+            } else {
+                result.added.push(token.content.clone());
+            }
+
+            // Update the current revert operation
+            self.added_tokens_r
+                .entry(*id)
+                .and_modify(|t| *t = token.clone())
+                .or_insert_with(|| token.clone());
+        }
+
+        self.refresh_added_tokens();
+
+        result
+    }
+
+    /// Add the given tokens like `add_tokens`, unless some of them already exist. In this case,
+    /// nothing gets added, and the error lists the tokens that are part of the `Model`
+    /// vocabulary, and the ones already added (or given twice).
+    pub fn add_tokens_checked(
+        &mut self,
+        tokens: &[AddedToken],
+        model: &dyn Model,
+    ) -> Result<usize> {
+        let mut in_model = vec![];
+        let mut already_added = vec![];
+        let mut seen = hash::HashSet::default();
+        for token in tokens {
+            if self.token_to_id(&token.content).is_some() || !seen.insert(&token.content) {
+                already_added.push(token.content.clone());
+            } else if model.token_to_id(&token.content).is_some() {
+                in_model.push(token.content.clone());
+            }
+        }
+        if !in_model.is_empty() || !already_added.is_empty() {
+            return Err(Box::new(Error::TokensCollision(in_model, already_added)));
+        }
+
+        Ok(self.add_tokens(tokens, model))
+    }
+
+    /// Register the given tokens as special tokens, adding the ones that are not already part
+    /// of any of the vocabularies.
+    pub fn add_special_tokens<T: AsRef<str>>(&mut self, tokens: &[T], model: &dyn Model) -> usize {
+        let added_tokens = tokens
+            .iter()
+            .map(|t| AddedToken::from(t.as_ref().to_owned()))
+            .collect::<Vec<_>>();
+
+        let added = self.add_tokens(&added_tokens, model);
+        for token in tokens {
+            let token = token.as_ref();
+            if let Some(id) = self.token_to_id(token).or_else(|| model.token_to_id(token)) {
+                self.special_tokens.entry(token.to_owned()).or_insert(id);
+            }
+        }
+
+        self.refresh_added_tokens();
+
+        added
+    }
+
+    /// Add the given token using the given id. An error is returned if the token or the id is
+    /// already part of any of the vocabularies.
+    pub fn add_token_with_id(
+        &mut self,
+        token: AddedToken,
+        id: u32,
+        model: &dyn Model,
+    ) -> Result<()> {
+        if token.content.is_empty()
+            || self
+                .token_to_id(&token.content)
+                .or_else(|| model.token_to_id(&token.content))
+                .is_some()
+        {
+            return Err(Box::new(Error::TokenAlreadyExists(token.content)));
+        }
+        if let Some(existing) = self
+            .id_to_token(id)
+            .map(|t| t.to_owned())
+            .or_else(|| model.id_to_token(id))
+        {
+            return Err(Box::new(Error::IdAlreadyUsed(id, existing)));
+        }
+
+        self.added_tokens.insert(token.clone(), id);
+        self.added_tokens_r.insert(id, token);
+        self.refresh_added_tokens();
+
+        Ok(())
+    }
+
+    /// Register the given token as a special token, using the given id. An error is returned if
+    /// the token or the id is already part of any of the vocabularies.
+    pub fn add_special_token_with_id(
+        &mut self,
+        token: &str,
+        id: u32,
+        model: &dyn Model,
+    ) -> Result<()> {
+        self.add_token_with_id(AddedToken::from(token.to_owned()), id, model)?;
+        self.special_tokens.insert(token.to_owned(), id);
+        self.refresh_added_tokens();
+
+        Ok(())
+    }
+
+    fn refresh_added_tokens(&mut self) {
+        // We rebuild the regex here everytime on purpose, because the added tokens may
+        // have changed
+        let special_tokens = self
+            .special_tokens
+            .keys()
+            .map(|t| AddedToken {
+                content: t.to_owned(),
+                single_word: true,
+            })
+            .collect::<Vec<_>>();
+        // The regex alternation stops at the first token that matches, so the longest tokens
+        // need to come first. Otherwise, a token that contains another one (like `<mask>` and
+        // `<mask`) could get split and then processed by the pre-tokenizer.
+        let mut tokens = self
+            .added_tokens
+            .keys()
+            .chain(special_tokens.iter())
+            .collect::<Vec<_>>();
+        tokens.sort_by(|a, b| {
+            b.content
+                .len()
+                .cmp(&a.content.len())
+                .then_with(|| a.content.cmp(&b.content))
+        });
+        self.split_first_chars = tokens
+            .iter()
+            .filter_map(|token| token.content.chars().next())
+            .collect();
+        let added_tokens = tokens
+            .into_iter()
+            .map(|token| {
+                if token.single_word {
+                    let first_b = token
+                        .content
+                        .chars()
+                        .next()
+                        .map(|c| {
+                            if regex_syntax::is_word_character(c) {
+                                r"\b"
+                            } else {
+                                ""
+                            }
+                        })
+                        .unwrap();
+                    let last_b = token
+                        .content
+                        .chars()
+                        .last()
+                        .map(|c| {
+                            if regex_syntax::is_word_character(c) {
+                                r"\b"
+                            } else {
+                                ""
+                            }
+                        })
+                        .unwrap();
+                    format!(r"{}{}{}", first_b, regex::escape(&token.content), last_b)
+                } else {
+                    regex::escape(&token.content)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if added_tokens.is_empty() {
+            self.split_re = None;
+        } else {
+            self.split_re =
+                Some(regex::Regex::new(&format!(r"({})", added_tokens.join("|"))).unwrap());
+        }
+    }
+
+    /// Find the added and special tokens in the given text. Returns their offsets (in bytes)
+    /// along with their id, in order of appearance. The matches are found lazily, while
+    /// iterating.
+    pub fn find_matches<'a>(&'a self, text: &'a str) -> impl Iterator<Item = (Offsets, u32)> + 'a {
+        // Running the regex is expensive, so we first check that an added token can start
+        // somewhere in the text. Most texts don't contain any.
+        let split_re = self
+            .split_re
+            .as_ref()
+            .filter(|_| text.chars().any(|c| self.split_first_chars.contains(&c)));
+
+        split_re
+            .into_iter()
+            .flat_map(move |split_re| split_re.find_iter(text))
+            .filter_map(move |m| {
+                let id = self
+                    .special_tokens
+                    .get(m.as_str())
+                    .copied()
+                    .or_else(|| self.token_to_id(m.as_str()))?;
+                Some(((m.start(), m.end()), id))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::wordlevel::{WordLevel, WordLevelBuilder};
+
+    fn get_model() -> WordLevel {
+        WordLevelBuilder::new()
+            .vocab(
+                [("[UNK]", 0), ("hello", 1), ("[SEP]", 2)]
+                    .iter()
+                    .map(|(t, id)| (t.to_string(), *id))
+                    .collect(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn add_and_query() {
+        let model = get_model();
+        let mut vocab = AddedVocabulary::new();
+        assert_eq!(
+            vocab.add_tokens(&[AddedToken::from("hello".into())], &model),
+            0
+        );
+        assert_eq!(
+            vocab.add_tokens(&[AddedToken::from("<tok>".into())], &model),
+            1
+        );
+        assert_eq!(vocab.add_special_tokens(&["[SEP]", "[CLS]"], &model), 1);
+
+        assert_eq!(vocab.len(), 2);
+        assert_eq!(vocab.token_to_id("<tok>"), Some(3));
+        assert_eq!(vocab.token_to_id("[CLS]"), Some(4));
+        // Special tokens from the model keep their id
+        assert_eq!(vocab.token_to_id("[SEP]"), Some(2));
+        assert_eq!(vocab.id_to_token(4), Some("[CLS]"));
+        assert_eq!(vocab.id_to_token(2), None);
+        assert!(vocab.is_special("[SEP]"));
+        assert!(vocab.is_special("[CLS]"));
+        assert!(!vocab.is_special("<tok>"));
+        assert_eq!(vocab.id_range(), Some(3..=4));
+        assert_eq!(AddedVocabulary::new().id_range(), None);
+    }
+
+    #[test]
+    fn add_checked() {
+        let model = get_model();
+        let mut vocab = AddedVocabulary::new();
+        vocab.add_tokens(&[AddedToken::from("<tok>".into())], &model);
+
+        let err = vocab
+            .add_tokens_checked(
+                &[
+                    AddedToken::from("<new>".into()),
+                    AddedToken::from("hello".into()),
+                    AddedToken::from("<tok>".into()),
+                    AddedToken::from("<other>".into()),
+                    AddedToken::from("<other>".into()),
+                ],
+                &model,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            Error::TokensCollision(vec!["hello".into()], vec!["<tok>".into(), "<other>".into()])
+                .to_string()
+        );
+        // Nothing got added
+        assert_eq!(vocab.len(), 1);
+
+        assert_eq!(
+            vocab
+                .add_tokens_checked(&[AddedToken::from("<new>".into())], &model)
+                .unwrap(),
+            1
+        );
+        assert_eq!(vocab.token_to_id("<new>"), Some(4));
+    }
+
+    #[test]
+    fn find_matches() {
+        let model = get_model();
+        let mut vocab = AddedVocabulary::new();
+        assert_eq!(vocab.find_matches("[CLS] hello").next(), None);
+
+        vocab.add_tokens(&[AddedToken::from("<tok>".into())], &model);
+        vocab.add_special_tokens(&["[SEP]", "[CLS]"], &model);
+        assert_eq!(
+            vocab
+                .find_matches("[CLS] hello<tok> [SEP]")
+                .collect::<Vec<_>>(),
+            vec![((0, 5), 4), ((11, 16), 3), ((17, 22), 2)]
+        );
+        assert_eq!(vocab.find_matches("hello").next(), None);
+        assert_eq!(
+            vocab.find_matches("<tok><tok>[SEP]").collect::<Vec<_>>(),
+            vec![((0, 5), 3), ((5, 10), 3), ((10, 15), 2)]
+        );
+    }
+}
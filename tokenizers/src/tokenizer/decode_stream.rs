@@ -0,0 +1,89 @@
+use super::{Error, Result, Tokenizer};
+
+/// The longest incomplete UTF-8 sequence is 3 bytes long, so when more ids than this are held
+/// back, they can't be completed anymore.
+const MAX_HELD_BACK_IDS: usize = 3;
+
+/// Decodes ids one at a time, as they get generated, and returns the new text as soon as it is
+/// complete. With the byte-level decoders, a char can span several tokens, and decoding only
+/// some of them gives an invalid UTF-8 sequence, replaced by `U+FFFD`. Such text is held back
+/// until the following ids complete it, for at most 3 ids (the longest incomplete UTF-8
+/// sequence). After this, the text is returned with its `U+FFFD`.
+///
+/// Each new id gets decoded along with the ids of the last text returned, so that the
+/// `Decoder` can handle what depends on the previous tokens (like the spaces between words).
+pub struct DecodeStream<'t> {
+    tokenizer: &'t Tokenizer,
+    skip_special_tokens: bool,
+    /// The ids not decoded yet, preceded by the ones of the last returned text
+    ids: Vec<u32>,
+    /// The text of the first `prefix_index` ids
+    prefix: String,
+    prefix_index: usize,
+}
+
+impl<'t> DecodeStream<'t> {
+    pub(super) fn new(tokenizer: &'t Tokenizer, skip_special_tokens: bool) -> Self {
+        DecodeStream {
+            tokenizer,
+            skip_special_tokens,
+            ids: vec![],
+            prefix: String::new(),
+            prefix_index: 0,
+        }
+    }
+
+    /// Add the given id, returning the new text if it is complete. Otherwise, the id is kept
+    /// until the next ones complete it.
+    pub fn step(&mut self, id: u32) -> Result<Option<String>> {
+        self.ids.push(id);
+        let text = self.decode()?;
+        let held_back = self.ids.len() - self.prefix_index;
+        if text.len() <= self.prefix.len()
+            || (text.ends_with('\u{FFFD}') && held_back <= MAX_HELD_BACK_IDS)
+        {
+            return Ok(None);
+        }
+
+        let new_text = self.new_text(text)?;
+        self.ids.drain(..self.prefix_index);
+        self.prefix = self.decode()?;
+        self.prefix_index = self.ids.len();
+
+        Ok(Some(new_text))
+    }
+
+    /// Return the text of the ids held back so far, even if it is incomplete (the invalid
+    /// sequences are then replaced by `U+FFFD`). This is useful once the generation is over.
+    pub fn finish(&mut self) -> Result<Option<String>> {
+        let text = self.decode()?;
+        let new_text = if text.len() > self.prefix.len() {
+            Some(self.new_text(text)?)
+        } else {
+            None
+        };
+
+        self.ids.clear();
+        self.prefix.clear();
+        self.prefix_index = 0;
+
+        Ok(new_text)
+    }
+
+    fn decode(&self) -> Result<String> {
+        self.tokenizer
+            .decode_iter(self.ids.iter().copied(), self.skip_special_tokens)
+    }
+
+    /// Remove the already returned prefix from the given text
+    fn new_text(&self, text: String) -> Result<String> {
+        if text.starts_with(&self.prefix) {
+            Ok(text[self.prefix.len()..].to_owned())
+        } else {
+            Err(Box::new(Error::DecodeStreamPrefix(
+                self.prefix.clone(),
+                text,
+            )))
+        }
+    }
+}
@@ -1,5 +1,24 @@
 use crate::utils::padding::PaddingDirection;
+use crate::utils::truncation::TruncationDirection;
 use rayon::prelude::*;
+use serde::{
+    de::{self, IgnoredAny, MapAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// How the special tokens are marked in the `special_tokens_mask` of an `Encoding`. Some models
+/// expect the special tokens to be marked with `1`, and others with `0`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SpecialMaskConvention {
+    /// `1` for the special tokens, `0` for the other ones. This is the default.
+    #[default]
+    SpecialIsOne,
+    /// `0` for the special tokens, `1` for the other ones
+    SpecialIsZero,
+}
 
 /// Represents the output of a `Tokenizer`.
 #[derive(Default, PartialEq, Debug, Clone)]
@@ -10,7 +29,10 @@ pub struct Encoding {
     offsets: Vec<(usize, usize)>,
     special_tokens_mask: Vec<u32>,
     attention_mask: Vec<u32>,
+    word_starts: Vec<bool>,
+    unknown_flags: Vec<bool>,
     overflowing: Vec<Encoding>,
+    num_truncated: usize,
 }
 impl Encoding {
     #[allow(clippy::too_many_arguments)]
@@ -21,6 +43,8 @@ impl Encoding {
         offsets: Vec<(usize, usize)>,
         special_tokens_mask: Vec<u32>,
         attention_mask: Vec<u32>,
+        word_starts: Vec<bool>,
+        unknown_flags: Vec<bool>,
         overflowing: Vec<Encoding>,
     ) -> Self {
         Encoding {
@@ -30,7 +54,10 @@ impl Encoding {
             offsets,
             special_tokens_mask,
             attention_mask,
+            word_starts,
+            unknown_flags,
             overflowing,
+            num_truncated: 0,
         }
     }
 
@@ -54,14 +81,51 @@ impl Encoding {
         &mut self.offsets
     }
 
+    /// Get the offsets of each token in the input, or `None` for the special tokens (added by
+    /// the `PostProcessor`, or for padding) since they don't correspond to any part of it.
+    /// Their offsets are always `(0, 0)`, which could otherwise be mistaken for a real span.
+    pub fn get_source_offsets(&self) -> Vec<Option<(usize, usize)>> {
+        self.offsets
+            .iter()
+            .zip(&self.special_tokens_mask)
+            .map(|(offsets, special)| if *special == 1 { None } else { Some(*offsets) })
+            .collect()
+    }
+
+    /// The special tokens mask, with `1` for the special tokens (including the padding)
     pub fn get_special_tokens_mask(&self) -> &[u32] {
         &self.special_tokens_mask
     }
 
+    /// The special tokens mask, using the given `SpecialMaskConvention`
+    pub fn get_special_tokens_mask_with(&self, convention: SpecialMaskConvention) -> Vec<u32> {
+        match convention {
+            SpecialMaskConvention::SpecialIsOne => self.special_tokens_mask.clone(),
+            SpecialMaskConvention::SpecialIsZero => self
+                .special_tokens_mask
+                .iter()
+                .map(|mask| 1 - mask)
+                .collect(),
+        }
+    }
+
     pub fn get_attention_mask(&self) -> &[u32] {
         &self.attention_mask
     }
 
+    /// Whether each token starts a new word (the first token of a pre-token), or continues one.
+    /// Special tokens are considered as their own word, and padding tokens are not.
+    pub fn get_word_starts(&self) -> &[bool] {
+        &self.word_starts
+    }
+
+    /// Whether each token is the unknown token, produced by the `Model` for a part of the input
+    /// it couldn't tokenize. These tokens cover their whole part of the input, without any
+    /// subword information.
+    pub fn get_unknown_flags(&self) -> &[bool] {
+        &self.unknown_flags
+    }
+
     pub fn get_overflowing(&self) -> &Vec<Encoding> {
         &self.overflowing
     }
@@ -70,10 +134,190 @@ impl Encoding {
         std::mem::replace(&mut self.overflowing, vec![])
     }
 
-    /// Truncate the current `Encoding`.
+    /// Whether some tokens have been removed from this `Encoding` by the truncation. In this
+    /// case, the offsets don't cover the whole input.
+    pub fn is_truncated(&self) -> bool {
+        self.num_truncated > 0
+    }
+
+    /// The number of tokens removed from this `Encoding` by the truncation. They can be found
+    /// in the overflowing parts.
+    pub fn get_num_truncated(&self) -> usize {
+        self.num_truncated
+    }
+
+    pub(crate) fn set_num_truncated(&mut self, num_truncated: usize) {
+        self.num_truncated = num_truncated;
+    }
+
+    /// The ids as `i64`, as expected by most tensor libraries
+    pub fn get_ids_i64(&self) -> Vec<i64> {
+        to_i64(&self.ids)
+    }
+
+    /// The type ids as `i64`, as expected by most tensor libraries
+    pub fn get_type_ids_i64(&self) -> Vec<i64> {
+        to_i64(&self.type_ids)
+    }
+
+    /// The special tokens mask as `i64`, as expected by most tensor libraries
+    pub fn get_special_tokens_mask_i64(&self) -> Vec<i64> {
+        to_i64(&self.special_tokens_mask)
+    }
+
+    /// The attention mask as `i64`, as expected by most tensor libraries
+    pub fn get_attention_mask_i64(&self) -> Vec<i64> {
+        to_i64(&self.attention_mask)
+    }
+
+    /// The attention mask as `u8`, which takes a quarter of the memory of the `u32` one
+    pub fn get_attention_mask_u8(&self) -> Vec<u8> {
+        self.attention_mask
+            .iter()
+            .map(|v| (*v != 0) as u8)
+            .collect()
+    }
+
+    /// The attention mask packed with 1 bit per token: the token `i` uses the bit `i % 8` (least
+    /// significant first) of the byte `i / 8`. The unused bits of the last byte are 0.
+    pub fn get_attention_mask_bits(&self) -> Vec<u8> {
+        let mut bits = vec![0u8; self.attention_mask.len().div_ceil(8)];
+        for (i, v) in self.attention_mask.iter().enumerate() {
+            if *v != 0 {
+                bits[i / 8] |= 1 << (i % 8);
+            }
+        }
+        bits
+    }
+
+    /// Flatten the values selected by `values` (like `Encoding::get_ids`) for a whole batch of
+    /// encodings, in row-major order. Returns them along with the shape of the batch
+    /// `[batch_size, sequence_length]`, or `None` if the encodings don't all have the same
+    /// length (they need to be padded first).
+    ///
+    /// ```
+    /// # use tokenizers::tokenizer::Encoding;
+    /// # fn example(encodings: &[Encoding]) {
+    /// let (input_ids, shape) = Encoding::flatten_batch_i64(encodings, Encoding::get_ids).unwrap();
+    /// # }
+    /// ```
+    pub fn flatten_batch_i64<F>(encodings: &[Encoding], values: F) -> Option<(Vec<i64>, [usize; 2])>
+    where
+        F: Fn(&Encoding) -> &[u32],
+    {
+        let len = encodings.first().map_or(0, |e| e.get_ids().len());
+        if encodings.iter().any(|e| e.get_ids().len() != len) {
+            return None;
+        }
+
+        let mut flat = Vec::with_capacity(encodings.len() * len);
+        for encoding in encodings {
+            flat.extend(values(encoding).iter().map(|v| i64::from(*v)));
+        }
+        Some((flat, [encodings.len(), len]))
+    }
+
+    /// Convert to a `SharedEncoding`, using the tokens of the given vocabulary (id -> token)
+    /// instead of our own whenever possible.
+    pub(crate) fn into_shared(self, vocab_r: &HashMap<u32, Arc<str>>) -> SharedEncoding {
+        let tokens = self
+            .ids
+            .iter()
+            .zip(self.tokens)
+            .map(|(id, token)| match vocab_r.get(id) {
+                Some(shared) if **shared == *token => shared.clone(),
+                _ => Arc::from(token),
+            })
+            .collect();
+
+        SharedEncoding {
+            ids: self.ids,
+            type_ids: self.type_ids,
+            tokens,
+            offsets: self.offsets,
+            special_tokens_mask: self.special_tokens_mask,
+            attention_mask: self.attention_mask,
+            word_starts: self.word_starts,
+            unknown_flags: self.unknown_flags,
+            overflowing: self
+                .overflowing
+                .into_iter()
+                .map(|o| o.into_shared(vocab_r))
+                .collect(),
+            num_truncated: self.num_truncated,
+        }
+    }
+
+    /// Split the `Encoding` in two at the given index, returning the tokens after it. The
+    /// overflowing parts stay with the first part.
+    pub(crate) fn split_off(&mut self, at: usize) -> Encoding {
+        Encoding {
+            ids: self.ids.split_off(at),
+            type_ids: self.type_ids.split_off(at),
+            tokens: self.tokens.split_off(at),
+            offsets: self.offsets.split_off(at),
+            special_tokens_mask: self.special_tokens_mask.split_off(at),
+            attention_mask: self.attention_mask.split_off(at),
+            word_starts: self.word_starts.split_off(at),
+            unknown_flags: self.unknown_flags.split_off(at),
+            overflowing: vec![],
+            num_truncated: 0,
+        }
+    }
+
+    /// Get the tokens in the given range as a new `Encoding`, with all their information. The
+    /// overflowing parts are not kept. Returns `None` if the range is out of bounds.
+    pub fn get_range(&self, range: std::ops::Range<usize>) -> Option<Encoding> {
+        if range.start > range.end || range.end > self.ids.len() {
+            return None;
+        }
+        Some(Encoding {
+            ids: self.ids[range.clone()].to_vec(),
+            type_ids: self.type_ids[range.clone()].to_vec(),
+            tokens: self.tokens[range.clone()].to_vec(),
+            offsets: self.offsets[range.clone()].to_vec(),
+            special_tokens_mask: self.special_tokens_mask[range.clone()].to_vec(),
+            attention_mask: self.attention_mask[range.clone()].to_vec(),
+            word_starts: self.word_starts[range.clone()].to_vec(),
+            unknown_flags: self.unknown_flags[range].to_vec(),
+            overflowing: vec![],
+            num_truncated: 0,
+        })
+    }
+
+    /// Truncate the current `Encoding` to `max_len` tokens, removing them from the given side.
+    /// The removed tokens go in the overflowing parts, each of them sharing `stride` tokens with
+    /// the part next to it. With `TruncationDirection::Left`, the overflowing parts go backward,
+    /// from the end to the beginning of the sequence.
     ///
     /// Panic if `stride >= max_len` or `max_len == 0`.
-    pub fn truncate(&mut self, max_len: usize, stride: usize) {
+    pub fn truncate(&mut self, max_len: usize, stride: usize, direction: TruncationDirection) {
+        match direction {
+            TruncationDirection::Right => self.truncate_right(max_len, stride),
+            TruncationDirection::Left => {
+                // Truncating the reversed sequence from the right is the same as truncating
+                // this one from the left
+                self.reverse();
+                self.truncate_right(max_len, stride);
+                self.reverse();
+                self.overflowing.iter_mut().for_each(Encoding::reverse);
+            }
+        }
+    }
+
+    /// Reverse the order of the tokens, leaving the overflowing parts untouched
+    fn reverse(&mut self) {
+        self.ids.reverse();
+        self.type_ids.reverse();
+        self.tokens.reverse();
+        self.offsets.reverse();
+        self.special_tokens_mask.reverse();
+        self.attention_mask.reverse();
+        self.word_starts.reverse();
+        self.unknown_flags.reverse();
+    }
+
+    fn truncate_right(&mut self, max_len: usize, stride: usize) {
         if max_len >= self.ids.len() {
             return;
         }
@@ -87,6 +331,8 @@ impl Encoding {
         let o_offsets = self.offsets.split_off(max_len);
         let o_spe_toks = self.special_tokens_mask.split_off(max_len);
         let o_attent = self.attention_mask.split_off(max_len);
+        let o_word_starts = self.word_starts.split_off(max_len);
+        let o_unknown_flags = self.unknown_flags.split_off(max_len);
 
         // Now we need to separate the overflowing part into as many Encoding as needed
         assert!(stride < max_len);
@@ -137,7 +383,22 @@ impl Encoding {
                     part_id,
                     stride,
                 ),
+                word_starts: get_current_part(
+                    &prev_encoding.word_starts,
+                    &o_word_starts,
+                    part_size,
+                    part_id,
+                    stride,
+                ),
+                unknown_flags: get_current_part(
+                    &prev_encoding.unknown_flags,
+                    &o_unknown_flags,
+                    part_size,
+                    part_id,
+                    stride,
+                ),
                 overflowing: vec![],
+                num_truncated: 0,
             };
 
             part_id += 1;
@@ -146,6 +407,7 @@ impl Encoding {
         }
 
         self.overflowing = overflowing;
+        self.num_truncated += o_ids.len();
     }
 
     /// Merge ourself with the given `Encoding`. Happens in place.
@@ -193,7 +455,35 @@ impl Encoding {
         );
         self.special_tokens_mask.extend(pair.special_tokens_mask);
         self.attention_mask.extend(pair.attention_mask);
+        self.word_starts.extend(pair.word_starts);
+        self.unknown_flags.extend(pair.unknown_flags);
         self.overflowing = overflowings;
+        self.num_truncated += pair.num_truncated;
+    }
+
+    /// Append the given `Encoding` to ourself, with a separator token inbetween. The separator is
+    /// marked as a special token. Offsets are kept relative to each original input.
+    pub fn append_with_separator(
+        &mut self,
+        other: Encoding,
+        sep_id: u32,
+        sep_token: String,
+        sep_type_id: u32,
+    ) {
+        let separator = Encoding {
+            ids: vec![sep_id],
+            type_ids: vec![sep_type_id],
+            tokens: vec![sep_token],
+            offsets: vec![(0, 0)],
+            special_tokens_mask: vec![1],
+            attention_mask: vec![1],
+            word_starts: vec![true],
+            unknown_flags: vec![false],
+            overflowing: vec![],
+            num_truncated: 0,
+        };
+        self.merge_with(separator, false);
+        self.merge_with(other, false);
     }
 
     pub fn pad(
@@ -242,6 +532,14 @@ impl Encoding {
                     .map(|_| (0, 0))
                     .chain(self.offsets.drain(..))
                     .collect();
+                self.word_starts = (0..pad_length)
+                    .map(|_| false)
+                    .chain(self.word_starts.drain(..))
+                    .collect();
+                self.unknown_flags = (0..pad_length)
+                    .map(|_| false)
+                    .chain(self.unknown_flags.drain(..))
+                    .collect();
             }
             PaddingDirection::Right => {
                 self.ids.extend((0..pad_length).map(|_| pad_id));
@@ -251,8 +549,318 @@ impl Encoding {
                 self.attention_mask.extend((0..pad_length).map(|_| 0));
                 self.special_tokens_mask.extend((0..pad_length).map(|_| 1));
                 self.offsets.extend((0..pad_length).map(|_| (0, 0)));
+                self.word_starts.extend((0..pad_length).map(|_| false));
+                self.unknown_flags.extend((0..pad_length).map(|_| false));
+            }
+        }
+    }
+
+    /// List the differences between ourself and the given `Encoding`, in a human-readable way.
+    /// This is empty when both are equal, and mostly helps understanding why they are not.
+    pub fn diff(&self, other: &Encoding) -> Vec<String> {
+        let mut differences = vec![];
+        self.diff_into("", other, &mut differences);
+        differences
+    }
+
+    fn diff_into(&self, prefix: &str, other: &Encoding, differences: &mut Vec<String>) {
+        diff_values(prefix, "ids", &self.ids, &other.ids, differences);
+        diff_values(
+            prefix,
+            "type_ids",
+            &self.type_ids,
+            &other.type_ids,
+            differences,
+        );
+        diff_values(prefix, "tokens", &self.tokens, &other.tokens, differences);
+        diff_values(
+            prefix,
+            "offsets",
+            &self.offsets,
+            &other.offsets,
+            differences,
+        );
+        diff_values(
+            prefix,
+            "special_tokens_mask",
+            &self.special_tokens_mask,
+            &other.special_tokens_mask,
+            differences,
+        );
+        diff_values(
+            prefix,
+            "attention_mask",
+            &self.attention_mask,
+            &other.attention_mask,
+            differences,
+        );
+        diff_values(
+            prefix,
+            "word_starts",
+            &self.word_starts,
+            &other.word_starts,
+            differences,
+        );
+        diff_values(
+            prefix,
+            "unknown_flags",
+            &self.unknown_flags,
+            &other.unknown_flags,
+            differences,
+        );
+        if self.num_truncated != other.num_truncated {
+            differences.push(format!(
+                "{}num_truncated: {} != {}",
+                prefix, self.num_truncated, other.num_truncated
+            ));
+        }
+        if self.overflowing.len() != other.overflowing.len() {
+            differences.push(format!(
+                "{}overflowing: {} encodings != {} encodings",
+                prefix,
+                self.overflowing.len(),
+                other.overflowing.len()
+            ));
+        }
+        for (i, (a, b)) in self.overflowing.iter().zip(&other.overflowing).enumerate() {
+            a.diff_into(&format!("{}overflowing[{}].", prefix, i), b, differences);
+        }
+    }
+}
+
+const FIELDS: &[&str] = &[
+    "ids",
+    "type_ids",
+    "tokens",
+    "offsets",
+    "special_tokens_mask",
+    "attention_mask",
+    "word_starts",
+    "unknown_flags",
+    "overflowing",
+    "num_truncated",
+];
+
+impl Serialize for Encoding {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut encoding = serializer.serialize_struct("Encoding", FIELDS.len())?;
+        encoding.serialize_field("ids", &self.ids)?;
+        encoding.serialize_field("type_ids", &self.type_ids)?;
+        encoding.serialize_field("tokens", &self.tokens)?;
+        encoding.serialize_field("offsets", &self.offsets)?;
+        encoding.serialize_field("special_tokens_mask", &self.special_tokens_mask)?;
+        encoding.serialize_field("attention_mask", &self.attention_mask)?;
+        encoding.serialize_field("word_starts", &self.word_starts)?;
+        encoding.serialize_field("unknown_flags", &self.unknown_flags)?;
+        encoding.serialize_field("overflowing", &self.overflowing)?;
+        encoding.serialize_field("num_truncated", &self.num_truncated)?;
+        encoding.end()
+    }
+}
+impl<'de> Deserialize<'de> for Encoding {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Encoding", FIELDS, EncodingVisitor)
+    }
+}
+struct EncodingVisitor;
+impl<'de> Visitor<'de> for EncodingVisitor {
+    type Value = Encoding;
+
+    fn expecting(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "an Encoding")
+    }
+
+    fn visit_map<V>(self, mut map: V) -> std::result::Result<Self::Value, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        let mut encoding = Encoding::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_ref() {
+                "ids" => encoding.ids = map.next_value()?,
+                "type_ids" => encoding.type_ids = map.next_value()?,
+                "tokens" => encoding.tokens = map.next_value()?,
+                "offsets" => encoding.offsets = map.next_value()?,
+                "special_tokens_mask" => encoding.special_tokens_mask = map.next_value()?,
+                "attention_mask" => encoding.attention_mask = map.next_value()?,
+                "word_starts" => encoding.word_starts = map.next_value()?,
+                "unknown_flags" => encoding.unknown_flags = map.next_value()?,
+                "overflowing" => encoding.overflowing = map.next_value()?,
+                "num_truncated" => encoding.num_truncated = map.next_value()?,
+                // Ignore the fields we don't know about
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        // All the values describe the same tokens
+        let len = encoding.ids.len();
+        let lengths = [
+            ("type_ids", encoding.type_ids.len()),
+            ("tokens", encoding.tokens.len()),
+            ("offsets", encoding.offsets.len()),
+            ("special_tokens_mask", encoding.special_tokens_mask.len()),
+            ("attention_mask", encoding.attention_mask.len()),
+            ("word_starts", encoding.word_starts.len()),
+            ("unknown_flags", encoding.unknown_flags.len()),
+        ];
+        for (name, field_len) in lengths.iter() {
+            if *field_len != len {
+                return Err(de::Error::invalid_length(
+                    *field_len,
+                    &format!("{} values in {}, like ids", len, name).as_str(),
+                ));
             }
         }
+        Ok(encoding)
+    }
+}
+
+/// Some storage for the vectors of an `Encoding`, reused by `Tokenizer::encode_into` to avoid
+/// allocating them for each input. The `Encoding`s that are not needed anymore can give their
+/// vectors back with `recycle`. Each thread needs its own `EncodeBuffers`.
+#[derive(Debug, Default)]
+pub struct EncodeBuffers {
+    pub(crate) ids: Vec<u32>,
+    pub(crate) type_ids: Vec<u32>,
+    pub(crate) tokens: Vec<String>,
+    pub(crate) offsets: Vec<(usize, usize)>,
+    pub(crate) special_tokens_mask: Vec<u32>,
+    pub(crate) attention_mask: Vec<u32>,
+    pub(crate) word_starts: Vec<bool>,
+    pub(crate) unknown_flags: Vec<bool>,
+    /// The start of each pre-token, only used while tokenizing
+    pub(crate) pre_token_starts: Vec<usize>,
+}
+
+impl EncodeBuffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep the vectors of the given `Encoding`, to reuse them for the next inputs. The vectors
+    /// already in the buffers are only replaced by larger ones.
+    pub fn recycle(&mut self, encoding: Encoding) {
+        fn keep_largest<T>(buffer: &mut Vec<T>, mut v: Vec<T>) {
+            if v.capacity() > buffer.capacity() {
+                v.clear();
+                *buffer = v;
+            }
+        }
+        keep_largest(&mut self.ids, encoding.ids);
+        keep_largest(&mut self.type_ids, encoding.type_ids);
+        keep_largest(&mut self.tokens, encoding.tokens);
+        keep_largest(&mut self.offsets, encoding.offsets);
+        keep_largest(&mut self.special_tokens_mask, encoding.special_tokens_mask);
+        keep_largest(&mut self.attention_mask, encoding.attention_mask);
+        keep_largest(&mut self.word_starts, encoding.word_starts);
+        keep_largest(&mut self.unknown_flags, encoding.unknown_flags);
+    }
+
+    /// Take the given buffer, empty and with room for at least `len` elements
+    pub(crate) fn take<T>(buffer: &mut Vec<T>, len: usize) -> Vec<T> {
+        let mut v = std::mem::take(buffer);
+        v.clear();
+        v.reserve(len);
+        v
+    }
+}
+
+/// An `Encoding` whose tokens are shared with the vocabulary, as given by
+/// `Tokenizer::encode_batch_shared`. Cloning a token is cheap, and the same token appearing many
+/// times is only stored once.
+#[derive(Default, PartialEq, Debug, Clone)]
+pub struct SharedEncoding {
+    ids: Vec<u32>,
+    type_ids: Vec<u32>,
+    tokens: Vec<Arc<str>>,
+    offsets: Vec<(usize, usize)>,
+    special_tokens_mask: Vec<u32>,
+    attention_mask: Vec<u32>,
+    word_starts: Vec<bool>,
+    unknown_flags: Vec<bool>,
+    overflowing: Vec<SharedEncoding>,
+    num_truncated: usize,
+}
+impl SharedEncoding {
+    pub fn get_tokens(&self) -> &[Arc<str>] {
+        &self.tokens
+    }
+
+    pub fn get_ids(&self) -> &[u32] {
+        &self.ids
+    }
+
+    pub fn get_type_ids(&self) -> &[u32] {
+        &self.type_ids
+    }
+
+    pub fn get_offsets(&self) -> &[(usize, usize)] {
+        &self.offsets
+    }
+
+    pub fn get_special_tokens_mask(&self) -> &[u32] {
+        &self.special_tokens_mask
+    }
+
+    pub fn get_attention_mask(&self) -> &[u32] {
+        &self.attention_mask
+    }
+
+    pub fn get_word_starts(&self) -> &[bool] {
+        &self.word_starts
+    }
+
+    pub fn get_unknown_flags(&self) -> &[bool] {
+        &self.unknown_flags
+    }
+
+    pub fn get_overflowing(&self) -> &[SharedEncoding] {
+        &self.overflowing
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.num_truncated > 0
+    }
+
+    pub fn get_num_truncated(&self) -> usize {
+        self.num_truncated
+    }
+}
+
+#[inline]
+fn to_i64(values: &[u32]) -> Vec<i64> {
+    values.iter().map(|v| i64::from(*v)).collect()
+}
+
+/// Describe each difference between `a` and `b`: their length, then every mismatching value
+fn diff_values<T: PartialEq + std::fmt::Debug>(
+    prefix: &str,
+    name: &str,
+    a: &[T],
+    b: &[T],
+    differences: &mut Vec<String>,
+) {
+    if a.len() != b.len() {
+        differences.push(format!(
+            "{}{}: length {} != {}",
+            prefix,
+            name,
+            a.len(),
+            b.len()
+        ));
+    }
+    for (i, (x, y)) in a.iter().zip(b).enumerate() {
+        if x != y {
+            differences.push(format!("{}{}[{}]: {:?} != {:?}", prefix, name, i, x, y));
+        }
     }
 }
 
@@ -277,6 +885,108 @@ fn get_current_part<T: Clone>(
 mod tests {
     use super::*;
 
+    #[test]
+    fn i64_values() {
+        let encoding = Encoding::new(
+            vec![1, 10, 2],
+            vec![0, 0, 1],
+            vec!["[CLS]".into(), "a".into(), "[SEP]".into()],
+            vec![(0, 0), (0, 1), (0, 0)],
+            vec![1, 0, 1],
+            vec![1; 3],
+            vec![true; 3],
+            vec![false; 3],
+            vec![],
+        );
+        assert_eq!(encoding.get_ids_i64(), vec![1i64, 10, 2]);
+        assert_eq!(encoding.get_type_ids_i64(), vec![0i64, 0, 1]);
+        assert_eq!(encoding.get_special_tokens_mask_i64(), vec![1i64, 0, 1]);
+        assert_eq!(encoding.get_attention_mask_i64(), vec![1i64; 3]);
+
+        let mut padded = encoding.clone();
+        padded.truncate(2, 0, TruncationDirection::Right);
+        padded.pad(3, 0, 0, "[PAD]", PaddingDirection::Right);
+        let batch = vec![encoding, padded];
+        assert_eq!(
+            Encoding::flatten_batch_i64(&batch, Encoding::get_ids),
+            Some((vec![1, 10, 2, 1, 10, 0], [2, 3]))
+        );
+        assert_eq!(
+            Encoding::flatten_batch_i64(&batch, Encoding::get_attention_mask),
+            Some((vec![1, 1, 1, 1, 1, 0], [2, 3]))
+        );
+        assert_eq!(
+            Encoding::flatten_batch_i64(&[], Encoding::get_ids),
+            Some((vec![], [0, 0]))
+        );
+
+        let mut unpadded = batch;
+        unpadded[1].truncate(2, 0, TruncationDirection::Right);
+        assert_eq!(
+            Encoding::flatten_batch_i64(&unpadded, Encoding::get_ids),
+            None
+        );
+    }
+
+    #[test]
+    fn packed_attention_mask() {
+        let mut encoding = Encoding::new(
+            vec![1; 10],
+            vec![0; 10],
+            vec!["a".into(); 10],
+            vec![(0, 1); 10],
+            vec![0; 10],
+            vec![1; 10],
+            vec![true; 10],
+            vec![false; 10],
+            vec![],
+        );
+        encoding.pad(12, 0, 0, "[PAD]", PaddingDirection::Left);
+        assert_eq!(
+            encoding.get_attention_mask_u8(),
+            vec![0u8, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]
+        );
+        assert_eq!(
+            encoding.get_attention_mask_bits(),
+            vec![0b1111_1100, 0b0000_1111]
+        );
+        assert!(Encoding::default().get_attention_mask_bits().is_empty());
+    }
+
+    #[test]
+    fn get_range() {
+        let mut encoding = Encoding::new(
+            (0..8).collect(),
+            vec![0, 0, 0, 0, 1, 1, 1, 1],
+            (0..8).map(|i| format!("t{}", i)).collect(),
+            (0..8).map(|i| (i, i + 1)).collect(),
+            vec![1, 0, 0, 0, 0, 0, 0, 1],
+            vec![1; 8],
+            vec![true, true, false, true, true, false, true, true],
+            vec![false, false, false, true, false, false, false, false],
+            vec![],
+        );
+        encoding.overflowing = vec![encoding.clone()];
+
+        let range = encoding.get_range(2..5).unwrap();
+        assert_eq!(range.get_ids(), &[2, 3, 4]);
+        assert_eq!(range.get_type_ids(), &[0, 0, 1]);
+        assert_eq!(range.get_tokens(), &["t2", "t3", "t4"]);
+        assert_eq!(range.get_offsets(), &[(2, 3), (3, 4), (4, 5)]);
+        assert_eq!(range.get_special_tokens_mask(), &[0, 0, 0]);
+        assert_eq!(range.get_attention_mask(), &[1, 1, 1]);
+        assert_eq!(range.get_word_starts(), &[false, true, true]);
+        assert_eq!(range.get_unknown_flags(), &[false, true, false]);
+        assert!(range.get_overflowing().is_empty());
+
+        assert_eq!(encoding.get_range(0..8).unwrap().get_ids().len(), 8);
+        assert!(encoding.get_range(8..8).unwrap().get_ids().is_empty());
+        assert!(encoding.get_range(6..9).is_none());
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 5..2;
+        assert!(encoding.get_range(reversed).is_none());
+    }
+
     #[test]
     fn merge_encodings() {
         let mut a = Encoding {
@@ -286,7 +996,10 @@ mod tests {
             offsets: vec![(0, 6)],
             special_tokens_mask: vec![0],
             attention_mask: vec![1],
+            word_starts: vec![true],
+            unknown_flags: vec![false],
             overflowing: vec![],
+            num_truncated: 0,
         };
         let b = Encoding {
             ids: vec![2],
@@ -295,7 +1008,10 @@ mod tests {
             offsets: vec![(0, 6)],
             special_tokens_mask: vec![0],
             attention_mask: vec![1],
+            word_starts: vec![true],
+            unknown_flags: vec![false],
             overflowing: vec![],
+            num_truncated: 0,
         };
         a.merge_with(b, true);
 
@@ -308,7 +1024,10 @@ mod tests {
                 offsets: vec![(0, 6), (6, 12)],
                 special_tokens_mask: vec![0, 0],
                 attention_mask: vec![1, 1],
+                word_starts: vec![true, true],
+                unknown_flags: vec![false; 2],
                 overflowing: vec![],
+                num_truncated: 0,
             }
         );
     }
@@ -326,9 +1045,14 @@ mod tests {
             offsets: vec![(0, 5), (6, 11), (11, 12)],
             special_tokens_mask: vec![0, 0, 0],
             attention_mask: vec![1, 1, 1],
+            word_starts: vec![true, true, true],
+            unknown_flags: vec![false; 3],
             overflowing: vec![],
+            num_truncated: 0,
         };
-        a.truncate(2, 0);
+        a.truncate(2, 0, TruncationDirection::Right);
+        assert!(a.is_truncated());
+        assert_eq!(a.get_num_truncated(), 1);
 
         assert_eq!(
             a,
@@ -339,6 +1063,8 @@ mod tests {
                 offsets: vec![(0, 5), (6, 11)],
                 special_tokens_mask: vec![0, 0],
                 attention_mask: vec![1, 1],
+                word_starts: vec![true, true],
+                unknown_flags: vec![false; 2],
                 overflowing: vec![Encoding {
                     ids: vec![3],
                     type_ids: vec![0],
@@ -346,9 +1072,162 @@ mod tests {
                     offsets: vec![(11, 12)],
                     special_tokens_mask: vec![0],
                     attention_mask: vec![1],
+                    word_starts: vec![true],
+                    unknown_flags: vec![false],
                     overflowing: vec![],
-                }]
+                    num_truncated: 0,
+                }],
+                num_truncated: 1,
             }
         );
     }
+
+    #[test]
+    fn truncate_left() {
+        let tokens = ["a", "b", "c", "d", "e"];
+        let mut encoding = Encoding::new(
+            vec![1, 2, 3, 4, 5],
+            vec![0; 5],
+            tokens.iter().map(|t| t.to_string()).collect(),
+            vec![(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)],
+            vec![0; 5],
+            vec![1; 5],
+            vec![true, false, true, false, true],
+            vec![false; 5],
+            vec![],
+        );
+        encoding.truncate(2, 1, TruncationDirection::Left);
+
+        assert_eq!(encoding.get_tokens(), &["d", "e"]);
+        assert_eq!(encoding.get_ids(), &[4, 5]);
+        assert_eq!(encoding.get_offsets(), &[(3, 4), (4, 5)]);
+        assert_eq!(encoding.get_word_starts(), &[false, true]);
+        assert_eq!(encoding.get_num_truncated(), 3);
+        // The overflowing parts go backward, each one ending with `stride` tokens of the next
+        let overflowing = encoding
+            .get_overflowing()
+            .iter()
+            .map(|o| o.get_tokens().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            overflowing,
+            vec![vec!["c", "d"], vec!["b", "c"], vec!["a", "b"]]
+        );
+        assert_eq!(
+            encoding.get_overflowing()[0].get_offsets(),
+            &[(2, 3), (3, 4)]
+        );
+    }
+
+    #[test]
+    fn append_with_separator() {
+        let mut a = Encoding {
+            ids: vec![1],
+            type_ids: vec![0],
+            tokens: vec![String::from("Hello")],
+            offsets: vec![(0, 5)],
+            special_tokens_mask: vec![0],
+            attention_mask: vec![1],
+            word_starts: vec![true],
+            unknown_flags: vec![false],
+            overflowing: vec![],
+            num_truncated: 0,
+        };
+        let b = Encoding {
+            ids: vec![2, 3],
+            type_ids: vec![1, 1],
+            tokens: vec![String::from("World"), String::from("!")],
+            offsets: vec![(0, 5), (5, 6)],
+            special_tokens_mask: vec![0, 0],
+            attention_mask: vec![1, 1],
+            word_starts: vec![true, true],
+            unknown_flags: vec![false; 2],
+            overflowing: vec![],
+            num_truncated: 0,
+        };
+        a.append_with_separator(b, 42, String::from("[SEP]"), 0);
+
+        assert_eq!(
+            a,
+            Encoding {
+                ids: vec![1, 42, 2, 3],
+                type_ids: vec![0, 0, 1, 1],
+                tokens: vec![
+                    String::from("Hello"),
+                    String::from("[SEP]"),
+                    String::from("World"),
+                    String::from("!")
+                ],
+                offsets: vec![(0, 5), (0, 0), (0, 5), (5, 6)],
+                special_tokens_mask: vec![0, 1, 0, 0],
+                attention_mask: vec![1, 1, 1, 1],
+                word_starts: vec![true, true, true, true],
+                unknown_flags: vec![false; 4],
+                overflowing: vec![],
+                num_truncated: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        let mut encoding = Encoding::new(
+            vec![1, 2, 3],
+            vec![0, 0, 1],
+            vec!["Hello".into(), "World".into(), "!".into()],
+            vec![(0, 5), (6, 11), (0, 1)],
+            vec![0, 0, 0],
+            vec![1, 1, 1],
+            vec![true, true, true],
+            vec![false, true, false],
+            vec![],
+        );
+        encoding.truncate(2, 1, TruncationDirection::Right);
+
+        let json = serde_json::to_string(&encoding).unwrap();
+        let deserialized: Encoding = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, encoding);
+        assert_eq!(deserialized.get_overflowing().len(), 1);
+        assert_eq!(deserialized.get_num_truncated(), 1);
+
+        // The values must all describe the same tokens
+        let json = json.replace(r#""ids":[1,2]"#, r#""ids":[1]"#);
+        assert!(serde_json::from_str::<Encoding>(&json).is_err());
+    }
+
+    #[test]
+    fn diff() {
+        let a = Encoding::new(
+            vec![1, 2],
+            vec![0, 0],
+            vec!["a".into(), "b".into()],
+            vec![(0, 1), (1, 2)],
+            vec![0, 0],
+            vec![1, 1],
+            vec![true, true],
+            vec![false; 2],
+            vec![],
+        );
+        assert!(a.diff(&a).is_empty());
+
+        let mut b = a.clone();
+        b.ids[1] = 3;
+        b.offsets[0] = (0, 2);
+        b.overflowing.push(a.clone());
+        assert_eq!(
+            a.diff(&b),
+            vec![
+                "ids[1]: 2 != 3",
+                "offsets[0]: (0, 1) != (0, 2)",
+                "overflowing: 0 encodings != 1 encodings",
+            ]
+        );
+
+        let mut c = a.clone();
+        c.pad(3, 0, 0, "[PAD]", PaddingDirection::Right);
+        assert_eq!(
+            c.diff(&a)[..2],
+            ["ids: length 3 != 2", "type_ids: length 3 != 2"]
+        );
+    }
 }
@@ -10,52 +10,251 @@
 //!   ...).
 
 use crate::utils::iter::ResultShunt;
-pub use crate::utils::padding::{pad_encodings, PaddingDirection, PaddingParams, PaddingStrategy};
-pub use crate::utils::truncation::{truncate_encodings, TruncationParams, TruncationStrategy};
+pub use crate::utils::padding::{
+    pad_encodings, pad_encodings_bucketed, PaddingDirection, PaddingParams, PaddingStrategy,
+};
+pub use crate::utils::truncation::{
+    truncate_encodings, TruncationDirection, TruncationParams, TruncationStrategy,
+};
+use dyn_clone::DynClone;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
+use serde::{
+    de::{self, IgnoredAny, MapAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use std::{
+    borrow::Cow,
     collections::HashMap,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    ops::RangeInclusive,
     path::{Path, PathBuf},
+    sync::{Arc, RwLock},
 };
 
+mod added_vocabulary;
+mod decode_stream;
 mod encoding;
 mod normalizer;
 
+pub use added_vocabulary::*;
+pub use decode_stream::*;
 pub use encoding::*;
 pub use normalizer::*;
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 pub type Offsets = (usize, usize);
 
+/// A hook called with the pre-tokens of each sequence, right before they get tokenized by the
+/// `Model`. It can modify them (filter some, insert others, ...).
+pub type PreModelHook = Arc<dyn Fn(&mut Vec<(String, Offsets)>) + Send + Sync>;
+/// A hook called with the tokens produced by the `Model` for each sequence, before they get
+/// converted into an `Encoding`.
+pub type PostModelHook = Arc<dyn Fn(&mut Vec<Token>) + Send + Sync>;
+
+/// The number of pre-tokens above which they get given to the `Model` by batches of this size
+const MODEL_BATCH_SIZE: usize = 512;
+
+/// The default number of splits of an input (added tokens and the parts between them) encoded
+/// at once, see `Tokenizer::with_max_added_token_splits`
+const MAX_ADDED_TOKEN_SPLITS: usize = 1024;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A split of the input doesn't fall on char boundaries
+    BadSplit(usize, usize),
+    /// The offsets of the token at the given index are invalid
+    InvalidOffsets(usize, Offsets),
+    /// The offsets of the token at the given index go backward
+    NonMonotonicOffsets(usize),
+    /// The given id is already used by another token
+    IdAlreadyUsed(u32, String),
+    /// The given token is already part of the vocabulary
+    TokenAlreadyExists(String),
+    /// The number of alignments of a `NormalizedString` doesn't match its number of chars
+    AlignmentsLength(usize, usize),
+    /// The alignment of the normalized char at the given index is invalid
+    InvalidAlignment(usize, Offsets),
+    /// The alignment of the normalized char at the given index goes backward
+    NonMonotonicAlignments(usize),
+    /// The input is not valid UTF-8, and the pipeline can't process raw bytes
+    InvalidUtf8,
+    /// The `Decoder` changed some text already returned by a `DecodeStream`
+    DecodeStreamPrefix(String, String),
+    /// The vocabulary of the `Model` can't be extended
+    VocabNotExtensible,
+    /// The truncation `max_length` leaves no room for the input once the given number of
+    /// special tokens is added
+    TruncationTooSmall(usize, usize),
+    /// No padding token could be found in the vocabulary
+    PadTokenNotFound,
+    /// Some of the tokens to add are already part of the `Model` vocabulary (first list), or
+    /// have already been added (second list)
+    TokensCollision(Vec<String>, Vec<String>),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::BadSplit(start, end) => {
+                write!(fmt, "Tokenizer error: Invalid split {}..{}", start, end)
+            }
+            Error::InvalidOffsets(index, offsets) => write!(
+                fmt,
+                "Tokenizer error: Invalid offsets {:?} for token {}",
+                offsets, index
+            ),
+            Error::NonMonotonicOffsets(index) => write!(
+                fmt,
+                "Tokenizer error: Offsets going backward at token {}",
+                index
+            ),
+            Error::IdAlreadyUsed(id, token) => write!(
+                fmt,
+                "Tokenizer error: Id {} is already used by token {}",
+                id, token
+            ),
+            Error::TokenAlreadyExists(token) => write!(
+                fmt,
+                "Tokenizer error: Token {} is already part of the vocabulary",
+                token
+            ),
+            Error::AlignmentsLength(expected, found) => write!(
+                fmt,
+                "Tokenizer error: Expected {} alignments, found {}",
+                expected, found
+            ),
+            Error::InvalidAlignment(index, alignment) => write!(
+                fmt,
+                "Tokenizer error: Invalid alignment {:?} for normalized char {}",
+                alignment, index
+            ),
+            Error::NonMonotonicAlignments(index) => write!(
+                fmt,
+                "Tokenizer error: Alignments going backward at normalized char {}",
+                index
+            ),
+            Error::InvalidUtf8 => write!(
+                fmt,
+                "Tokenizer error: The input is not valid UTF-8, and can only be processed by a \
+                 byte-level pipeline"
+            ),
+            Error::DecodeStreamPrefix(prefix, text) => write!(
+                fmt,
+                "Tokenizer error: Decoded text {:?} doesn't start with the text already \
+                 returned {:?}",
+                text, prefix
+            ),
+            Error::VocabNotExtensible => write!(
+                fmt,
+                "Tokenizer error: The vocabulary of this model can't be extended"
+            ),
+            Error::TruncationTooSmall(max_length, n_special) => write!(
+                fmt,
+                "Tokenizer error: Truncation max_length {} leaves no room for the input once \
+                 the {} special tokens are added",
+                max_length, n_special
+            ),
+            Error::PadTokenNotFound => write!(
+                fmt,
+                "Tokenizer error: No padding token found in the vocabulary, padding must be \
+                 configured with `with_padding`"
+            ),
+            Error::TokensCollision(in_model, already_added) => write!(
+                fmt,
+                "Tokenizer error: Some tokens already exist, in the model vocabulary: {:?}, \
+                 in the added tokens: {:?}",
+                in_model, already_added
+            ),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
 /// Takes care of pre-processing strings.
-pub trait Normalizer {
+pub trait Normalizer: DynClone {
     fn normalize(&self, normalized: &mut NormalizedString) -> Result<()>;
 }
+dyn_clone::clone_trait_object!(Normalizer);
 
 /// The `PreTokenizer` is in charge of doing the pre-segmentation step. It splits the given string
 /// in multiple substrings, keeping track of the offsets of said substrings from the
 /// `NormalizedString`. In some occasions, the `PreTokenizer` might need to modify the given
 /// `NormalizedString` to ensure we can entirely keep track of the offsets and the mapping with
 /// the original string.
-pub trait PreTokenizer {
+pub trait PreTokenizer: DynClone {
     fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>>;
+
+    /// Pre-tokenize some raw bytes, that might not be valid UTF-8. The returned
+    /// `NormalizedString` must have one original char per input byte, so that the offsets can
+    /// be converted back to bytes. Only the byte-level pre-tokenizers can support this.
+    fn pre_tokenize_bytes(
+        &self,
+        _bytes: &[u8],
+    ) -> Result<(NormalizedString, Vec<(String, Offsets)>)> {
+        Err(Box::new(Error::InvalidUtf8))
+    }
 }
+dyn_clone::clone_trait_object!(PreTokenizer);
 
 /// Represents a model used during Tokenization (like BPE or Word or Unigram).
-pub trait Model {
+pub trait Model: DynClone {
     fn tokenize(&self, tokens: Vec<(String, Offsets)>) -> Result<Vec<Token>>;
     fn token_to_id(&self, token: &str) -> Option<u32>;
     fn id_to_token(&self, id: u32) -> Option<String>;
+    fn get_vocab(&self) -> &HashMap<String, u32>;
     fn get_vocab_size(&self) -> usize;
     fn save(&self, folder: &Path, name: Option<&str>) -> Result<Vec<PathBuf>>;
+
+    /// Whether the model does its own pre-tokenization. In this case, the `PreTokenizer` is
+    /// ignored, and the model receives the whole normalized string as a single piece.
+    fn handles_pre_tokenization(&self) -> bool {
+        false
+    }
+
+    /// The id of the token used for the parts of the input that can't be tokenized, if any.
+    fn get_unk_id(&self) -> Option<u32> {
+        None
+    }
+
+    /// Tokenize several independent batches of pre-tokens. By default, they get tokenized one
+    /// after the other, but the models tokenizing each pre-token on its own (like BPE,
+    /// WordPiece or WordLevel) process them in parallel. The `Tokenizer` uses this to split
+    /// the inputs with many pre-tokens.
+    fn tokenize_batch(&self, batches: Vec<Vec<(String, Offsets)>>) -> Result<Vec<Vec<Token>>> {
+        batches
+            .into_iter()
+            .map(|batch| self.tokenize(batch))
+            .collect()
+    }
+
+    /// Add the given tokens to the vocabulary, with the given ids. Only the models that don't
+    /// need anything else than their vocabulary to tokenize (like WordLevel and WordPiece) can
+    /// support this. The others (like BPE with its merges) return an error.
+    ///
+    /// If any of the tokens or ids is already part of the vocabulary, or appears twice in
+    /// `tokens`, an error is returned and nothing is added.
+    fn extend_vocab(&mut self, _tokens: Vec<(String, u32)>) -> Result<()> {
+        Err(Box::new(Error::VocabNotExtensible))
+    }
+
+    /// The prefix added to the tokens that continue a word, if any (like `##` in WordPiece)
+    fn get_continuing_subword_prefix(&self) -> Option<&str> {
+        None
+    }
+
+    /// The suffix added to the tokens that end a word, if any (like `</w>` in the original BPE)
+    fn get_end_of_word_suffix(&self) -> Option<&str> {
+        None
+    }
 }
+dyn_clone::clone_trait_object!(Model);
 
 /// A `PostProcessor` has the responsibility to post process an encoded output of the `Tokenizer`.
 /// It adds any special tokens that a language model would require.
-pub trait PostProcessor {
+pub trait PostProcessor: DynClone {
     /// Returns the number of tokens that will be added during the processing step
     fn added_tokens(&self, is_pair: bool) -> usize;
     /// Process both encodings and returns a new merged one
@@ -66,6 +265,7 @@ pub trait PostProcessor {
         add_special_tokens: bool,
     ) -> Result<Encoding>;
 }
+dyn_clone::clone_trait_object!(PostProcessor);
 impl dyn PostProcessor {
     pub fn default_process(
         mut encoding: Encoding,
@@ -83,9 +283,10 @@ impl dyn PostProcessor {
 }
 
 /// A `Decoder` has the responsibility to merge the given `Vec<String>` in a `String`.
-pub trait Decoder {
+pub trait Decoder: DynClone {
     fn decode(&self, tokens: Vec<String>) -> Result<String>;
 }
+dyn_clone::clone_trait_object!(Decoder);
 
 /// A `Trainer` has the responsibility to train a model. We feed it with lines/sentences
 /// and it returns a `Model` when done.
@@ -111,10 +312,126 @@ impl Token {
     }
 }
 
+/// The input of `Tokenizer::encode`. Each sequence can be either borrowed or owned, so that a
+/// borrowed input never needs to be copied.
 #[derive(Debug, Clone)]
-pub enum EncodeInput {
-    Single(String),
-    Dual(String, String),
+pub enum EncodeInput<'s> {
+    Single(Cow<'s, str>),
+    Dual(Cow<'s, str>, Cow<'s, str>),
+}
+
+impl<'s> From<&'s str> for EncodeInput<'s> {
+    fn from(input: &'s str) -> Self {
+        EncodeInput::Single(input.into())
+    }
+}
+
+impl From<String> for EncodeInput<'_> {
+    fn from(input: String) -> Self {
+        EncodeInput::Single(input.into())
+    }
+}
+
+impl<'s> From<(&'s str, &'s str)> for EncodeInput<'s> {
+    fn from((sequence, pair): (&'s str, &'s str)) -> Self {
+        EncodeInput::Dual(sequence.into(), pair.into())
+    }
+}
+
+impl From<(String, String)> for EncodeInput<'_> {
+    fn from((sequence, pair): (String, String)) -> Self {
+        EncodeInput::Dual(sequence.into(), pair.into())
+    }
+}
+
+/// Which messages to keep when a conversation doesn't fit in a token budget.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeepPolicy {
+    /// Keep the most recent messages, dropping from the start of the conversation.
+    Latest,
+    /// Always keep the first message (like a system prompt), and then the most recent ones,
+    /// dropping from the middle of the conversation.
+    FirstAndLatest,
+}
+
+/// Some information about what happened while encoding an input.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EncodeInfo {
+    /// Whether the input got truncated
+    pub truncated: bool,
+    /// The number of tokens removed by the truncation. These tokens are still available in the
+    /// overflowing part of the `Encoding`.
+    pub num_truncated: usize,
+}
+
+/// The differences between the vocabulary of a `Tokenizer` and a reference one, as given by
+/// `Tokenizer::diff_vocab`. Each list is sorted.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VocabDiff {
+    /// The tokens of the reference vocabulary that we don't have
+    pub missing: Vec<String>,
+    /// The tokens we have that are not part of the reference vocabulary
+    pub extra: Vec<String>,
+    /// The tokens with a different id: `(token, our_id, reference_id)`
+    pub id_mismatches: Vec<(String, u32, u32)>,
+}
+
+impl VocabDiff {
+    /// Whether both vocabularies are the same
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.id_mismatches.is_empty()
+    }
+}
+
+/// Some statistics about the tokenization of a corpus, as given by `Tokenizer::corpus_stats`.
+/// The line breaks are not part of the lines, and no special token is added.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CorpusStats {
+    /// The number of lines
+    pub lines: usize,
+    /// The number of chars in all the lines
+    pub chars: usize,
+    /// The number of tokens
+    pub tokens: usize,
+    /// The number of unknown tokens
+    pub unknown_tokens: usize,
+    /// The number of occurrences of each token id
+    pub token_counts: HashMap<u32, usize>,
+}
+
+impl CorpusStats {
+    /// The average number of tokens per line
+    pub fn tokens_per_line(&self) -> f64 {
+        ratio(self.tokens, self.lines)
+    }
+
+    /// The average number of tokens per char, also called fertility
+    pub fn tokens_per_char(&self) -> f64 {
+        ratio(self.tokens, self.chars)
+    }
+
+    /// The proportion of unknown tokens
+    pub fn unknown_rate(&self) -> f64 {
+        ratio(self.unknown_tokens, self.tokens)
+    }
+
+    fn merge_with(&mut self, other: CorpusStats) {
+        self.lines += other.lines;
+        self.chars += other.chars;
+        self.tokens += other.tokens;
+        self.unknown_tokens += other.unknown_tokens;
+        for (id, count) in other.token_counts {
+            *self.token_counts.entry(id).or_insert(0) += count;
+        }
+    }
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -153,8 +470,97 @@ impl std::cmp::PartialEq for AddedToken {
     }
 }
 impl std::cmp::Eq for AddedToken {}
+// Even if the equality only considers the content, we need to keep all the options when saving
+impl Serialize for AddedToken {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut token = serializer.serialize_struct("AddedToken", 2)?;
+        token.serialize_field("content", &self.content)?;
+        token.serialize_field("single_word", &self.single_word)?;
+        token.end()
+    }
+}
+impl<'de> Deserialize<'de> for AddedToken {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct(
+            "AddedToken",
+            &["content", "single_word"],
+            AddedTokenVisitor,
+        )
+    }
+}
+struct AddedTokenVisitor;
+impl<'de> Visitor<'de> for AddedTokenVisitor {
+    type Value = AddedToken;
+
+    fn expecting(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "an AddedToken")
+    }
+
+    fn visit_map<V>(self, mut map: V) -> std::result::Result<Self::Value, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        let mut content = None;
+        let mut token = AddedToken::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_ref() {
+                "content" => content = Some(map.next_value()?),
+                "single_word" => token.single_word = map.next_value()?,
+                // Ignore the options we don't know about
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        token.content = content.ok_or_else(|| de::Error::missing_field("content"))?;
+        Ok(token)
+    }
+}
+
+/// The tokens of the vocabulary (including the added tokens) by id, shared with the
+/// `SharedEncoding`s. It is built on the first call to `encode_batch_shared`, and dropped
+/// whenever the vocabulary changes.
+#[derive(Default)]
+struct SharedVocab(RwLock<Option<Arc<SharedVocabR>>>);
+type SharedVocabR = HashMap<u32, Arc<str>>;
+
+impl SharedVocab {
+    fn get_or_build<F>(&self, build: F) -> Arc<SharedVocabR>
+    where
+        F: FnOnce() -> SharedVocabR,
+    {
+        if let Some(vocab) = self.0.read().unwrap().as_ref() {
+            return vocab.clone();
+        }
+        self.0
+            .write()
+            .unwrap()
+            .get_or_insert_with(|| Arc::new(build()))
+            .clone()
+    }
+
+    fn clear(&mut self) {
+        *self.0.get_mut().unwrap() = None;
+    }
+}
+
+impl Clone for SharedVocab {
+    fn clone(&self) -> Self {
+        SharedVocab(RwLock::new(self.0.read().unwrap().clone()))
+    }
+}
 
 /// A `Tokenizer` is capable of encoding/decoding any text.
+///
+/// Cloning a `Tokenizer` clones each of its parts, so that the clone can be modified without
+/// affecting the original. The hooks are shared.
+#[derive(Clone)]
 pub struct Tokenizer {
     // Tokenizer parts
     normalizer: Option<Box<dyn Normalizer + Sync>>,
@@ -162,16 +568,25 @@ pub struct Tokenizer {
     model: Box<dyn Model + Sync>,
     post_processor: Option<Box<dyn PostProcessor + Sync>>,
     decoder: Option<Box<dyn Decoder + Sync>>,
+    pre_model_hook: Option<PreModelHook>,
+    post_model_hook: Option<PostModelHook>,
 
     // Added Vocabulary capabilities
-    added_tokens: HashMap<AddedToken, u32>,
-    added_tokens_r: HashMap<u32, AddedToken>,
-    split_re: Option<regex::Regex>,
-    special_tokens: HashMap<String, u32>,
+    added_vocabulary: AddedVocabulary,
 
     // General processing parameters
     trunc: Option<TruncationParams>,
     padding: Option<PaddingParams>,
+    special_tokens_on_empty: bool,
+    validation: bool,
+    max_in_flight: Option<usize>,
+    max_added_token_splits: usize,
+    decode_separator: Option<String>,
+    keep_empty_pre_tokens: bool,
+    special_tokens_mask_convention: SpecialMaskConvention,
+    add_special_tokens: bool,
+
+    shared_vocab: SharedVocab,
 }
 
 impl Tokenizer {
@@ -183,14 +598,23 @@ impl Tokenizer {
             model,
             post_processor: None,
             decoder: None,
+            pre_model_hook: None,
+            post_model_hook: None,
 
-            added_tokens: HashMap::new(),
-            added_tokens_r: HashMap::new(),
-            split_re: None,
-            special_tokens: HashMap::new(),
+            added_vocabulary: AddedVocabulary::new(),
 
             trunc: None,
             padding: None,
+            special_tokens_on_empty: true,
+            validation: false,
+            max_in_flight: None,
+            max_added_token_splits: MAX_ADDED_TOKEN_SPLITS,
+            decode_separator: None,
+            keep_empty_pre_tokens: false,
+            special_tokens_mask_convention: SpecialMaskConvention::default(),
+            add_special_tokens: true,
+
+            shared_vocab: SharedVocab::default(),
         }
     }
 
@@ -242,9 +666,50 @@ impl Tokenizer {
         self.decoder.as_ref()
     }
 
+    /// Suggest a `Decoder` matching the subword markers of the current `Model`: a `BPEDecoder`
+    /// when the model uses an end-of-word suffix, and a `WordPiece` decoder (without any
+    /// cleanup) when it uses a continuing subword prefix. Returns `None` if the model uses
+    /// neither. The suggested decoder can then be set with `with_decoder`.
+    pub fn decoder_for_model(&self) -> Option<Box<dyn Decoder + Sync>> {
+        if let Some(suffix) = self.model.get_end_of_word_suffix() {
+            Some(Box::new(crate::decoders::bpe::BPEDecoder::new(
+                suffix.to_owned(),
+            )))
+        } else if let Some(prefix) = self.model.get_continuing_subword_prefix() {
+            Some(Box::new(crate::decoders::wordpiece::WordPiece::new(
+                prefix.to_owned(),
+                false,
+            )))
+        } else {
+            None
+        }
+    }
+
+    /// Set a hook called with the pre-tokens of each sequence, right before the `Model`.
+    /// The added tokens don't go through it.
+    ///
+    /// The hook must be `Sync` since `encode_batch` calls it from several threads at once. To
+    /// keep some state across calls, use some synchronization primitive (like a `Mutex` or
+    /// an atomic).
+    pub fn with_pre_model_hook(&mut self, hook: PreModelHook) -> &Self {
+        self.pre_model_hook = Some(hook);
+        self
+    }
+
+    /// Set a hook called with the tokens of each sequence, right after the `Model`.
+    /// The added tokens don't go through it.
+    ///
+    /// Just like with `with_pre_model_hook`, the hook may get called from several threads at
+    /// once.
+    pub fn with_post_model_hook(&mut self, hook: PostModelHook) -> &Self {
+        self.post_model_hook = Some(hook);
+        self
+    }
+
     /// Set the model
     pub fn with_model(&mut self, model: Box<dyn Model + Sync>) -> &Self {
         self.model = model;
+        self.shared_vocab.clear();
         self
     }
 
@@ -266,44 +731,300 @@ impl Tokenizer {
         self
     }
 
+    /// Truncate and pad every encoding to exactly `length` tokens, special tokens included.
+    ///
+    /// The current truncation strategy and stride, and the current padding direction and token,
+    /// are kept if any. Otherwise, we use `TruncationStrategy::LongestFirst`, and pad on the
+    /// right with the first of `[PAD]` or `<pad>` found in the vocabulary.
+    pub fn pad_and_truncate_to(&mut self, length: usize) -> Result<&Self> {
+        let n_special = self.num_special_tokens_to_add(true);
+        if length <= n_special {
+            return Err(Box::new(Error::TruncationTooSmall(length, n_special)));
+        }
+
+        let padding = match self.padding.take() {
+            Some(padding) => padding,
+            None => {
+                let (pad_token, pad_id) = ["[PAD]", "<pad>"]
+                    .iter()
+                    .find_map(|token| Some((token.to_string(), self.token_to_id(token)?)))
+                    .ok_or(Error::PadTokenNotFound)?;
+                PaddingParams {
+                    strategy: PaddingStrategy::BatchLongest,
+                    direction: PaddingDirection::Right,
+                    pad_id,
+                    pad_type_id: 0,
+                    pad_token,
+                }
+            }
+        };
+        self.padding = Some(PaddingParams {
+            strategy: PaddingStrategy::Fixed(length),
+            ..padding
+        });
+
+        let trunc = self.trunc.take().unwrap_or(TruncationParams {
+            max_length: length,
+            strategy: TruncationStrategy::LongestFirst,
+            stride: 0,
+            respect_word_boundaries: false,
+        });
+        self.trunc = Some(TruncationParams {
+            max_length: length,
+            ..trunc
+        });
+
+        Ok(self)
+    }
+
+    /// Set whether an input that produces no tokens at all (like an empty or whitespace-only
+    /// input) should still receive the special tokens added by the `PostProcessor`. If not, such
+    /// an input produces an empty `Encoding` (that can still get padded).
+    /// Defaults to `true`.
+    pub fn with_special_tokens_on_empty(&mut self, special_tokens_on_empty: bool) -> &Self {
+        self.special_tokens_on_empty = special_tokens_on_empty;
+        self
+    }
+
+    /// Set whether the empty pre-tokens should be given to the `Model`. Some normalizers and
+    /// pre-tokenizers can produce empty pieces (like a part of the input left empty after
+    /// stripping), for which most models would produce spurious tokens. Such pieces are dropped
+    /// by default.
+    pub fn with_keep_empty_pre_tokens(&mut self, keep_empty_pre_tokens: bool) -> &Self {
+        self.keep_empty_pre_tokens = keep_empty_pre_tokens;
+        self
+    }
+
+    /// Set whether `encode_default` and `encode_batch_default` add the special tokens, so that
+    /// a pipeline that always does the same doesn't need to pass it around. Defaults to `true`.
+    pub fn with_add_special_tokens(&mut self, add_special_tokens: bool) -> &Self {
+        self.add_special_tokens = add_special_tokens;
+        self
+    }
+
+    /// Set how the special tokens are marked in the mask given by
+    /// `Tokenizer::get_special_tokens_mask`. Defaults to `SpecialMaskConvention::SpecialIsOne`.
+    /// The encodings themselves always mark the special tokens with `1`, as expected by all
+    /// their methods (like `pad` or `get_source_offsets`).
+    pub fn with_special_tokens_mask_convention(
+        &mut self,
+        convention: SpecialMaskConvention,
+    ) -> &Self {
+        self.special_tokens_mask_convention = convention;
+        self
+    }
+
+    /// Get the `SpecialMaskConvention` used by `Tokenizer::get_special_tokens_mask`
+    pub fn get_special_tokens_mask_convention(&self) -> SpecialMaskConvention {
+        self.special_tokens_mask_convention
+    }
+
+    /// Get the special tokens mask of the given `Encoding`, using the convention set with
+    /// `with_special_tokens_mask_convention`
+    pub fn get_special_tokens_mask(&self, encoding: &Encoding) -> Vec<u32> {
+        encoding.get_special_tokens_mask_with(self.special_tokens_mask_convention)
+    }
+
+    /// Set whether every split and offset should be validated while encoding. Instead of relying
+    /// on unchecked operations, the `Tokenizer` then checks every slice it takes from the input,
+    /// and every offset it produces, returning an error as soon as something is wrong.
+    /// This is useful when processing untrusted inputs, but it comes at a cost: every offset gets
+    /// checked one more time, so expect encoding to be slower. Defaults to `false`.
+    pub fn with_validation(&mut self, validation: bool) -> &Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Set the maximum number of inputs processed at once by `encode_batch`. The inputs are then
+    /// encoded and padded by chunks of this size, which bounds the memory used by the
+    /// intermediate results on very large batches. Note that with `PaddingStrategy::BatchLongest`,
+    /// each chunk gets padded to its own longest encoding. Defaults to `None`, processing the
+    /// whole batch at once.
+    pub fn with_max_in_flight(&mut self, max_in_flight: Option<usize>) -> &Self {
+        self.max_in_flight = max_in_flight;
+        self
+    }
+
+    /// Set the maximum number of splits of an input (its added tokens and the parts between them)
+    /// encoded at once. An input containing more added tokens gets encoded by chunks of this
+    /// many splits, each of them merged into the final `Encoding` before starting the next one.
+    /// This bounds the memory used by the intermediate results on pathological inputs, like a
+    /// long repetition of added tokens. Defaults to 1024.
+    pub fn with_max_added_token_splits(&mut self, max_added_token_splits: usize) -> &Self {
+        self.max_added_token_splits = max_added_token_splits.max(1);
+        self
+    }
+
+    /// Set the separator used to join the tokens when decoding without any `Decoder`. Defaults
+    /// to `None`, joining them with a space, which doesn't work well with the languages that don't
+    /// separate their words, or with subwords: use `Some("")` to simply concatenate them.
+    pub fn with_decode_separator(&mut self, separator: Option<String>) -> &Self {
+        self.decode_separator = separator;
+        self
+    }
+
+    /// Get the added vocabulary, with the added and special tokens
+    pub fn get_added_vocabulary(&self) -> &AddedVocabulary {
+        &self.added_vocabulary
+    }
+
     /// Get the size of the vocabulary
     pub fn get_vocab_size(&self, with_added_tokens: bool) -> usize {
         self.model.get_vocab_size()
             + if with_added_tokens {
-                self.added_tokens.len()
+                self.added_vocabulary.len()
             } else {
                 0
             }
     }
 
+    /// Get the number of added tokens (the ones not part of the `Model` vocabulary), along with
+    /// the range of their ids. The range is empty when there is no added token. The added tokens
+    /// can go right after the `Model` vocabulary in an embedding table only when the range
+    /// starts at `get_vocab_size(false)` and contains exactly as many ids as there are tokens.
+    pub fn added_tokens_info(&self) -> (usize, RangeInclusive<u32>) {
+        let range = self
+            .added_vocabulary
+            .id_range()
+            .unwrap_or(RangeInclusive::new(1, 0));
+        (self.added_vocabulary.len(), range)
+    }
+
+    /// Get the vocabulary, optionally including the added tokens
+    pub fn get_vocab(&self, with_added_tokens: bool) -> HashMap<String, u32> {
+        let mut vocab = self.model.get_vocab().clone();
+        if with_added_tokens {
+            vocab.extend(self.added_vocabulary.get_vocab());
+        }
+        vocab
+    }
+
+    /// Whether the given token is part of the vocabulary, either the `Model` one or the added
+    /// tokens
+    pub fn contains_token(&self, token: &str) -> bool {
+        self.token_to_id(token).is_some()
+    }
+
+    /// Get all the tokens starting with the given prefix, from both the `Model` vocabulary and
+    /// the added tokens, sorted alphabetically. This scans the whole vocabulary.
+    pub fn tokens_with_prefix(&self, prefix: &str) -> Vec<String> {
+        let added = self.added_vocabulary.get_vocab();
+        let mut tokens = self
+            .model
+            .get_vocab()
+            .keys()
+            .chain(added.keys())
+            .filter(|token| token.starts_with(prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        tokens.sort();
+        tokens.dedup();
+        tokens
+    }
+
+    /// Get the longest token of the vocabulary (in chars), along with its id. The added tokens
+    /// are included, and the ties are broken by keeping the smallest id.
+    pub fn longest_token(&self) -> Option<(String, u32)> {
+        self.get_vocab(true)
+            .into_iter()
+            .max_by_key(|(token, id)| (token.chars().count(), std::cmp::Reverse(*id)))
+    }
+
+    /// Get the shortest token of the vocabulary (in chars), along with its id. The added tokens
+    /// are included, and the ties are broken by keeping the smallest id.
+    pub fn shortest_token(&self) -> Option<(String, u32)> {
+        self.get_vocab(true)
+            .into_iter()
+            .min_by_key(|(token, id)| (token.chars().count(), *id))
+    }
+
+    /// Get all the tokens longer than `len` chars, along with their id, from the longest to the
+    /// shortest one. The added tokens are included.
+    pub fn tokens_longer_than(&self, len: usize) -> Vec<(String, u32)> {
+        let mut tokens = self
+            .get_vocab(true)
+            .into_iter()
+            .map(|(token, id)| (token.chars().count(), token, id))
+            .filter(|(n_chars, _, _)| *n_chars > len)
+            .collect::<Vec<_>>();
+        tokens.sort_by(|a, b| b.0.cmp(&a.0).then(a.2.cmp(&b.2)));
+        tokens
+            .into_iter()
+            .map(|(_, token, id)| (token, id))
+            .collect()
+    }
+
+    /// Compare the whole vocabulary, including the added tokens, with the given reference one.
+    /// This is useful to check that a vocabulary got loaded as expected.
+    pub fn diff_vocab(&self, other: &HashMap<String, u32>) -> VocabDiff {
+        let vocab = self.get_vocab(true);
+
+        let mut diff = VocabDiff::default();
+        for (token, id) in &vocab {
+            match other.get(token) {
+                Some(other_id) if other_id != id => {
+                    diff.id_mismatches.push((token.clone(), *id, *other_id))
+                }
+                Some(_) => {}
+                None => diff.extra.push(token.clone()),
+            }
+        }
+        diff.missing = other
+            .keys()
+            .filter(|token| !vocab.contains_key(*token))
+            .cloned()
+            .collect();
+
+        diff.missing.sort();
+        diff.extra.sort();
+        diff.id_mismatches.sort();
+        diff
+    }
+
+    /// Save the vocabulary in the given file, as a list of `token\tid` lines sorted by id.
+    /// Unlike `Model::save`, this gives the same simple format for any model, which makes it
+    /// easy to inspect and compare vocabularies.
+    pub fn save_vocab<P: AsRef<Path>>(&self, path: P, with_added_tokens: bool) -> Result<()> {
+        let mut vocab = self
+            .get_vocab(with_added_tokens)
+            .into_iter()
+            .collect::<Vec<_>>();
+        vocab.sort_unstable_by_key(|(_, id)| *id);
+
+        let mut file = BufWriter::new(File::create(path)?);
+        for (token, id) in vocab {
+            writeln!(file, "{}\t{}", token, id)?;
+        }
+        file.flush()?;
+
+        Ok(())
+    }
+
     /// Converts a token in the corresponding id.
     pub fn token_to_id(&self, token: &str) -> Option<u32> {
-        if let Some(id) = self.added_tokens.get(&AddedToken::from(token.to_owned())) {
-            Some(*id)
-        } else {
-            self.model.token_to_id(token)
-        }
+        self.added_vocabulary
+            .token_to_id(token)
+            .or_else(|| self.model.token_to_id(token))
     }
 
     /// Converts an id to the corresponding token.
     pub fn id_to_token(&self, id: u32) -> Option<String> {
-        if let Some(token) = self.added_tokens_r.get(&id) {
-            Some(token.content.clone())
-        } else {
-            self.model.id_to_token(id)
-        }
+        self.added_vocabulary
+            .id_to_token(id)
+            .map(|token| token.to_owned())
+            .or_else(|| self.model.id_to_token(id))
     }
 
     /// Normalize the given sentence and return the corresponding normalized string
     pub fn normalize(&self, sentence: &str) -> Result<NormalizedString> {
         let mut normalized = self
             .split_on_added_tokens(sentence)
-            .into_iter()
-            .map(|(sentence, id)| -> Result<NormalizedString> {
+            .map(|split| -> Result<NormalizedString> {
+                let (sentence, id) = split?;
                 if id.is_some() {
-                    Ok(NormalizedString::from(&sentence))
+                    Ok(NormalizedString::from(sentence))
                 } else {
-                    let mut normalized = self.do_normalize(&sentence)?;
+                    let mut normalized = self.do_normalize(sentence)?;
                     let _ = self.pre_tokenize(&mut normalized)?;
 
                     Ok(normalized)
@@ -320,175 +1041,722 @@ impl Tokenizer {
         Ok(normalized)
     }
 
+    /// Run the whole pipeline (up to the `Model`) on a single sequence, returning its `Encoding`
+    /// along with the `NormalizedString` used to produce it. No truncation, post-processing or
+    /// padding happens here. Without `normalize`, the sequence is considered already normalized.
+    fn encode_single_sequence(
+        &self,
+        sentence: &str,
+        type_id: u32,
+        normalize: bool,
+        buffers: &mut EncodeBuffers,
+    ) -> Result<(Encoding, NormalizedString)> {
+        // First we need to split into as many sequences as needed to avoid splitting
+        // on our added tokens
+        let splits = self.split_on_added_tokens(sentence);
+        self.encode_splits(splits, type_id, normalize, buffers)
+    }
+
+    /// Encode each of the given splits, as produced by `split_on_added_tokens`, merging them
+    /// into a single `Encoding` and `NormalizedString`. The splits are encoded by chunks of
+    /// `max_added_token_splits`, pulling at most this many splits from `splits` at once.
+    fn encode_splits<'s>(
+        &self,
+        splits: impl IntoIterator<Item = Result<(&'s str, Option<u32>)>>,
+        type_id: u32,
+        normalize: bool,
+        buffers: &mut EncodeBuffers,
+    ) -> Result<(Encoding, NormalizedString)> {
+        let mut encode_split = |(sentence, id): (&str, Option<u32>)| -> Result<_> {
+            // If this is one of our added tokens, lets return an encoding directly
+            if let Some(id) = id {
+                return Ok((
+                    Encoding::new(
+                        vec![id],
+                        vec![type_id],
+                        vec![sentence.to_owned()],
+                        vec![(0, sentence.chars().count())],
+                        vec![0],
+                        vec![1],
+                        vec![true],
+                        vec![false],
+                        vec![],
+                    ),
+                    NormalizedString::from(sentence),
+                ));
+            }
+
+            // 1. Normalization
+            let normalized = if normalize {
+                self.do_normalize(sentence)?
+            } else {
+                NormalizedString::from(sentence)
+            };
+
+            self.encode_normalized_sequence(normalized, type_id, buffers)
+        };
+
+        let mut splits = splits.into_iter();
+        let mut merged: Option<(Encoding, NormalizedString)> = None;
+        loop {
+            let chunk = splits
+                .by_ref()
+                .take(self.max_added_token_splits)
+                .map(|split| split.and_then(&mut encode_split));
+            let results = ResultShunt::process(chunk, |iter| iter.collect::<Vec<_>>())?;
+            if results.is_empty() {
+                break;
+            }
+
+            for (mut encoding, n) in results {
+                let (first, normalized) = match merged.as_mut() {
+                    Some(merged) => merged,
+                    None => {
+                        merged = Some((encoding, n));
+                        continue;
+                    }
+                };
+                // The offsets of each split are relative to its own normalized string, so they
+                // need to be shifted by the length of everything before it. Using the end of the
+                // last offsets instead would ignore any trailing part without tokens, like
+                // whitespace.
+                let shift = normalized.len();
+                encoding
+                    .get_offsets_mut()
+                    .iter_mut()
+                    .for_each(|(start, end)| {
+                        *start += shift;
+                        *end += shift;
+                    });
+                first.merge_with(encoding, false);
+                normalized.merge_with(&n);
+            }
+        }
+
+        Ok(merged.unwrap_or_else(|| (Encoding::default(), NormalizedString::from(""))))
+    }
+
+    /// Run the pre-tokenization and the `Model` on an already normalized sequence
+    fn encode_normalized_sequence(
+        &self,
+        mut normalized: NormalizedString,
+        type_id: u32,
+        buffers: &mut EncodeBuffers,
+    ) -> Result<(Encoding, NormalizedString)> {
+        // 2. Pre tokenization
+        let pre_tokenized = self.pre_tokenize(&mut normalized)?;
+
+        // 3. Model
+        Ok((
+            self.do_tokenize(pre_tokenized, type_id, buffers)?,
+            normalized,
+        ))
+    }
+
+    /// Tokenize the given pre-tokens with the `Model`, running the hooks around it. The vectors
+    /// of the `Encoding` are taken from the given buffers.
+    fn do_tokenize(
+        &self,
+        mut pre_tokenized: Vec<(String, Offsets)>,
+        type_id: u32,
+        buffers: &mut EncodeBuffers,
+    ) -> Result<Encoding> {
+        if let Some(hook) = &self.pre_model_hook {
+            hook(&mut pre_tokenized);
+        }
+
+        // The first token of each pre-token starts a new word
+        buffers.pre_token_starts.clear();
+        buffers
+            .pre_token_starts
+            .extend(pre_tokenized.iter().map(|(_, offsets)| offsets.0));
+        let mut word_starts = buffers.pre_token_starts.iter().copied().peekable();
+
+        let mut output = if pre_tokenized.len() > MODEL_BATCH_SIZE {
+            // Many pre-tokens, like for a whole document, so the model can process them by
+            // batches, possibly in parallel
+            let n_batches = pre_tokenized.len().div_ceil(MODEL_BATCH_SIZE);
+            let mut pre_tokens = pre_tokenized.into_iter();
+            let batches = (0..n_batches)
+                .map(|_| pre_tokens.by_ref().take(MODEL_BATCH_SIZE).collect())
+                .collect();
+            self.model
+                .tokenize_batch(batches)?
+                .into_iter()
+                .flatten()
+                .collect()
+        } else {
+            self.model.tokenize(pre_tokenized)?
+        };
+        if let Some(hook) = &self.post_model_hook {
+            hook(&mut output);
+        }
+        let length = output.len();
+
+        let mut ids = EncodeBuffers::take(&mut buffers.ids, length);
+        let mut tokens = EncodeBuffers::take(&mut buffers.tokens, length);
+        let mut offsets = EncodeBuffers::take(&mut buffers.offsets, length);
+        let mut words = EncodeBuffers::take(&mut buffers.word_starts, length);
+        for t in output {
+            while word_starts.peek().is_some_and(|start| *start < t.offsets.0) {
+                word_starts.next();
+            }
+            words.push(word_starts.next_if_eq(&t.offsets.0).is_some());
+            ids.push(t.id);
+            tokens.push(t.value);
+            offsets.push(t.offsets);
+        }
+        let unk_id = self.model.get_unk_id();
+        let mut unknown_flags = EncodeBuffers::take(&mut buffers.unknown_flags, length);
+        unknown_flags.extend(ids.iter().map(|id| Some(*id) == unk_id));
+
+        let mut type_ids = EncodeBuffers::take(&mut buffers.type_ids, length);
+        type_ids.resize(length, type_id);
+        let mut special_tokens_mask = EncodeBuffers::take(&mut buffers.special_tokens_mask, length);
+        special_tokens_mask.resize(length, 0);
+        let mut attention_mask = EncodeBuffers::take(&mut buffers.attention_mask, length);
+        attention_mask.resize(length, 1);
+
+        Ok(Encoding::new(
+            ids,
+            type_ids,
+            tokens,
+            offsets,
+            special_tokens_mask,
+            attention_mask,
+            words,
+            unknown_flags,
+            vec![],
+        ))
+    }
+
     /// Encode the given sentence
     pub fn encode(&self, input: EncodeInput, add_special_tokens: bool) -> Result<Encoding> {
-        let generate_output =
-            move |sentence: String, type_id: u32| -> Result<(Encoding, NormalizedString)> {
-                // First we need to split into as many sequences as needed to avoid splitting
-                // on our added tokens
-                let results = self.split_on_added_tokens(&sentence).into_iter().map(
-                    |(sentence, id)| -> Result<(Encoding, NormalizedString)> {
-                        // If this is one of our added tokens, lets return an encoding directly
-                        if let Some(id) = id {
-                            return Ok((
-                                Encoding::new(
-                                    vec![id],
-                                    vec![type_id],
-                                    vec![sentence.to_owned()],
-                                    vec![(0, sentence.len())],
-                                    vec![0],
-                                    vec![1],
-                                    vec![],
-                                ),
-                                NormalizedString::from(&sentence),
-                            ));
-                        }
+        self.encode_with_info(input, add_special_tokens)
+            .map(|(encoding, _)| encoding)
+    }
 
-                        // 1. Normalization
-                        let mut normalized = self.do_normalize(&sentence)?;
-
-                        // 2. Pre tokenization
-                        let pre_tokenized = self.pre_tokenize(&mut normalized)?;
-
-                        // 3. Model
-                        let output = self.model.tokenize(pre_tokenized)?;
-                        let length = output.len();
-
-                        let (ids, tokens, offsets) = output.into_iter().fold(
-                            (
-                                Vec::with_capacity(length),
-                                Vec::with_capacity(length),
-                                Vec::with_capacity(length),
-                            ),
-                            |(mut ids, mut tokens, mut offsets), t| {
-                                ids.push(t.id);
-                                tokens.push(t.value);
-                                offsets.push(t.offsets);
-                                (ids, tokens, offsets)
-                            },
-                        );
-
-                        Ok((
-                            Encoding::new(
-                                ids,
-                                vec![type_id; length],
-                                tokens,
-                                offsets,
-                                vec![0; length],
-                                vec![1; length],
-                                vec![],
-                            ),
-                            normalized,
-                        ))
-                    },
-                );
-
-                let (mut encodings, mut normalized) =
-                    ResultShunt::process(results, |iter| iter.unzip::<_, _, Vec<_>, Vec<_>>())?;
-
-                if encodings.is_empty() {
-                    return Ok((Encoding::default(), NormalizedString::from("")));
-                }
+    /// Encode the given sentence like `encode`, adding the special tokens as set with
+    /// `with_add_special_tokens`
+    pub fn encode_default(&self, input: EncodeInput) -> Result<Encoding> {
+        self.encode(input, self.add_special_tokens)
+    }
 
-                let others = encodings.split_off(1);
-                let mut first: Encoding = encodings.into_iter().next().unwrap();
+    /// Encode some raw bytes, that might not be valid UTF-8. Valid UTF-8 gets encoded just like
+    /// with `encode`. Otherwise, the bytes can only be processed by a byte-level pipeline,
+    /// without any `Normalizer`, and with a `PreTokenizer` that supports raw bytes (like
+    /// `ByteLevel`). In this case, the added tokens are not extracted from the input, and the
+    /// offsets are in bytes instead of chars.
+    pub fn encode_bytes(&self, bytes: &[u8], add_special_tokens: bool) -> Result<Encoding> {
+        if let Ok(sentence) = std::str::from_utf8(bytes) {
+            return self.encode(sentence.into(), add_special_tokens);
+        }
 
-                for encoding in others {
-                    first.merge_with(encoding, true);
-                }
+        let pre_tokenizer = match (&self.normalizer, &self.pre_tokenizer) {
+            (None, Some(pre_tokenizer)) => pre_tokenizer,
+            _ => return Err(Box::new(Error::InvalidUtf8)),
+        };
+        let (normalized, pre_tokenized) = pre_tokenizer.pre_tokenize_bytes(bytes)?;
+        let encoding = self.do_tokenize(pre_tokenized, 0, &mut EncodeBuffers::new())?;
 
-                let others = normalized.split_off(1);
-                let mut normalized: NormalizedString = normalized.into_iter().next().unwrap();
-                for n in others {
-                    normalized.merge_with(&n);
-                }
+        self.finish_encoding((encoding, normalized), None, add_special_tokens, true)
+            .map(|(encoding, _)| encoding)
+    }
 
-                Ok((first, normalized))
-            };
+    /// Encode the given sentence, also returning some information about what happened during
+    /// the encoding, like whether the input got truncated.
+    pub fn encode_with_info(
+        &self,
+        input: EncodeInput,
+        add_special_tokens: bool,
+    ) -> Result<(Encoding, EncodeInfo)> {
+        self.encode_and_pad(input, add_special_tokens, true)
+    }
 
+    /// Encode the given sentence without padding it, even if some padding is configured. The
+    /// truncation and post-processing are still applied. This is useful to pad the encodings
+    /// later, when building a batch with `pad_encodings`.
+    pub fn encode_no_pad(&self, input: EncodeInput, add_special_tokens: bool) -> Result<Encoding> {
+        self.encode_and_pad(input, add_special_tokens, false)
+            .map(|(encoding, _)| encoding)
+    }
+
+    fn encode_and_pad(
+        &self,
+        input: EncodeInput,
+        add_special_tokens: bool,
+        pad: bool,
+    ) -> Result<(Encoding, EncodeInfo)> {
+        let (encoded, pair_encoded) =
+            self.encode_sequences(input, true, &mut EncodeBuffers::new())?;
+        self.finish_encoding(encoded, pair_encoded, add_special_tokens, pad)
+    }
+
+    /// Encode the given input like `encode`, taking the vectors of the `Encoding` from the given
+    /// buffers instead of allocating new ones. Giving the `Encoding`s back to the buffers once
+    /// they are not needed anymore, with `EncodeBuffers::recycle`, avoids most allocations when
+    /// encoding many inputs one after the other.
+    pub fn encode_into(
+        &self,
+        input: EncodeInput,
+        add_special_tokens: bool,
+        buffers: &mut EncodeBuffers,
+    ) -> Result<Encoding> {
+        let (encoded, pair_encoded) = self.encode_sequences(input, true, buffers)?;
+        self.finish_encoding(encoded, pair_encoded, add_special_tokens, true)
+            .map(|(encoding, _)| encoding)
+    }
+
+    /// Encode a single sequence already split on the added tokens, as returned by
+    /// `split_on_added_tokens`. When encoding many inputs with the same added tokens at the same
+    /// places, the split can be computed once, and only the other parts updated.
+    pub fn encode_presplit(
+        &self,
+        splits: Vec<(String, Option<u32>)>,
+        type_id: u32,
+        add_special_tokens: bool,
+    ) -> Result<Encoding> {
+        let splits = splits.iter().map(|(split, id)| Ok((split.as_str(), *id)));
+        let encoded = self.encode_splits(splits, type_id, true, &mut EncodeBuffers::new())?;
+        self.finish_encoding(encoded, None, add_special_tokens, true)
+            .map(|(encoding, _)| encoding)
+    }
+
+    /// Encode the given `NormalizedString`, as produced by some `Normalizer`, without normalizing
+    /// it again. This allows normalizing a sequence once, and encoding it with several
+    /// tokenizers. The added tokens are extracted from the input before its normalization, so
+    /// they are not looked for here. The offsets refer to the original string.
+    pub fn encode_normalized(
+        &self,
+        normalized: NormalizedString,
+        add_special_tokens: bool,
+    ) -> Result<Encoding> {
+        let encoded = self.encode_normalized_sequence(normalized, 0, &mut EncodeBuffers::new())?;
+        self.finish_encoding(encoded, None, add_special_tokens, true)
+            .map(|(encoding, _)| encoding)
+    }
+
+    /// Encode the given input, considering that it is already normalized: the `Normalizer` is
+    /// not applied, but everything else is. The offsets then map directly to the input.
+    pub fn encode_prenormalized(
+        &self,
+        input: EncodeInput,
+        add_special_tokens: bool,
+    ) -> Result<Encoding> {
+        let (encoded, pair_encoded) =
+            self.encode_sequences(input, false, &mut EncodeBuffers::new())?;
+        self.finish_encoding(encoded, pair_encoded, add_special_tokens, true)
+            .map(|(encoding, _)| encoding)
+    }
+
+    /// Encode the given input both with and without the special tokens, returning
+    /// `(with special tokens, without special tokens)`. This is cheaper than calling `encode`
+    /// twice, since only the post-processing gets done twice.
+    pub fn encode_both(&self, input: EncodeInput) -> Result<(Encoding, Encoding)> {
+        let (encoded, pair_encoded) =
+            self.encode_sequences(input, true, &mut EncodeBuffers::new())?;
+        let (with_special, _) =
+            self.finish_encoding(encoded.clone(), pair_encoded.clone(), true, true)?;
+        let (without_special, _) = self.finish_encoding(encoded, pair_encoded, false, true)?;
+        Ok((with_special, without_special))
+    }
+
+    /// Encode each sequence of the given input, up to the `Model`
+    #[allow(clippy::type_complexity)]
+    fn encode_sequences(
+        &self,
+        input: EncodeInput,
+        normalize: bool,
+        buffers: &mut EncodeBuffers,
+    ) -> Result<(
+        (Encoding, NormalizedString),
+        Option<(Encoding, NormalizedString)>,
+    )> {
         let (sentence, pair) = match input {
             EncodeInput::Single(s1) => (s1, None),
             EncodeInput::Dual(s1, s2) => (s1, Some(s2)),
         };
 
-        let (encoding, normalized) = generate_output(sentence, 0)?;
-        let (pair_encoding, pair_normalized) = match pair {
-            Some(pair) => {
-                let (e, n) = generate_output(pair, 1)?;
-                (Some(e), Some(n))
-            }
-            None => (None, None),
+        let encoded = self.encode_single_sequence(&sentence, 0, normalize, buffers)?;
+        let pair_encoded = match pair {
+            Some(pair) => Some(self.encode_single_sequence(&pair, 1, normalize, buffers)?),
+            None => None,
         };
 
+        Ok((encoded, pair_encoded))
+    }
+
+    /// Post-process the encoded sequences, and convert their offsets back to the original
+    /// inputs, using the given `NormalizedString`.
+    fn finish_encoding(
+        &self,
+        (encoding, normalized): (Encoding, NormalizedString),
+        pair: Option<(Encoding, NormalizedString)>,
+        add_special_tokens: bool,
+        pad: bool,
+    ) -> Result<(Encoding, EncodeInfo)> {
+        let (pair_encoding, pair_normalized) = pair.unzip();
+
+        // Inputs without any token might not want the special tokens
+        let is_empty = encoding.get_ids().is_empty()
+            && pair_encoding
+                .as_ref()
+                .is_none_or(|e| e.get_ids().is_empty());
+        let add_special_tokens = add_special_tokens && (self.special_tokens_on_empty || !is_empty);
+
         // 4. Post processing
-        let mut output = self.post_process(encoding, pair_encoding, add_special_tokens)?;
-
-        // 5. Convert offsets back to original string
-        let mut current_offset = (0, 0);
-        let mut n_source = &normalized;
-        output
-            .get_offsets_mut()
-            .iter_mut()
-            .for_each(|(start, end)| {
-                if (*start, *end) < current_offset {
-                    n_source = &pair_normalized.as_ref().unwrap_or(&normalized);
+        let (mut output, first_len, num_truncated) =
+            self.post_process(encoding, pair_encoding, add_special_tokens, pad)?;
+        // The `PostProcessor` builds a new `Encoding`, so we need to keep track of this ourselves
+        output.set_num_truncated(num_truncated);
+
+        // 5. Convert offsets back to original string. The post-processor only adds special
+        // tokens around the sequences, so the first `first_len` other tokens belong to the first
+        // sequence, and the following ones to the pair. The special tokens don't come from the
+        // input, so they always get `(0, 0)`, whatever the post-processor gave them.
+        let special_tokens_mask = output.get_special_tokens_mask().to_vec();
+        let mut n_tokens = 0;
+        for (index, (start, end)) in output.get_offsets_mut().iter_mut().enumerate() {
+            if special_tokens_mask[index] == 1 {
+                *start = 0;
+                *end = 0;
+                continue;
+            }
+            let n_source = if n_tokens < first_len {
+                &normalized
+            } else {
+                pair_normalized.as_ref().unwrap_or(&normalized)
+            };
+            n_tokens += 1;
+            let (s, e) = match n_source.convert_offsets(Range::Normalized(*start..*end)) {
+                Some(range) => (range.start, range.end),
+                None if self.validation && start < end => {
+                    return Err(Box::new(Error::InvalidOffsets(index, (*start, *end))));
                 }
-                current_offset = (*start, *end);
-                let (s, e) = n_source
-                    .convert_offsets(Range::Normalized(*start..*end))
-                    .map_or((*start, *end), |range| (range.start, range.end));
-                *start = s;
-                *end = e;
-            });
+                None => (*start, *end),
+            };
+            *start = s;
+            *end = e;
+        }
 
-        Ok(output)
+        // 6. Some original chars may have been removed by the normalization between two tokens
+        // of the same word. We give them to the following token, so that the tokens of a word
+        // cover its whole span without any gap.
+        let word_starts = output.get_word_starts().to_vec();
+        let offsets = output.get_offsets_mut();
+        for index in 1..offsets.len() {
+            let continues_word = !word_starts[index]
+                && special_tokens_mask[index] == 0
+                && special_tokens_mask[index - 1] == 0;
+            let prev_end = offsets[index - 1].1;
+            if continues_word && prev_end < offsets[index].0 {
+                offsets[index].0 = prev_end;
+            }
+        }
+
+        if self.validation {
+            let max_len = std::cmp::max(
+                normalized.len_original(),
+                pair_normalized.as_ref().map_or(0, |n| n.len_original()),
+            );
+            self.validate_offsets(&output, max_len, pair_normalized.is_some())?;
+        }
+
+        Ok((
+            output,
+            EncodeInfo {
+                truncated: num_truncated > 0,
+                num_truncated,
+            },
+        ))
     }
 
-    /// Encode all the sentences in parallel, using multiple threads
+    /// Check that the offsets of the given `Encoding` are all valid: each of them must be a
+    /// valid range of the original input, and they can go backward only when switching to the
+    /// pair sequence. Special tokens are ignored.
+    fn validate_offsets(&self, encoding: &Encoding, max_len: usize, is_pair: bool) -> Result<()> {
+        let mut switched = !is_pair;
+        let mut last_start = 0;
+        for (index, (offsets, special)) in encoding
+            .get_offsets()
+            .iter()
+            .zip(encoding.get_special_tokens_mask())
+            .enumerate()
+        {
+            if *special == 1 {
+                continue;
+            }
+            if offsets.0 > offsets.1 || offsets.1 > max_len {
+                return Err(Box::new(Error::InvalidOffsets(index, *offsets)));
+            }
+            if offsets.0 < last_start {
+                if switched {
+                    return Err(Box::new(Error::NonMonotonicOffsets(index)));
+                }
+                switched = true;
+            }
+            last_start = offsets.0;
+        }
+
+        Ok(())
+    }
+
+    /// Encode all the sentences in parallel, using multiple threads. If a maximum number of
+    /// inputs in flight is set, they get processed by chunks of this size.
     pub fn encode_batch(
         &self,
         inputs: Vec<EncodeInput>,
         add_special_tokens: bool,
     ) -> Result<Vec<Encoding>> {
-        let encodings = inputs
+        self.encode_batch_with(inputs, add_special_tokens, |encoding| encoding)
+    }
+
+    /// Encode all the sentences like `encode_batch`, adding the special tokens as set with
+    /// `with_add_special_tokens`
+    pub fn encode_batch_default(&self, inputs: Vec<EncodeInput>) -> Result<Vec<Encoding>> {
+        self.encode_batch(inputs, self.add_special_tokens)
+    }
+
+    /// Encode all the sentences in parallel like `encode_batch`, but using the threads of the
+    /// given pool instead of the global one.
+    pub fn encode_batch_in(
+        &self,
+        pool: &rayon::ThreadPool,
+        inputs: Vec<EncodeInput>,
+        add_special_tokens: bool,
+    ) -> Result<Vec<Encoding>> {
+        pool.install(|| self.encode_batch(inputs, add_special_tokens))
+    }
+
+    /// Encode all the sentences in parallel, like `encode_batch`, but with the tokens shared
+    /// with the vocabulary instead of copied in each `Encoding`. On large batches, this saves a
+    /// lot of memory since the same tokens come back over and over. Without padding, each
+    /// `Encoding` is converted as soon as it is produced. The shared vocabulary is built on the
+    /// first call, and kept until the vocabulary changes.
+    pub fn encode_batch_shared(
+        &self,
+        inputs: Vec<EncodeInput>,
+        add_special_tokens: bool,
+    ) -> Result<Vec<SharedEncoding>> {
+        let vocab_r = self.shared_vocab.get_or_build(|| {
+            self.get_vocab(true)
+                .into_iter()
+                .map(|(token, id)| (id, Arc::from(token)))
+                .collect()
+        });
+
+        self.encode_batch_with(inputs, add_special_tokens, |encoding| {
+            encoding.into_shared(&vocab_r)
+        })
+    }
+
+    /// Encode and pad the inputs by chunks of `max_in_flight`, converting each `Encoding` with
+    /// `convert` as soon as its chunk is done.
+    fn encode_batch_with<T, F>(
+        &self,
+        inputs: Vec<EncodeInput>,
+        add_special_tokens: bool,
+        convert: F,
+    ) -> Result<Vec<T>>
+    where
+        T: Send,
+        F: Fn(Encoding) -> T + Sync,
+    {
+        let chunk_size = self.max_in_flight.unwrap_or(inputs.len()).max(1);
+
+        let mut encodings = Vec::with_capacity(inputs.len());
+        let mut inputs = inputs.into_iter();
+        loop {
+            let chunk = inputs.by_ref().take(chunk_size).collect::<Vec<_>>();
+            if chunk.is_empty() {
+                break;
+            }
+
+            let chunk = chunk
+                .into_par_iter()
+                .map(|input| self.encode_no_pad(input, add_special_tokens));
+
+            if let Some(params) = &self.padding {
+                // We do the padding here to make sure we handle the batch padding
+                let chunk = pad_encodings(chunk.collect::<Result<Vec<Encoding>>>()?, params)?;
+                encodings.par_extend(chunk.into_par_iter().map(&convert));
+            } else {
+                // Without padding, each `Encoding` is complete and can be converted right away
+                let chunk = chunk
+                    .map(|encoding| encoding.map(&convert))
+                    .collect::<Result<Vec<T>>>()?;
+                encodings.extend(chunk);
+            }
+        }
+
+        Ok(encodings)
+    }
+
+    /// Encode all the sentences in parallel, grouping the encodings with similar lengths in the
+    /// same bucket to reduce the padding. The lengths of the encodings in a bucket differ by at
+    /// most `bucket_tolerance` tokens, and each bucket gets padded separately.
+    /// Each bucket also contains the indices of its encodings in `inputs`, to restore their
+    /// original order.
+    pub fn encode_batch_bucketed(
+        &self,
+        inputs: Vec<EncodeInput>,
+        add_special_tokens: bool,
+        bucket_tolerance: usize,
+    ) -> Result<Vec<(Vec<usize>, Vec<Encoding>)>> {
+        let mut encodings = inputs
             .into_par_iter()
-            .map(|input| self.encode(input, add_special_tokens))
-            .collect::<Result<Vec<Encoding>>>()?;
+            .map(|input| self.encode_no_pad(input, add_special_tokens))
+            .collect::<Result<Vec<Encoding>>>()?
+            .into_iter()
+            .enumerate()
+            .collect::<Vec<_>>();
+        encodings.sort_by_key(|(_, encoding)| encoding.get_ids().len());
+
+        let mut buckets: Vec<(Vec<usize>, Vec<Encoding>)> = vec![];
+        let mut bucket_start = 0;
+        for (index, encoding) in encodings {
+            let len = encoding.get_ids().len();
+            match buckets.last_mut() {
+                Some((indices, bucket)) if len - bucket_start <= bucket_tolerance => {
+                    indices.push(index);
+                    bucket.push(encoding);
+                }
+                _ => {
+                    bucket_start = len;
+                    buckets.push((vec![index], vec![encoding]));
+                }
+            }
+        }
 
         if let Some(params) = &self.padding {
-            // We do the padding here to make sure we handle the batch padding
-            pad_encodings(encodings, &params)
-        } else {
-            Ok(encodings)
+            buckets = buckets
+                .into_iter()
+                .map(|(indices, bucket)| Ok((indices, pad_encodings(bucket, params)?)))
+                .collect::<Result<_>>()?;
         }
+        Ok(buckets)
+    }
+
+    /// Returns the number of special tokens the `PostProcessor` adds to a single sequence or
+    /// to a pair of sequences.
+    pub fn num_special_tokens_to_add(&self, is_pair: bool) -> usize {
+        self.post_processor
+            .as_ref()
+            .map_or(0, |processor| processor.added_tokens(is_pair))
+    }
+
+    /// Returns the maximum number of tokens that a single sequence or a pair of sequences can
+    /// have before getting truncated, once the special tokens have been added. This is `None`
+    /// when there is no truncation, including with a `max_length` of 0.
+    pub fn effective_max_length(&self, is_pair: bool) -> Option<usize> {
+        self.trunc
+            .as_ref()
+            .filter(|trunc| trunc.max_length > 0)
+            .map(|trunc| {
+                trunc
+                    .max_length
+                    .saturating_sub(self.num_special_tokens_to_add(is_pair))
+            })
+    }
+
+    /// Select the messages of a conversation that fit in `max_tokens`, according to the given
+    /// `KeepPolicy`. Each message is counted with its own special tokens, and neither truncation
+    /// nor padding is applied while counting. Returns the indices of the kept messages, in order.
+    pub fn fit_to_budget(
+        &self,
+        messages: &[String],
+        max_tokens: usize,
+        keep: KeepPolicy,
+    ) -> Result<Vec<usize>> {
+        let n_special = self.num_special_tokens_to_add(false);
+        let costs = messages
+            .par_iter()
+            .map(|message| {
+                self.encode_single_sequence(message, 0, true, &mut EncodeBuffers::new())
+                    .map(|(encoding, _)| encoding.get_ids().len() + n_special)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut kept = vec![];
+        let mut used = 0;
+        let mut first_latest = 0;
+        if keep == KeepPolicy::FirstAndLatest && !costs.is_empty() {
+            first_latest = 1;
+            if costs[0] <= max_tokens {
+                kept.push(0);
+                used += costs[0];
+            }
+        }
+
+        let mut latest = vec![];
+        for (i, cost) in costs.iter().enumerate().skip(first_latest).rev() {
+            if used + cost > max_tokens {
+                break;
+            }
+            used += cost;
+            latest.push(i);
+        }
+        kept.extend(latest.into_iter().rev());
+
+        Ok(kept)
     }
 
     /// Decode the given ids, back to a String
     pub fn decode(&self, ids: Vec<u32>, skip_special_tokens: bool) -> Result<String> {
+        self.decode_iter(ids, skip_special_tokens)
+    }
+
+    /// Decode the ids yielded by the given iterator, without having to collect them first.
+    /// The tokens are converted lazily, and then given to the `Decoder` all at once, so that
+    /// it can handle what spans multiple tokens (like the bytes of a char with byte-level).
+    pub fn decode_iter<I>(&self, ids: I, skip_special_tokens: bool) -> Result<String>
+    where
+        I: IntoIterator<Item = u32>,
+    {
         let tokens = ids
             .into_iter()
-            .map(|id| {
-                let token = if let Some(token) = self.added_tokens_r.get(&id) {
-                    Some(token.content.to_owned())
-                } else {
-                    self.model.id_to_token(id)
-                };
-
-                token.filter(|token| {
-                    !skip_special_tokens || !self.special_tokens.contains_key(token)
+            .filter_map(|id| {
+                self.id_to_token(id).filter(|token| {
+                    !skip_special_tokens || !self.added_vocabulary.is_special(token)
                 })
             })
-            .filter(|token| token.is_some())
-            .map(|id| id.unwrap())
             .collect::<Vec<_>>();
 
         if let Some(decoder) = &self.decoder {
             decoder.decode(tokens)
         } else {
-            Ok(tokens.join(" "))
+            Ok(tokens.join(self.decode_separator.as_deref().unwrap_or(" ")))
         }
     }
 
+    /// Check whether the given text gets decoded back to itself after being encoded. The
+    /// normalization is usually not reversible, so the decoded text is compared to the normalized
+    /// one. The truncation, padding and special tokens don't apply here.
+    pub fn is_lossless(&self, text: &str) -> Result<bool> {
+        let (encoding, _) =
+            self.encode_single_sequence(text, 0, true, &mut EncodeBuffers::new())?;
+        let decoded = self.decode(encoding.get_ids().to_vec(), false)?;
+
+        let normalized = self
+            .split_on_added_tokens(text)
+            .map(|split| {
+                let (sentence, id) = split?;
+                if id.is_some() {
+                    Ok(sentence.to_owned())
+                } else {
+                    Ok(self.do_normalize(sentence)?.get().to_owned())
+                }
+            })
+            .collect::<Result<String>>()?;
+
+        Ok(decoded == normalized)
+    }
+
+    /// Start decoding ids one at a time, as they get generated. See `DecodeStream`.
+    pub fn decode_stream(&self, skip_special_tokens: bool) -> DecodeStream<'_> {
+        DecodeStream::new(self, skip_special_tokens)
+    }
+
     /// Decode all sentences in parallel
     pub fn decode_batch(
         &self,
@@ -501,56 +1769,79 @@ impl Tokenizer {
             .collect()
     }
 
-    /// Train a model and replace our current Model, using the given Trainer
+    /// Decode all sentences in parallel, and join the results with the given separator
+    pub fn decode_batch_joined(
+        &self,
+        sentences: Vec<Vec<u32>>,
+        skip_special_tokens: bool,
+        sep: &str,
+    ) -> Result<String> {
+        Ok(self.decode_batch(sentences, skip_special_tokens)?.join(sep))
+    }
+
+    /// Train a model and replace our current Model, using the given Trainer. The progress of
+    /// the files reading is shown only if the `Trainer` asks for it.
     #[allow(clippy::borrowed_box)]
     pub fn train(&mut self, trainer: &Box<dyn Trainer>, files: Vec<String>) -> Result<()> {
-        let progress = ProgressBar::new(100 * files.len() as u64);
-        progress.set_style(
-            ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] {msg:<40!} {wide_bar} {percent:>19!}"),
-        );
-        progress.set_message("Reading files");
+        let words = self.count_words(trainer, files)?;
 
-        let results = files
-            .into_par_iter()
-            .map(|filename| -> Result<HashMap<String, u32>> {
-                let mut words = HashMap::new();
-                let file = File::open(filename)?;
-                let len = file.metadata().map_or(0, |c| c.len());
-                let mut file = BufReader::new(file);
-                let mut prev_prog = 0;
-                let mut read = 0;
-                let mut curr_prog;
-
-                let mut buf = String::new();
-                loop {
-                    buf.clear();
-                    // We read new lines using this API instead of the Lines Iterator
-                    // on purpose. We want to keep the `\n` and potential `\r` between each lines
-                    match file.read_line(&mut buf)? {
-                        0 => break,
-                        b => {
-                            let mut normalized = self.do_normalize(&buf)?;
-                            let pre_tokenized = self.pre_tokenize(&mut normalized)?;
-                            trainer.process_tokens(
-                                &mut words,
-                                pre_tokenized.into_iter().map(|(t, _)| t).collect(),
-                            );
-
-                            read += b as u64;
-                            curr_prog = ((read as f64 / len as f64) * 100.0) as u64;
-                            if curr_prog > prev_prog {
-                                progress.inc(curr_prog - prev_prog);
-                                prev_prog = curr_prog;
-                            }
-                        }
-                    }
-                }
+        let (model, special_tokens) = trainer.train(words)?;
+        self.model = model;
+        self.add_special_tokens(&special_tokens);
 
-                Ok(words)
-            })
-            .collect::<Vec<_>>();
-        progress.finish();
+        Ok(())
+    }
+
+    /// Train a model using the given Trainer, and add the tokens it learned to our current
+    /// Model, instead of replacing it. The existing tokens keep their id, and the new ones get
+    /// the ids following the current vocabulary, skipping the ones used by the added tokens.
+    ///
+    /// This is only supported by the models that can extend their vocabulary (see
+    /// `Model::extend_vocab`): WordLevel and WordPiece. BPE and Unigram would need to merge their
+    /// merges or scores too, and return an error.
+    #[allow(clippy::borrowed_box)]
+    pub fn train_incremental(
+        &mut self,
+        trainer: &Box<dyn Trainer>,
+        files: Vec<String>,
+    ) -> Result<()> {
+        let words = self.count_words(trainer, files)?;
+        let (model, special_tokens) = trainer.train(words)?;
+
+        let mut learned = model.get_vocab().iter().collect::<Vec<_>>();
+        learned.sort_by_key(|(_, id)| **id);
+        let mut next_id = self.model.get_vocab().values().max().map_or(0, |id| id + 1);
+        let mut new_tokens = vec![];
+        for (token, _) in learned {
+            if self.token_to_id(token).is_some() {
+                continue;
+            }
+            while self.added_vocabulary.id_to_token(next_id).is_some() {
+                next_id += 1;
+            }
+            new_tokens.push((token.to_owned(), next_id));
+            next_id += 1;
+        }
+        self.model.extend_vocab(new_tokens)?;
+        self.add_special_tokens(&special_tokens);
+
+        Ok(())
+    }
+
+    /// Count the words in the given files, as pre-tokenized by our pipeline and processed by
+    /// the given Trainer
+    #[allow(clippy::borrowed_box)]
+    fn count_words(
+        &self,
+        trainer: &Box<dyn Trainer>,
+        files: Vec<String>,
+    ) -> Result<HashMap<String, u32>> {
+        let results = read_files(files, trainer.should_show_progress(), |words, line| {
+            let mut normalized = self.do_normalize(line)?;
+            let pre_tokenized = self.pre_tokenize(&mut normalized)?;
+            trainer.process_tokens(words, pre_tokenized.into_iter().map(|(t, _)| t).collect());
+            Ok(())
+        });
 
         let mut words = HashMap::new();
         for result in results {
@@ -562,22 +1853,52 @@ impl Tokenizer {
             }
         }
 
-        let (model, special_tokens) = trainer.train(words)?;
-        self.model = model;
-        self.add_special_tokens(&special_tokens);
+        Ok(words)
+    }
 
-        Ok(())
+    /// Tokenize every line of the given files, and gather some statistics about it. This helps
+    /// comparing several tokenizers on the same corpus. The files are processed in parallel.
+    pub fn corpus_stats(&self, files: Vec<String>) -> Result<CorpusStats> {
+        let unk_id = self.model.get_unk_id();
+        let results = read_files(files, false, |stats: &mut CorpusStats, line| {
+            let line = line.trim_end_matches(&['\n', '\r'][..]);
+            let (encoding, _) =
+                self.encode_single_sequence(line, 0, true, &mut EncodeBuffers::new())?;
+            stats.lines += 1;
+            stats.chars += line.chars().count();
+            stats.tokens += encoding.get_ids().len();
+            for id in encoding.get_ids() {
+                if Some(*id) == unk_id {
+                    stats.unknown_tokens += 1;
+                }
+                *stats.token_counts.entry(*id).or_insert(0) += 1;
+            }
+            Ok(())
+        });
+
+        let mut stats = CorpusStats::default();
+        for result in results {
+            stats.merge_with(result?);
+        }
+        Ok(stats)
     }
 
-    /// PreTokenization logic, handling the case where there is no PreTokenizer set
+    /// PreTokenization logic, handling the case where there is no PreTokenizer set, or where
+    /// the Model handles it itself
     fn pre_tokenize(
         &self,
         mut normalized: &mut NormalizedString,
     ) -> Result<Vec<(String, Offsets)>> {
-        match &self.pre_tokenizer {
-            None => Ok(vec![(normalized.get().to_owned(), (0, normalized.len()))]),
-            Some(pre_tokenizer) => pre_tokenizer.pre_tokenize(&mut normalized),
+        let mut pre_tokenized = match &self.pre_tokenizer {
+            Some(pre_tokenizer) if !self.model.handles_pre_tokenization() => {
+                pre_tokenizer.pre_tokenize(&mut normalized)?
+            }
+            _ => vec![(normalized.get().to_owned(), (0, normalized.len()))],
+        };
+        if !self.keep_empty_pre_tokens {
+            pre_tokenized.retain(|(pre_token, _)| !pre_token.is_empty());
         }
+        Ok(pre_tokenized)
     }
 
     /// Normalization logic, go through all normalizers
@@ -591,25 +1912,36 @@ impl Tokenizer {
         Ok(normalized)
     }
 
-    /// Post processing logic, handling the case where there is no PostProcessor set
+    /// Post processing logic, handling the case where there is no PostProcessor set.
+    /// Also returns the number of tokens left in the first sequence after the truncation, and
+    /// the number of tokens removed by the truncation.
     fn post_process(
         &self,
         encoding: Encoding,
         pair_encoding: Option<Encoding>,
         add_special_tokens: bool,
-    ) -> Result<Encoding> {
+        pad: bool,
+    ) -> Result<(Encoding, usize, usize)> {
+        let len = |encoding: &Encoding, pair_encoding: &Option<Encoding>| {
+            encoding.get_ids().len() + pair_encoding.as_ref().map_or(0, |e| e.get_ids().len())
+        };
+        let original_len = len(&encoding, &pair_encoding);
+
         // 1. First we truncate if needed
         let (encoding, pair_encoding) = {
             if let Some(trunc) = &self.trunc {
-                let n_added_tokens = if let Some(processor) = &self.post_processor {
-                    processor.added_tokens(pair_encoding.is_some())
-                } else {
-                    0
-                };
-
-                if add_special_tokens && n_added_tokens > 0 {
+                if add_special_tokens && trunc.max_length > 0 {
+                    // A `max_length` of 0 disables the truncation, so it must not be reached by
+                    // removing the special tokens
+                    let n_special = self.num_special_tokens_to_add(pair_encoding.is_some());
+                    if n_special > 0 && trunc.max_length <= n_special {
+                        return Err(Box::new(Error::TruncationTooSmall(
+                            trunc.max_length,
+                            n_special,
+                        )));
+                    }
                     let params = TruncationParams {
-                        max_length: trunc.max_length - n_added_tokens,
+                        max_length: trunc.max_length - n_special,
                         ..*trunc
                     };
                     truncate_encodings(encoding, pair_encoding, &params)?
@@ -620,6 +1952,8 @@ impl Tokenizer {
                 (encoding, pair_encoding)
             }
         };
+        let num_truncated = original_len - len(&encoding, &pair_encoding);
+        let first_len = encoding.get_ids().len();
 
         // 2. Then We post process
         let mut final_encoding = if let Some(processor) = &self.post_processor {
@@ -629,7 +1963,7 @@ impl Tokenizer {
         };
 
         // 3. Then we pad if needed
-        if let Some(params) = &self.padding {
+        if let Some(params) = self.padding.as_ref().filter(|_| pad) {
             // We can only pad for a given size. If the Strategy is BatchLongest, it will be done
             // when we handle a batch
             let size = if let PaddingStrategy::Fixed(size) = params.strategy {
@@ -647,165 +1981,220 @@ impl Tokenizer {
             );
         }
 
-        Ok(final_encoding)
+        Ok((final_encoding, first_len, num_truncated))
     }
 
     /// Register the given tokens as special tokens. This is especially useful for removing
     /// these special tokens while decoding
     pub fn add_special_tokens<T: AsRef<str>>(&mut self, tokens: &[T]) -> usize {
-        let added_tokens = tokens
-            .iter()
-            .map(|t| AddedToken::from(t.as_ref().to_owned()))
-            .collect::<Vec<_>>();
-
-        let added = self.add_tokens(&added_tokens);
-        for token in tokens {
-            if let Some(id) = self.token_to_id(token.as_ref()) {
-                self.special_tokens
-                    .entry(token.as_ref().to_owned())
-                    .or_insert(id);
-            }
-        }
-
-        self.refresh_added_tokens();
-
-        added
+        self.shared_vocab.clear();
+        self.added_vocabulary
+            .add_special_tokens(tokens, self.model.as_ref())
     }
 
     /// Add the given tokens to the added vocabulary
     pub fn add_tokens(&mut self, tokens: &[AddedToken]) -> usize {
-        let mut ignored = 0;
-        for token in tokens {
-            if token.content.is_empty() || self.token_to_id(&token.content).is_some() {
-                ignored += 1;
-                continue;
-            }
+        self.shared_vocab.clear();
+        self.added_vocabulary
+            .add_tokens(tokens, self.model.as_ref())
+    }
 
-            let new_id = (self.model.get_vocab_size() + self.added_tokens.len()) as u32;
-            let id = self
-                .added_tokens
-                .entry(token.clone())
-                .and_modify(|_| ignored += 1)
-                .or_insert(new_id);
+    /// Add the given tokens to the added vocabulary, unless some of them are already part of the
+    /// vocabulary. Unlike `add_tokens`, which ignores these tokens, this returns an error
+    /// listing them, and adds nothing.
+    pub fn add_tokens_checked(&mut self, tokens: &[AddedToken]) -> Result<usize> {
+        self.shared_vocab.clear();
+        self.added_vocabulary
+            .add_tokens_checked(tokens, self.model.as_ref())
+    }
 
-            // Update the current revert operation
-            self.added_tokens_r
-                .entry(*id)
-                .and_modify(|t| *t = token.clone())
-                .or_insert_with(|| token.clone());
-        }
+    /// Add the given tokens to the added vocabulary, reporting which ones have been added, and
+    /// which ones were ignored because they are empty or already part of the vocabulary.
+    pub fn add_tokens_detailed(&mut self, tokens: &[AddedToken]) -> AddResult {
+        self.shared_vocab.clear();
+        self.added_vocabulary
+            .add_tokens_detailed(tokens, self.model.as_ref())
+    }
 
-        self.refresh_added_tokens();
+    /// Add the given token to the added vocabulary, using the given id instead of the next
+    /// available one. This is useful to match the embeddings of a pretrained model.
+    /// An error is returned if the token or the id is already part of the vocabulary.
+    pub fn add_token_with_id(&mut self, token: AddedToken, id: u32) -> Result<()> {
+        self.shared_vocab.clear();
+        self.added_vocabulary
+            .add_token_with_id(token, id, self.model.as_ref())
+    }
 
-        // Return the number of added tokens
-        tokens.len() - ignored
+    /// Register the given token as a special token, using the given id. An error is returned if
+    /// the token or the id is already part of the vocabulary.
+    pub fn add_special_token_with_id(&mut self, token: &str, id: u32) -> Result<()> {
+        self.shared_vocab.clear();
+        self.added_vocabulary
+            .add_special_token_with_id(token, id, self.model.as_ref())
     }
 
-    fn refresh_added_tokens(&mut self) {
-        // We rebuild the regex here everytime on purpose, because the added tokens may
-        // have changed
-        let special_tokens = self
-            .special_tokens
-            .keys()
-            .map(|t| AddedToken {
-                content: t.to_owned(),
-                single_word: true,
-            })
-            .collect::<Vec<_>>();
-        let added_tokens = self
-            .added_tokens
-            .keys()
-            .chain(special_tokens.iter())
-            .map(|token| {
-                if token.single_word {
-                    let first_b = token
-                        .content
-                        .chars()
-                        .next()
-                        .map(|c| {
-                            if regex_syntax::is_word_character(c) {
-                                r"\b"
-                            } else {
-                                ""
-                            }
-                        })
-                        .unwrap();
-                    let last_b = token
-                        .content
-                        .chars()
-                        .last()
-                        .map(|c| {
-                            if regex_syntax::is_word_character(c) {
-                                r"\b"
-                            } else {
-                                ""
-                            }
-                        })
-                        .unwrap();
-                    format!(r"{}{}{}", first_b, regex::escape(&token.content), last_b)
-                } else {
-                    regex::escape(&token.content)
-                }
-            })
-            .collect::<Vec<_>>();
+    /// Find the added and special tokens in the given text, exactly like while encoding it.
+    /// Returns each of them with its id and its offsets (in chars) in the text, in order of
+    /// appearance.
+    pub fn find_added_tokens(&self, text: &str) -> Vec<(String, u32, Offsets)> {
+        let mut chars = 0;
+        let mut last_byte = 0;
+        let mut char_offset = |byte: usize| {
+            chars += text[last_byte..byte].chars().count();
+            last_byte = byte;
+            chars
+        };
 
-        if added_tokens.is_empty() {
-            self.split_re = None;
-        } else {
-            self.split_re =
-                Some(regex::Regex::new(&format!(r"({})", added_tokens.join("|"))).unwrap());
-        }
+        self.added_vocabulary
+            .find_matches(text)
+            .map(|((start, end), id)| {
+                let offsets = (char_offset(start), char_offset(end));
+                (text[start..end].to_owned(), id, offsets)
+            })
+            .collect()
     }
 
-    /// Split the given sentence on multiple parts, finding the added tokens and their id in the process
-    fn split_on_added_tokens(&self, sentence: &str) -> Vec<(String, Option<u32>)> {
-        if let Some(split_re) = &self.split_re {
-            let splits = split_re
-                .find_iter(&sentence)
-                .map(|m| (m.start(), m.end()))
-                .collect::<Vec<_>>();
-
-            // We also insert the splits that are inbetween the added tokens, to split the entire string
-            let mut start_offset = 0;
-            let mut splits = splits
-                .into_iter()
-                .flat_map(|(start, end)| {
-                    let mut splits = vec![];
-                    if start_offset < start {
-                        splits.push((start_offset, start));
-                    }
-                    splits.push((start, end));
+    /// Split the given sentence on multiple parts, finding the added tokens and their id in the
+    /// process. Each part is given with the id of its added token, or `None` for the parts in
+    /// between that go through the whole pipeline. See `encode_presplit`.
+    ///
+    /// The parts are produced lazily, so an input with many added tokens can be processed
+    /// without holding all of its parts at once.
+    pub fn split_on_added_tokens<'a>(
+        &'a self,
+        sentence: &'a str,
+    ) -> impl Iterator<Item = Result<(&'a str, Option<u32>)>> + 'a {
+        // We also produce the splits that are inbetween the added tokens, to split the entire
+        // string. The last `None` marks the end of the matches, to produce the trailing split.
+        let mut start_offset = 0;
+        let mut found_match = false;
+        let splits = self
+            .added_vocabulary
+            .find_matches(sentence)
+            .map(Some)
+            .chain(std::iter::once(None))
+            .flat_map(move |m| match m {
+                Some(((start, end), id)) => {
+                    // The matches never overlap. When two added tokens are adjacent (like
+                    // `<a><b>`), there is simply nothing to insert between them, and each keeps
+                    // its own split.
+                    debug_assert!(start_offset <= start);
+                    let before =
+                        Some(((start_offset, start), None)).filter(|_| start_offset < start);
                     start_offset = end;
-
-                    splits
-                })
-                .collect::<Vec<_>>();
-            if let Some((_, end)) = splits.iter().last().copied() {
-                if end < sentence.len() {
-                    splits.push((end, sentence.len()));
+                    found_match = true;
+                    before.into_iter().chain(Some(((start, end), Some(id))))
                 }
-            }
+                None => {
+                    // Without any added token, the whole sentence is a single split, even empty
+                    let rest = Some(((start_offset, sentence.len()), None))
+                        .filter(|_| !found_match || start_offset < sentence.len());
+                    rest.into_iter().chain(None)
+                }
+            });
 
-            if splits.is_empty() {
-                vec![(sentence.to_owned(), None)]
+        splits.map(move |((start, end), id)| {
+            let s = if self.validation {
+                sentence
+                    .get(start..end)
+                    .ok_or(Error::BadSplit(start, end))?
             } else {
-                splits
-                    .into_iter()
-                    .map(|(start, end)| unsafe {
-                        let s = sentence.get_unchecked(start..end).to_owned();
-                        let mut id = self.special_tokens.get(&s);
-                        if id.is_none() {
-                            id = self.added_tokens.get(&AddedToken {
-                                content: s.clone(),
-                                ..Default::default()
-                            });
+                unsafe { sentence.get_unchecked(start..end) }
+            };
+            Ok((s, id))
+        })
+    }
+}
+
+/// Read the given files line by line, in parallel, processing each line with `process_line`
+/// into a value built for each file. The lines keep their `\n` and potential `\r`.
+fn read_files<T, F>(files: Vec<String>, show_progress: bool, process_line: F) -> Vec<Result<T>>
+where
+    T: Default + Send,
+    F: Fn(&mut T, &str) -> Result<()> + Sync,
+{
+    let progress = if show_progress {
+        let progress = ProgressBar::new(100 * files.len() as u64);
+        progress.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] {msg:<40!} {wide_bar} {percent:>19!}"),
+        );
+        progress.set_message("Reading files");
+        Some(progress)
+    } else {
+        None
+    };
+
+    let results = files
+        .into_par_iter()
+        .map(|filename| -> Result<T> {
+            let mut value = T::default();
+            let (mut file, len) = open_text_file(&filename)?;
+            let mut prev_prog = 0;
+            let mut read = 0;
+            let mut curr_prog;
+
+            let mut buf = String::new();
+            loop {
+                buf.clear();
+                // We read new lines using this API instead of the Lines Iterator
+                // on purpose. We want to keep the `\n` and potential `\r` between each lines
+                match file.read_line(&mut buf)? {
+                    0 => break,
+                    b => {
+                        process_line(&mut value, &buf)?;
+
+                        read += b as u64;
+                        curr_prog = ((read as f64 / len as f64) * 100.0) as u64;
+                        if curr_prog > prev_prog {
+                            if let Some(progress) = &progress {
+                                progress.inc(curr_prog - prev_prog);
+                            }
+                            prev_prog = curr_prog;
                         }
-                        (s, id.copied())
-                    })
-                    .collect()
+                    }
+                }
             }
-        } else {
-            vec![(sentence.to_owned(), None)]
-        }
+
+            Ok(value)
+        })
+        .collect::<Vec<_>>();
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+
+    results
+}
+
+/// Open the given text file, returning a reader giving its content as UTF-8, along with the
+/// length of this content. A leading UTF-8 BOM is skipped, and the files starting with a UTF-16
+/// BOM (either little or big endian) are transcoded.
+fn open_text_file(filename: &str) -> Result<(Box<dyn BufRead>, u64)> {
+    let file = File::open(filename)?;
+    let len = file.metadata().map_or(0, |c| c.len());
+    let mut file = BufReader::new(file);
+
+    let start = file.fill_buf()?;
+    if start.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        file.consume(3);
+        return Ok((Box::new(file), len.saturating_sub(3)));
     }
+    let from_bytes = if start.starts_with(&[0xFF, 0xFE]) {
+        u16::from_le_bytes
+    } else if start.starts_with(&[0xFE, 0xFF]) {
+        u16::from_be_bytes
+    } else {
+        return Ok((Box::new(file), len));
+    };
+
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes)?;
+    let units = bytes[2..]
+        .chunks(2)
+        .map(|c| from_bytes([c[0], *c.get(1).unwrap_or(&0)]));
+    let content = std::char::decode_utf16(units)
+        .collect::<std::result::Result<String, _>>()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let len = content.len() as u64;
+    Ok((Box::new(std::io::Cursor::new(content.into_bytes())), len))
 }
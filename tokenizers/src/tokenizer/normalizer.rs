@@ -1,3 +1,4 @@
+use super::{Error, Result};
 use std::cmp::Ordering;
 use std::ops::{Bound, RangeBounds};
 use unicode_normalization_alignments::UnicodeNormalization;
@@ -73,7 +74,7 @@ impl NormalizedString {
         NormalizedString {
             original: s.to_owned(),
             normalized: s.to_owned(),
-            alignments: (0..s.chars().count()).map(|v| (v, v + 1)).collect(),
+            alignments: (0..char_count(s)).map(|v| (v, v + 1)).collect(),
         }
     }
 
@@ -211,24 +212,40 @@ impl NormalizedString {
 
     /// Applies NFD normalization
     pub fn nfd(&mut self) -> &mut Self {
+        // ASCII is left unchanged by all the normalization forms
+        if self.normalized.is_ascii() {
+            return self;
+        }
         self.transform(self.get().to_owned().nfd(), 0);
         self
     }
 
     /// Applies NFKD normalization
     pub fn nfkd(&mut self) -> &mut Self {
+        // ASCII is left unchanged by all the normalization forms
+        if self.normalized.is_ascii() {
+            return self;
+        }
         self.transform(self.get().to_owned().nfkd(), 0);
         self
     }
 
     /// Applies NFC normalization
     pub fn nfc(&mut self) -> &mut Self {
+        // ASCII is left unchanged by all the normalization forms
+        if self.normalized.is_ascii() {
+            return self;
+        }
         self.transform(self.get().to_owned().nfc(), 0);
         self
     }
 
     /// Applies NFKC normalization
     pub fn nfkc(&mut self) -> &mut Self {
+        // ASCII is left unchanged by all the normalization forms
+        if self.normalized.is_ascii() {
+            return self;
+        }
         self.transform(self.get().to_owned().nfkc(), 0);
         self
     }
@@ -296,28 +313,44 @@ impl NormalizedString {
         self
     }
 
+    /// Replace each char by the chars returned by the given function: none to remove it, or as
+    /// many as needed. The alignments are kept up to date, each new char being aligned with the
+    /// char it replaces. This is the simplest way to write a `Normalizer` that works char by char.
+    pub fn transform_chars<F, I>(&mut self, mut f: F) -> &mut Self
+    where
+        F: FnMut(char) -> I,
+        I: IntoIterator<Item = char>,
+    {
+        let mut normalized = String::with_capacity(self.normalized.len());
+        let mut alignments = Vec::with_capacity(self.alignments.len());
+        for (c, alignment) in self.normalized.chars().zip(self.alignments.iter()) {
+            for new_c in f(c) {
+                normalized.push(new_c);
+                alignments.push(*alignment);
+            }
+        }
+        self.normalized = normalized;
+        self.alignments = alignments;
+        self
+    }
+
     /// Lowercase
     pub fn lowercase(&mut self) -> &mut Self {
-        let mut new_chars: Vec<(char, isize)> = vec![];
-        self.for_each(|c| {
-            c.to_lowercase().enumerate().for_each(|(index, c)| {
-                new_chars.push((c, if index > 0 { 1 } else { 0 }));
-            })
-        });
-        self.transform(new_chars.into_iter(), 0);
-        self
+        // Each ASCII char stays a single char, so the alignments don't change
+        if self.normalized.is_ascii() {
+            self.normalized.make_ascii_lowercase();
+            return self;
+        }
+        self.transform_chars(|c| c.to_lowercase())
     }
 
     /// Uppercase
     pub fn uppercase(&mut self) -> &mut Self {
-        let mut new_chars: Vec<(char, isize)> = vec![];
-        self.for_each(|c| {
-            c.to_uppercase().enumerate().for_each(|(index, c)| {
-                new_chars.push((c, if index > 0 { 1 } else { 0 }));
-            })
-        });
-        self.transform(new_chars.into_iter(), 0);
-        self
+        if self.normalized.is_ascii() {
+            self.normalized.make_ascii_uppercase();
+            return self;
+        }
+        self.transform_chars(|c| c.to_uppercase())
     }
 
     /// Split off ourselves, returning a new Self that contains the range [at, len).
@@ -369,8 +402,10 @@ impl NormalizedString {
 
     /// Merge with the given NormalizedString by appending it to self
     pub fn merge_with(&mut self, other: &NormalizedString) {
+        // The alignments refer to the original string, so they need to be shifted by its
+        // length, which can differ from the normalized one
+        let len = self.len_original();
         self.original.push_str(&other.original);
-        let len = self.len();
         self.alignments.extend(
             other
                 .alignments
@@ -435,23 +470,104 @@ impl NormalizedString {
 
     /// Returns the length of the normalized string (counting chars not bytes)
     pub fn len(&self) -> usize {
-        self.normalized.chars().count()
+        char_count(&self.normalized)
     }
 
     /// Returns the length of the original string (counting chars not bytes)
     pub fn len_original(&self) -> usize {
-        self.original.chars().count()
+        char_count(&self.original)
     }
 
     /// Whether empty
     pub fn is_empty(&self) -> bool {
         self.normalized.len() == 0
     }
+
+    /// Return the ranges of the original string (in chars) that don't correspond to any part of
+    /// the normalized string, because they have been removed during the normalization.
+    pub fn removed_ranges(&self) -> Vec<std::ops::Range<usize>> {
+        let mut covered = vec![false; self.len_original()];
+        for (start, end) in &self.alignments {
+            covered
+                .iter_mut()
+                .take(*end)
+                .skip(*start)
+                .for_each(|c| *c = true);
+        }
+
+        let mut ranges: Vec<std::ops::Range<usize>> = vec![];
+        for (i, _) in covered.into_iter().enumerate().filter(|(_, c)| !c) {
+            match ranges.last_mut() {
+                Some(range) if range.end == i => range.end += 1,
+                _ => ranges.push(i..i + 1),
+            }
+        }
+        ranges
+    }
+
+    /// Return the whole alignment between both strings, as a list of
+    /// `(original range, normalized range)` (in chars), in the order of the normalized string.
+    /// The consecutive normalized chars that come from the same original range (like `"fi"`
+    /// from `"ﬁ"`) share the same entry, and the removed parts of the original string don't
+    /// appear at all (see `removed_ranges`).
+    pub fn get_alignments(&self) -> Vec<(std::ops::Range<usize>, std::ops::Range<usize>)> {
+        let mut table: Vec<(std::ops::Range<usize>, std::ops::Range<usize>)> = vec![];
+        for (i, (start, end)) in self.alignments.iter().enumerate() {
+            match table.last_mut() {
+                Some((original, normalized))
+                    if original.start == *start && original.end == *end =>
+                {
+                    normalized.end = i + 1
+                }
+                _ => table.push((*start..*end, i..i + 1)),
+            }
+        }
+        table
+    }
+
+    /// Check that the alignments are valid: there must be one for each normalized char, and they
+    /// must be valid ranges of the original string, that never go backward.
+    /// This helps to catch the mistakes in custom normalizers, that would otherwise silently
+    /// produce wrong offsets.
+    pub fn validate(&self) -> Result<()> {
+        let len = self.len();
+        if self.alignments.len() != len {
+            return Err(Box::new(Error::AlignmentsLength(
+                len,
+                self.alignments.len(),
+            )));
+        }
+
+        let len_original = self.len_original();
+        let mut last = (0, 0);
+        for (index, alignment) in self.alignments.iter().enumerate() {
+            if alignment.0 > alignment.1 || alignment.1 > len_original {
+                return Err(Box::new(Error::InvalidAlignment(index, *alignment)));
+            }
+            if alignment.0 < last.0 || alignment.1 < last.1 {
+                return Err(Box::new(Error::NonMonotonicAlignments(index)));
+            }
+            last = *alignment;
+        }
+
+        Ok(())
+    }
+}
+
+/// Count the chars of the given string, skipping the decoding for ASCII
+#[inline]
+fn char_count(s: &str) -> usize {
+    if s.is_ascii() {
+        s.len()
+    } else {
+        s.chars().count()
+    }
 }
 
 /// Returns a range of the given string slice, by indexing chars instead of bytes
 pub fn get_range_of<T: RangeBounds<usize>>(s: &str, range: T) -> Option<&str> {
-    let len = s.chars().count();
+    let is_ascii = s.is_ascii();
+    let len = if is_ascii { s.len() } else { s.chars().count() };
     let start = match range.start_bound() {
         Bound::Unbounded => 0,
         Bound::Included(i) => *i,
@@ -465,6 +581,9 @@ pub fn get_range_of<T: RangeBounds<usize>>(s: &str, range: T) -> Option<&str> {
 
     if start >= len || end > len || start >= end {
         None
+    } else if is_ascii {
+        // Each char is a single byte
+        Some(&s[start..end])
     } else {
         let start_b = s
             .char_indices()
@@ -485,6 +604,50 @@ mod tests {
     use super::*;
     use unicode_categories::UnicodeCategories;
 
+    #[test]
+    fn removed_ranges() {
+        let mut n = NormalizedString::from("  Hello\u{0}\u{1} élégant  ");
+        assert!(n.removed_ranges().is_empty());
+
+        n.filter(|c| !c.is_control())
+            .nfd()
+            .filter(|c| !c.is_mark_nonspacing());
+        n.strip();
+        assert_eq!(n.get(), "Hello elegant");
+        assert_eq!(n.removed_ranges(), vec![0..2, 7..9, 17..19]);
+    }
+
+    #[test]
+    fn get_alignments() {
+        let mut n = NormalizedString::from("a ﬁ-b");
+        n.nfkd().filter(|c| *c != '-');
+        assert_eq!(n.get(), "a fib");
+        assert_eq!(
+            n.get_alignments(),
+            vec![(0..1, 0..1), (1..2, 1..2), (2..3, 2..4), (4..5, 4..5)]
+        );
+        assert!(NormalizedString::from("").get_alignments().is_empty());
+    }
+
+    #[test]
+    fn validate() {
+        let mut n = NormalizedString::from("élégant");
+        n.nfd().filter(|c| !c.is_mark_nonspacing() && *c != 'n');
+        assert!(n.validate().is_ok());
+
+        let mut wrong_length = n.clone();
+        wrong_length.alignments.pop();
+        assert!(wrong_length.validate().is_err());
+
+        let mut out_of_bounds = n.clone();
+        out_of_bounds.alignments[5] = (6, 8);
+        assert!(out_of_bounds.validate().is_err());
+
+        let mut backward = n;
+        backward.alignments.swap(1, 2);
+        assert!(backward.validate().is_err());
+    }
+
     #[test]
     fn new_chars() {
         let mut n = NormalizedString::from("élégant");
@@ -525,6 +688,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn merge_with_changed_length() {
+        let mut n = NormalizedString::from("a-b");
+        n.filter(|c| *c != '-');
+        let mut other = NormalizedString::from("cd");
+        other.filter(|c| *c != 'c');
+        n.merge_with(&other);
+
+        assert_eq!(n.get(), "abd");
+        assert_eq!(n.get_original(), "a-bcd");
+        assert_eq!(&n.alignments, &[(0, 1), (2, 3), (4, 5)]);
+        assert_eq!(n.convert_offsets(Range::Normalized(2..3)), Some(4..5));
+    }
+
     #[test]
     fn mixed_addition_and_removal() {
         let mut n = NormalizedString::from("élégant");
@@ -535,6 +712,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn transform_chars() {
+        let mut n = NormalizedString::from("a-bc-İ");
+        n.transform_chars(|c| match c {
+            '-' => vec![],
+            'c' => vec!['c', 'c'],
+            c => c.to_lowercase().collect(),
+        });
+        assert_eq!(n.get(), "abcci̇");
+        assert_eq!(
+            &n.alignments,
+            &[(0, 1), (2, 3), (3, 4), (3, 4), (5, 6), (5, 6)]
+        );
+        assert_eq!(n.removed_ranges(), vec![1..2, 4..5]);
+        assert!(n.validate().is_ok());
+
+        // Leading and trailing removals
+        let mut n = NormalizedString::from("--a--");
+        n.transform_chars(|c| if c == '-' { None } else { Some(c) });
+        assert_eq!(n.get(), "a");
+        assert_eq!(&n.alignments, &[(2, 3)]);
+        assert_eq!(n.get_range_original(Range::Normalized(0..1)), Some("a"));
+    }
+
     #[test]
     fn range_conversion() {
         let mut n = NormalizedString::from("    __Hello__   ");
@@ -721,4 +922,21 @@ mod tests {
         assert_eq!(get_range_of(&s, ..), Some(&s[..]));
         assert_eq!(get_range_of(&s, 17..), Some("John 👋"));
     }
+
+    #[test]
+    fn ascii_fast_path() {
+        assert_eq!(get_range_of("Hello John", 6..), Some("John"));
+        assert_eq!(get_range_of("Hello John", 6..11), None);
+
+        let mut n = NormalizedString::from("Hello John");
+        n.nfkc().lowercase();
+        assert_eq!(n.get(), "hello john");
+        assert_eq!(n.len(), 10);
+        assert_eq!(n.get_range_original(Range::Normalized(6..10)), Some("John"));
+
+        // The same alignments as the general path
+        let mut general = NormalizedString::from("Hello John");
+        general.transform_chars(|c| c.to_lowercase());
+        assert_eq!(n.alignments, general.alignments);
+    }
 }
@@ -0,0 +1,11 @@
+//! The `HashMap` and `HashSet` used internally. With the `fast-hash` feature, they use the
+//! `FxHasher`, which is a lot faster than the default SipHash on small keys, but doesn't offer
+//! any protection against HashDoS. None of them is exposed in the public API.
+
+#[cfg(feature = "fast-hash")]
+pub(crate) type BuildHasher = std::hash::BuildHasherDefault<rustc_hash::FxHasher>;
+#[cfg(not(feature = "fast-hash"))]
+pub(crate) type BuildHasher = std::collections::hash_map::RandomState;
+
+pub(crate) type HashMap<K, V> = std::collections::HashMap<K, V, BuildHasher>;
+pub(crate) type HashSet<K> = std::collections::HashSet<K, BuildHasher>;
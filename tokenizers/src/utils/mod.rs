@@ -1,3 +1,5 @@
+pub(crate) mod hash;
 pub mod iter;
 pub mod padding;
+pub mod profiled;
 pub mod truncation;
@@ -61,3 +61,86 @@ pub fn pad_encodings(
 
     Ok(encodings)
 }
+
+/// Split the given encodings in buckets according to their length, and pad each bucket
+/// separately. `buckets` gives the maximum length of each bucket, in increasing order: an
+/// `Encoding` goes in the first bucket long enough to contain it, and the ones longer than all
+/// of them go in a last bucket. Each non-empty bucket is returned with the indices of its
+/// encodings in the original `Vec`, to restore their order.
+pub fn pad_encodings_bucketed(
+    encodings: Vec<Encoding>,
+    params: &PaddingParams,
+    buckets: &[usize],
+) -> Result<Vec<(Vec<usize>, Vec<Encoding>)>> {
+    let mut bucketed: Vec<(Vec<usize>, Vec<Encoding>)> = vec![(vec![], vec![]); buckets.len() + 1];
+    for (index, encoding) in encodings.into_iter().enumerate() {
+        let len = encoding.get_ids().len();
+        let bucket = buckets
+            .iter()
+            .position(|max_len| len <= *max_len)
+            .unwrap_or(buckets.len());
+        bucketed[bucket].0.push(index);
+        bucketed[bucket].1.push(encoding);
+    }
+
+    bucketed
+        .into_iter()
+        .filter(|(indices, _)| !indices.is_empty())
+        .map(|(indices, bucket)| Ok((indices, pad_encodings(bucket, params)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoding(len: usize) -> Encoding {
+        Encoding::new(
+            vec![1; len],
+            vec![0; len],
+            vec!["a".into(); len],
+            vec![(0, 1); len],
+            vec![0; len],
+            vec![1; len],
+            vec![true; len],
+            vec![false; len],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn bucketed() {
+        let params = PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            direction: PaddingDirection::Right,
+            pad_id: 0,
+            pad_type_id: 0,
+            pad_token: "[PAD]".into(),
+        };
+        let encodings = vec![
+            encoding(3),
+            encoding(10),
+            encoding(1),
+            encoding(30),
+            encoding(7),
+        ];
+
+        let buckets = pad_encodings_bucketed(encodings, &params, &[4, 16]).unwrap();
+        let lengths = buckets
+            .iter()
+            .map(|(indices, bucket)| {
+                let lengths = bucket.iter().map(|e| e.get_ids().len()).collect::<Vec<_>>();
+                (indices.clone(), lengths)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lengths,
+            vec![
+                (vec![0, 2], vec![3, 3]),
+                (vec![1, 4], vec![10, 10]),
+                (vec![3], vec![30]),
+            ]
+        );
+        assert_eq!(buckets[0].1[1].get_attention_mask(), &[1, 0, 0]);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::tokenizer::{Model, NormalizedString, Normalizer, Offsets, PreTokenizer, Result, Token};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The time spent in a component wrapped by `Profiled`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Timings {
+    /// The number of calls
+    pub calls: usize,
+    /// The total time spent in these calls
+    pub total: Duration,
+    /// The duration of the slowest call
+    pub max: Duration,
+}
+
+impl Timings {
+    /// The average duration of a call
+    pub fn mean(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::default()
+        } else {
+            self.total / self.calls as u32
+        }
+    }
+}
+
+/// Wraps a `Normalizer`, a `PreTokenizer` or a `Model` to time each of its calls to
+/// `normalize`, `pre_tokenize` or `tokenize`. The `Timings` are shared by all the clones of the
+/// wrapper, so they can still be retrieved with `Profiled::timings` once the component has been
+/// given to a `Tokenizer`.
+///
+/// ```
+/// # use tokenizers::pre_tokenizers::whitespace::Whitespace;
+/// # use tokenizers::tokenizer::{NormalizedString, PreTokenizer};
+/// use tokenizers::utils::profiled::Profiled;
+///
+/// let pre_tokenizer = Profiled::new(Whitespace::default());
+/// let timings = pre_tokenizer.timings();
+/// pre_tokenizer.pre_tokenize(&mut NormalizedString::from("Hello there")).unwrap();
+/// assert_eq!(timings.lock().unwrap().calls, 1);
+/// ```
+#[derive(Clone)]
+pub struct Profiled<T> {
+    inner: T,
+    timings: Arc<Mutex<Timings>>,
+}
+
+impl<T> Profiled<T> {
+    pub fn new(inner: T) -> Self {
+        Profiled {
+            inner,
+            timings: Arc::new(Mutex::new(Timings::default())),
+        }
+    }
+
+    /// Get the `Timings`, updated by every call to the wrapped component
+    pub fn timings(&self) -> Arc<Mutex<Timings>> {
+        self.timings.clone()
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    fn record<R>(&self, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let res = f();
+        let elapsed = start.elapsed();
+
+        let mut timings = self.timings.lock().unwrap();
+        timings.calls += 1;
+        timings.total += elapsed;
+        timings.max = timings.max.max(elapsed);
+        res
+    }
+}
+
+impl<T: Normalizer + Clone> Normalizer for Profiled<T> {
+    fn normalize(&self, normalized: &mut NormalizedString) -> Result<()> {
+        self.record(|| self.inner.normalize(normalized))
+    }
+}
+
+impl<T: PreTokenizer + Clone> PreTokenizer for Profiled<T> {
+    fn pre_tokenize(&self, normalized: &mut NormalizedString) -> Result<Vec<(String, Offsets)>> {
+        self.record(|| self.inner.pre_tokenize(normalized))
+    }
+
+    fn pre_tokenize_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<(NormalizedString, Vec<(String, Offsets)>)> {
+        self.record(|| self.inner.pre_tokenize_bytes(bytes))
+    }
+}
+
+impl<T: Model + Clone> Model for Profiled<T> {
+    fn tokenize(&self, tokens: Vec<(String, Offsets)>) -> Result<Vec<Token>> {
+        self.record(|| self.inner.tokenize(tokens))
+    }
+
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.inner.token_to_id(token)
+    }
+
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.inner.id_to_token(id)
+    }
+
+    fn get_vocab(&self) -> &HashMap<String, u32> {
+        self.inner.get_vocab()
+    }
+
+    fn get_vocab_size(&self) -> usize {
+        self.inner.get_vocab_size()
+    }
+
+    fn save(&self, folder: &Path, name: Option<&str>) -> Result<Vec<PathBuf>> {
+        self.inner.save(folder, name)
+    }
+
+    fn handles_pre_tokenization(&self) -> bool {
+        self.inner.handles_pre_tokenization()
+    }
+
+    fn get_unk_id(&self) -> Option<u32> {
+        self.inner.get_unk_id()
+    }
+
+    // The whole batch counts as a single call
+    fn tokenize_batch(&self, batches: Vec<Vec<(String, Offsets)>>) -> Result<Vec<Vec<Token>>> {
+        self.record(|| self.inner.tokenize_batch(batches))
+    }
+
+    fn extend_vocab(&mut self, tokens: Vec<(String, u32)>) -> Result<()> {
+        self.inner.extend_vocab(tokens)
+    }
+
+    fn get_continuing_subword_prefix(&self) -> Option<&str> {
+        self.inner.get_continuing_subword_prefix()
+    }
+
+    fn get_end_of_word_suffix(&self) -> Option<&str> {
+        self.inner.get_end_of_word_suffix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::wordlevel::WordLevelBuilder;
+    use crate::normalizers::utils::Lowercase;
+    use crate::pre_tokenizers::whitespace::Whitespace;
+    use crate::tokenizer::{EncodeInput, Tokenizer};
+
+    #[test]
+    fn profile_pipeline() {
+        let model = Profiled::new(
+            WordLevelBuilder::new()
+                .vocab(
+                    [("<unk>", 0), ("hello", 1), ("there", 2)]
+                        .iter()
+                        .map(|(t, id)| (t.to_string(), *id))
+                        .collect(),
+                )
+                .build(),
+        );
+        let normalizer = Profiled::new(Lowercase);
+        let pre_tokenizer = Profiled::new(Whitespace::default());
+        let timings = vec![
+            model.timings(),
+            normalizer.timings(),
+            pre_tokenizer.timings(),
+        ];
+
+        let mut tokenizer = Tokenizer::new(Box::new(model));
+        tokenizer.with_normalizer(Box::new(normalizer));
+        tokenizer.with_pre_tokenizer(Box::new(pre_tokenizer));
+        let encodings = tokenizer
+            .encode_batch(
+                vec![
+                    EncodeInput::Single("Hello there".into()),
+                    EncodeInput::Single("HELLO".into()),
+                    EncodeInput::Single("there hello".into()),
+                ],
+                false,
+            )
+            .unwrap();
+        assert_eq!(encodings[2].get_ids(), &[2, 1]);
+
+        for timings in timings {
+            let timings = timings.lock().unwrap();
+            assert_eq!(timings.calls, 3);
+            assert!(timings.max <= timings.total);
+            assert!(timings.mean() <= timings.max);
+        }
+    }
+}
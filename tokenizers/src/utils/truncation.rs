@@ -5,12 +5,16 @@ pub struct TruncationParams {
     pub max_length: usize,
     pub strategy: TruncationStrategy,
     pub stride: usize,
+    /// Whether to avoid splitting a word in multiple tokens when truncating: the whole word is
+    /// removed instead, even if this leaves us a few tokens under `max_length`
+    pub respect_word_boundaries: bool,
 }
 
 #[derive(Debug)]
 pub enum Error {
     SecondSequenceNotProvided,
     SequenceTooShort,
+    StrideTooLarge,
 }
 
 impl std::fmt::Display for Error {
@@ -23,6 +27,10 @@ impl std::fmt::Display for Error {
                 fmt,
                 "Truncation error: Sequence to truncate too short to respect the provided max_length"
             ),
+            Error::StrideTooLarge => write!(
+                fmt,
+                "Truncation error: The stride must be smaller than the length of each truncated sequence"
+            ),
         }
     }
 }
@@ -45,6 +53,86 @@ impl std::convert::AsRef<str> for TruncationStrategy {
     }
 }
 
+/// The side from which the tokens get removed when truncating an `Encoding`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TruncationDirection {
+    /// Remove the first tokens, keeping the end of the sequence
+    Left,
+    /// Remove the last tokens, keeping the beginning of the sequence
+    Right,
+}
+
+impl std::convert::AsRef<str> for TruncationDirection {
+    fn as_ref(&self) -> &str {
+        match self {
+            TruncationDirection::Left => "left",
+            TruncationDirection::Right => "right",
+        }
+    }
+}
+
+/// Truncate the given `Encoding` to `max_len`, keeping the special tokens at its end (like a
+/// `[SEP]` already added by a `PostProcessor`): the non-special tokens before them are removed
+/// instead, and each overflowing part ends with the same special tokens.
+fn truncate_keeping_special(
+    encoding: &mut Encoding,
+    max_len: usize,
+    params: &TruncationParams,
+) -> Result<()> {
+    let stride = params.stride;
+    let len = encoding.get_ids().len();
+    if max_len >= len {
+        return Ok(());
+    }
+    // With a pair of sequences, each of them can end up a lot shorter than `max_length`
+    if stride >= max_len {
+        return Err(Box::new(Error::StrideTooLarge));
+    }
+
+    let n_special = encoding
+        .get_special_tokens_mask()
+        .iter()
+        .rev()
+        .take_while(|mask| **mask == 1)
+        .count();
+
+    let truncation_len = |encoding: &Encoding, len: usize| {
+        if params.respect_word_boundaries {
+            word_boundary(encoding, len, stride)
+        } else {
+            len
+        }
+    };
+
+    if n_special == 0 || n_special == len || n_special + stride >= max_len {
+        let max_len = truncation_len(encoding, max_len);
+        encoding.truncate(max_len, stride, TruncationDirection::Right);
+    } else {
+        let special = encoding.split_off(len - n_special);
+        let max_len = truncation_len(encoding, max_len - n_special);
+        encoding.truncate(max_len, stride, TruncationDirection::Right);
+        encoding.merge_with(special, false);
+    }
+
+    Ok(())
+}
+
+/// Find the length to which the given `Encoding` can be truncated without splitting a word,
+/// going back from `len`. If the only word boundary before it is at the very beginning, or too
+/// close to respect the `stride`, `len` is kept as is.
+fn word_boundary(encoding: &Encoding, len: usize, stride: usize) -> usize {
+    let word_starts = encoding.get_word_starts();
+    if len >= word_starts.len() || word_starts[len] {
+        return len;
+    }
+    match word_starts[..len].iter().rposition(|start| *start) {
+        Some(boundary) if boundary > stride => boundary,
+        _ => len,
+    }
+}
+
+/// Truncate the given encodings to respect the `max_length` of the given params. The special
+/// tokens at the end of each `Encoding` are kept whenever possible.
 pub fn truncate_encodings(
     mut encoding: Encoding,
     mut pair_encoding: Option<Encoding>,
@@ -77,9 +165,9 @@ pub fn truncate_encodings(
                 }
             }
 
-            encoding.truncate(n_first, params.stride);
+            truncate_keeping_special(&mut encoding, n_first, params)?;
             if let Some(encoding) = pair_encoding.as_mut() {
-                encoding.truncate(n_second, params.stride);
+                truncate_keeping_special(encoding, n_second, params)?;
             }
         }
         TruncationStrategy::OnlyFirst | TruncationStrategy::OnlySecond => {
@@ -93,7 +181,7 @@ pub fn truncate_encodings(
 
             let target_len = target.get_ids().len();
             if target_len > to_remove {
-                target.truncate(target_len - to_remove, params.stride);
+                truncate_keeping_special(target, target_len - to_remove, params)?;
             } else {
                 return Err(Box::new(Error::SequenceTooShort));
             }
@@ -102,3 +190,113 @@ pub fn truncate_encodings(
 
     Ok((encoding, pair_encoding))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_encoding() -> Encoding {
+        Encoding::new(
+            vec![1, 10, 11, 12, 2],
+            vec![0; 5],
+            vec![
+                "[CLS]".into(),
+                "a".into(),
+                "b".into(),
+                "c".into(),
+                "[SEP]".into(),
+            ],
+            vec![(0, 0), (0, 1), (2, 3), (4, 5), (0, 0)],
+            vec![1, 0, 0, 0, 1],
+            vec![1; 5],
+            vec![true; 5],
+            vec![false; 5],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn keeps_special_tokens() {
+        let params = TruncationParams {
+            max_length: 4,
+            strategy: TruncationStrategy::LongestFirst,
+            stride: 0,
+            respect_word_boundaries: false,
+        };
+        let (encoding, _) = truncate_encodings(get_encoding(), None, &params).unwrap();
+        assert_eq!(encoding.get_tokens(), &["[CLS]", "a", "b", "[SEP]"]);
+        assert_eq!(encoding.get_special_tokens_mask(), &[1, 0, 0, 1]);
+        assert_eq!(encoding.get_num_truncated(), 1);
+        assert_eq!(encoding.get_overflowing().len(), 1);
+        assert_eq!(encoding.get_overflowing()[0].get_tokens(), &["c", "[SEP]"]);
+    }
+
+    #[test]
+    fn without_special_tokens() {
+        let params = TruncationParams {
+            max_length: 2,
+            strategy: TruncationStrategy::OnlyFirst,
+            stride: 0,
+            respect_word_boundaries: false,
+        };
+        let encoding = Encoding::new(
+            vec![10, 11, 12],
+            vec![0; 3],
+            vec!["a".into(), "b".into(), "c".into()],
+            vec![(0, 1), (2, 3), (4, 5)],
+            vec![0; 3],
+            vec![1; 3],
+            vec![true; 3],
+            vec![false; 3],
+            vec![],
+        );
+        let (encoding, _) = truncate_encodings(encoding, None, &params).unwrap();
+        assert_eq!(encoding.get_tokens(), &["a", "b"]);
+        assert_eq!(encoding.get_overflowing()[0].get_tokens(), &["c"]);
+    }
+
+    #[test]
+    fn respect_word_boundaries() {
+        let mut params = TruncationParams {
+            max_length: 4,
+            strategy: TruncationStrategy::LongestFirst,
+            stride: 0,
+            respect_word_boundaries: true,
+        };
+        // "a bcd", with "bcd" split in 3 tokens
+        let encoding = Encoding::new(
+            vec![10, 11, 12, 13],
+            vec![0; 4],
+            vec!["a".into(), "b".into(), "##c".into(), "##d".into()],
+            vec![(0, 1), (2, 3), (3, 4), (4, 5)],
+            vec![0; 4],
+            vec![1; 4],
+            vec![true, true, false, false],
+            vec![false; 4],
+            vec![],
+        );
+
+        for max_length in 2..=3 {
+            params.max_length = max_length;
+            let (truncated, _) = truncate_encodings(encoding.clone(), None, &params).unwrap();
+            assert_eq!(truncated.get_tokens(), &["a"]);
+            assert_eq!(truncated.get_num_truncated(), 3);
+        }
+
+        // A word longer than max_length still gets split
+        params.max_length = 1;
+        let single_word = Encoding::new(
+            vec![11, 12, 13],
+            vec![0; 3],
+            vec!["b".into(), "##c".into(), "##d".into()],
+            vec![(0, 1), (1, 2), (2, 3)],
+            vec![0; 3],
+            vec![1; 3],
+            vec![true, false, false],
+            vec![false; 3],
+            vec![],
+        );
+        let (truncated, _) = truncate_encodings(single_word, None, &params).unwrap();
+        assert_eq!(truncated.get_tokens(), &["b"]);
+    }
+}
@@ -29,7 +29,7 @@ fn byte_level_basic() {
 
     let input = String::from("Hello there, how are you?");
     let output = tokenizer
-        .encode(EncodeInput::Single(input.clone()), false)
+        .encode(EncodeInput::Single(input.as_str().into()), false)
         .unwrap();
 
     let offsets = output.get_offsets();
@@ -61,7 +61,7 @@ fn byte_level_basic() {
 
     let input = String::from("Hello there, how are you?");
     let output = tokenizer
-        .encode(EncodeInput::Single(input.clone()), false)
+        .encode(EncodeInput::Single(input.as_str().into()), false)
         .unwrap();
 
     let offsets = output.get_offsets();
@@ -95,7 +95,7 @@ fn byte_level_unicode() {
 
     let input = String::from("i⭢j");
     let output = tokenizer
-        .encode(EncodeInput::Single(input.clone()), false)
+        .encode(EncodeInput::Single(input.as_str().into()), false)
         .unwrap();
 
     let offsets = output.get_offsets();
@@ -112,7 +112,10 @@ fn byte_level_double_sequence() {
     // Without trimming offsets
     let tokenizer = get_byte_level(true, false);
     let output = tokenizer
-        .encode(EncodeInput::Dual(input_a.clone(), input_b.clone()), false)
+        .encode(
+            EncodeInput::Dual(input_a.as_str().into(), input_b.as_str().into()),
+            false,
+        )
         .unwrap();
 
     let offsets = output.get_offsets();
@@ -134,7 +137,7 @@ fn byte_level_double_sequence() {
     // When trimming offsets
     let tokenizer = get_byte_level(true, true);
     let output = tokenizer
-        .encode(EncodeInput::Dual(input_a, input_b), false)
+        .encode(EncodeInput::Dual(input_a.into(), input_b.into()), false)
         .unwrap();
     let offsets = output.get_offsets();
     assert_eq!(
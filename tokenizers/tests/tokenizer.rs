@@ -0,0 +1,1900 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokenizers::decoders::byte_fallback::ByteFallback;
+use tokenizers::models::bpe::{BpeTrainer, BPE};
+use tokenizers::models::wordlevel::{WordLevel, WordLevelBuilder};
+use tokenizers::models::wordpiece::{WordPiece, WordPieceTrainer};
+use tokenizers::normalizers::bert::BertNormalizer;
+use tokenizers::normalizers::map::Map;
+use tokenizers::normalizers::strip::Strip;
+use tokenizers::normalizers::utils::Lowercase;
+use tokenizers::pre_tokenizers::bert::BertPreTokenizer;
+use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+use tokenizers::pre_tokenizers::whitespace::{Whitespace, WhitespaceSplit};
+use tokenizers::processors::bert::BertProcessing;
+use tokenizers::tokenizer::{
+    AddResult, AddedToken, EncodeBuffers, EncodeInfo, EncodeInput, KeepPolicy, Model,
+    NormalizedString, Normalizer, Offsets, PaddingDirection, PaddingParams, PaddingStrategy,
+    SpecialMaskConvention, Token, Tokenizer, Trainer, TruncationParams, TruncationStrategy,
+    VocabDiff,
+};
+
+fn get_bert() -> Tokenizer {
+    let vocab: HashMap<String, u32> = [
+        "[UNK]", "[CLS]", "[SEP]", "[PAD]", "[MASK]", "un", "##aff", "##able", "hi", "!",
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, token)| (token.to_string(), i as u32))
+    .collect();
+
+    let mut tokenizer =
+        Tokenizer::new(Box::new(WordPiece::builder().vocab(vocab).build().unwrap()));
+    tokenizer.with_pre_tokenizer(Box::new(BertPreTokenizer));
+    tokenizer.with_post_processor(Box::new(BertProcessing::new(
+        (String::from("[SEP]"), 2),
+        (String::from("[CLS]"), 1),
+    )));
+
+    tokenizer
+}
+
+fn messages(messages: &[&str]) -> Vec<String> {
+    messages.iter().map(|m| m.to_string()).collect()
+}
+
+#[test]
+fn fit_to_budget_latest() {
+    let tokenizer = get_bert();
+    // Each message costs its tokens, plus [CLS] and [SEP]: 5, 3, 4 and 3
+    let conversation = messages(&["unaffable", "hi", "hi hi", "hi"]);
+
+    let fit = |budget| {
+        tokenizer
+            .fit_to_budget(&conversation, budget, KeepPolicy::Latest)
+            .unwrap()
+    };
+    assert_eq!(fit(100), vec![0, 1, 2, 3]);
+    assert_eq!(fit(10), vec![1, 2, 3]);
+    assert_eq!(fit(7), vec![2, 3]);
+    assert_eq!(fit(2), Vec::<usize>::new());
+}
+
+#[test]
+fn fit_to_budget_first_and_latest() {
+    let tokenizer = get_bert();
+    let conversation = messages(&["unaffable", "hi", "hi hi", "hi"]);
+
+    let fit = |budget| {
+        tokenizer
+            .fit_to_budget(&conversation, budget, KeepPolicy::FirstAndLatest)
+            .unwrap()
+    };
+    assert_eq!(fit(100), vec![0, 1, 2, 3]);
+    // The first message is kept, and the middle ones are dropped
+    assert_eq!(fit(12), vec![0, 2, 3]);
+    assert_eq!(fit(10), vec![0, 3]);
+    // The first message alone doesn't fit, but the latest ones still can
+    assert_eq!(fit(4), vec![3]);
+    assert_eq!(fit(2), Vec::<usize>::new());
+}
+
+#[test]
+fn fit_to_budget_special_tokens() {
+    let tokenizer = get_bert();
+    assert_eq!(tokenizer.num_special_tokens_to_add(false), 2);
+
+    // Without their special tokens, both messages would fit in 5 tokens
+    let conversation = messages(&["hi", "hi"]);
+    for keep in &[KeepPolicy::Latest, KeepPolicy::FirstAndLatest] {
+        assert_eq!(
+            tokenizer.fit_to_budget(&conversation, 6, *keep).unwrap(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            tokenizer
+                .fit_to_budget(&conversation, 5, *keep)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(tokenizer.fit_to_budget(&[], 5, *keep).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn word_starts() {
+    let tokenizer = get_bert();
+    let encoding = tokenizer
+        .encode(
+            EncodeInput::Dual("unaffable! hi".into(), "hi unable".into()),
+            true,
+        )
+        .unwrap();
+
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[CLS]", "un", "##aff", "##able", "!", "hi", "[SEP]", "hi", "un", "##able", "[SEP]"]
+    );
+    assert_eq!(
+        encoding.get_word_starts(),
+        &[true, true, false, false, true, true, true, true, true, false, true]
+    );
+}
+
+#[test]
+fn empty_inputs() {
+    let mut tokenizer = get_bert();
+    for input in &["", "   ", "\n"] {
+        let encoding = tokenizer
+            .encode(EncodeInput::Single((*input).into()), true)
+            .unwrap();
+        assert_eq!(encoding.get_tokens(), &["[CLS]", "[SEP]"]);
+        assert_eq!(encoding.get_special_tokens_mask(), &[1, 1]);
+        assert_eq!(encoding.get_offsets(), &[(0, 0), (0, 0)]);
+    }
+
+    tokenizer.with_special_tokens_on_empty(false);
+    for input in &["", "   ", "\n"] {
+        let encoding = tokenizer
+            .encode(EncodeInput::Single((*input).into()), true)
+            .unwrap();
+        assert!(encoding.get_ids().is_empty());
+    }
+
+    // Only inputs producing no token at all are affected
+    let encoding = tokenizer
+        .encode(EncodeInput::Dual("".into(), "hi".into()), true)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["[CLS]", "[SEP]", "hi", "[SEP]"]);
+}
+
+#[test]
+fn encode_with_info() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_truncation(Some(TruncationParams {
+        max_length: 5,
+        strategy: TruncationStrategy::LongestFirst,
+        stride: 0,
+        respect_word_boundaries: false,
+    }));
+
+    let (encoding, info) = tokenizer
+        .encode_with_info(EncodeInput::Single("hi unaffable".into()), true)
+        .unwrap();
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[CLS]", "hi", "un", "##aff", "[SEP]"]
+    );
+    assert_eq!(
+        info,
+        EncodeInfo {
+            truncated: true,
+            num_truncated: 1
+        }
+    );
+    assert!(encoding.is_truncated());
+    assert_eq!(encoding.get_num_truncated(), 1);
+
+    let (encoding, info) = tokenizer
+        .encode_with_info(EncodeInput::Single("hi".into()), true)
+        .unwrap();
+    assert_eq!(info, EncodeInfo::default());
+    assert!(!encoding.is_truncated());
+}
+
+#[test]
+fn validation() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_validation(true);
+
+    let encoding = tokenizer
+        .encode(
+            EncodeInput::Dual("unaffable hi!".into(), "hi unaffable".into()),
+            true,
+        )
+        .unwrap();
+    assert_eq!(
+        encoding.get_offsets(),
+        &[
+            (0, 0),
+            (0, 2),
+            (2, 5),
+            (5, 9),
+            (10, 12),
+            (12, 13),
+            (0, 0),
+            (0, 2),
+            (3, 5),
+            (5, 8),
+            (8, 12),
+            (0, 0)
+        ]
+    );
+}
+
+#[test]
+fn special_tokens_not_split() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_special_tokens(&["<mask>"]);
+    tokenizer.add_tokens(&[AddedToken {
+        content: "<mask".into(),
+        single_word: false,
+    }]);
+
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("hi <mask>. hi<mask>!".into()), false)
+        .unwrap();
+    assert_eq!(
+        encoding.get_tokens(),
+        &["hi", "<mask>", "[UNK]", "hi", "<mask>", "!"]
+    );
+}
+
+#[test]
+fn decode_iter() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_special_tokens(&["[CLS]", "[SEP]"]);
+    let ids = vec![1, 5, 6, 7, 2];
+
+    let decoded = tokenizer.decode_iter(ids.iter().copied(), true).unwrap();
+    assert_eq!(decoded, "un ##aff ##able");
+    assert_eq!(decoded, tokenizer.decode(ids.clone(), true).unwrap());
+    assert_eq!(
+        tokenizer
+            .decode_iter(ids.into_iter().take(2), false)
+            .unwrap(),
+        "[CLS] un"
+    );
+}
+
+#[test]
+fn add_token_with_id() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_special_token_with_id("</s>", 42).unwrap();
+    tokenizer
+        .add_token_with_id(
+            AddedToken {
+                content: "hello".into(),
+                single_word: false,
+            },
+            12,
+        )
+        .unwrap();
+
+    assert_eq!(tokenizer.token_to_id("</s>"), Some(42));
+    assert_eq!(tokenizer.id_to_token(12), Some("hello".into()));
+    assert_eq!(tokenizer.decode(vec![8, 42], true).unwrap(), "hi");
+
+    // Collisions with the model, or the added tokens
+    assert!(tokenizer.add_special_token_with_id("<s>", 2).is_err());
+    assert!(tokenizer.add_special_token_with_id("<s>", 42).is_err());
+    assert!(tokenizer.add_special_token_with_id("hi", 43).is_err());
+    assert!(tokenizer.add_special_token_with_id("hello", 43).is_err());
+
+    // The next ids are assigned around the ones given explicitly
+    tokenizer.add_tokens(&[AddedToken {
+        content: "world".into(),
+        single_word: false,
+    }]);
+    assert_eq!(tokenizer.token_to_id("world"), Some(13));
+    tokenizer.add_special_tokens(&["<s>"]);
+    assert_eq!(tokenizer.token_to_id("<s>"), Some(14));
+}
+
+#[test]
+fn save_vocab() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_tokens(&[AddedToken {
+        content: "hello".into(),
+        single_word: false,
+    }]);
+
+    let vocab = tokenizer.get_vocab(true);
+    assert_eq!(vocab.len(), 11);
+    assert_eq!(vocab["hello"], 10);
+    assert!(!tokenizer.get_vocab(false).contains_key("hello"));
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    tokenizer.save_vocab(file.path(), true).unwrap();
+    let lines = std::fs::read_to_string(file.path()).unwrap();
+    let lines = lines.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 11);
+    assert_eq!(lines[0], "[UNK]\t0");
+    assert_eq!(lines[6], "##aff\t6");
+    assert_eq!(lines[10], "hello\t10");
+
+    tokenizer.save_vocab(file.path(), false).unwrap();
+    let lines = std::fs::read_to_string(file.path()).unwrap();
+    assert_eq!(lines.lines().count(), 10);
+}
+
+#[test]
+fn encode_no_pad() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_padding(Some(PaddingParams {
+        strategy: PaddingStrategy::Fixed(8),
+        direction: PaddingDirection::Right,
+        pad_id: 3,
+        pad_type_id: 0,
+        pad_token: "[PAD]".into(),
+    }));
+
+    let input = EncodeInput::Single("hi !".into());
+    let encoding = tokenizer.encode(input.clone(), true).unwrap();
+    assert_eq!(encoding.get_ids(), [1, 8, 9, 2, 3, 3, 3, 3]);
+
+    let encoding = tokenizer.encode_no_pad(input.clone(), true).unwrap();
+    assert_eq!(encoding.get_ids(), [1, 8, 9, 2]);
+    assert_eq!(encoding.get_attention_mask(), [1, 1, 1, 1]);
+
+    // The batch still gets padded
+    let encodings = tokenizer.encode_batch(vec![input], true).unwrap();
+    assert_eq!(encodings[0].get_ids(), [1, 8, 9, 2, 3, 3, 3, 3]);
+}
+
+#[test]
+fn pair_offsets() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_normalizer(Box::new(BertNormalizer::new(true, false, false, false)));
+
+    // The control chars get removed by the normalizer, so the pair offsets must be converted
+    // using the pair, even if they don't seem to go backward, or if they follow some special
+    // tokens with `(0, 0)` offsets.
+    let pair = "\u{1}\u{1}\u{1} un hi";
+    let encode = |first: &str, add_special_tokens: bool| {
+        tokenizer
+            .encode(
+                EncodeInput::Dual(first.into(), pair.into()),
+                add_special_tokens,
+            )
+            .unwrap()
+    };
+
+    let encoding = encode("hi", false);
+    assert_eq!(encoding.get_tokens(), ["hi", "un", "hi"]);
+    assert_eq!(encoding.get_offsets(), [(0, 2), (4, 6), (7, 9)]);
+
+    let encoding = encode("hi", true);
+    assert_eq!(
+        encoding.get_tokens(),
+        ["[CLS]", "hi", "[SEP]", "un", "hi", "[SEP]"]
+    );
+    assert_eq!(
+        encoding.get_offsets(),
+        [(0, 0), (0, 2), (0, 0), (4, 6), (7, 9), (0, 0)]
+    );
+
+    let encoding = encode("", true);
+    assert_eq!(
+        encoding.get_tokens(),
+        ["[CLS]", "[SEP]", "un", "hi", "[SEP]"]
+    );
+    assert_eq!(
+        encoding.get_offsets(),
+        [(0, 0), (0, 0), (4, 6), (7, 9), (0, 0)]
+    );
+}
+
+#[test]
+fn encode_batch_bucketed() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_padding(Some(PaddingParams {
+        strategy: PaddingStrategy::BatchLongest,
+        direction: PaddingDirection::Right,
+        pad_id: 3,
+        pad_type_id: 0,
+        pad_token: "[PAD]".into(),
+    }));
+
+    let inputs = vec!["hi hi hi hi hi", "hi", "hi hi hi hi", "hi !", ""]
+        .into_iter()
+        .map(|s| EncodeInput::Single(s.into()))
+        .collect::<Vec<_>>();
+    let buckets = tokenizer.encode_batch_bucketed(inputs, true, 1).unwrap();
+
+    let lengths = buckets
+        .iter()
+        .map(|(indices, encodings)| {
+            (
+                indices.clone(),
+                encodings
+                    .iter()
+                    .map(|e| e.get_ids().len())
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lengths,
+        vec![
+            (vec![4, 1], vec![3, 3]),
+            (vec![3], vec![4]),
+            (vec![2, 0], vec![7, 7]),
+        ]
+    );
+    assert_eq!(buckets[0].1[0].get_ids(), [1, 2, 3]);
+}
+
+#[test]
+fn max_in_flight() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_padding(Some(PaddingParams {
+        strategy: PaddingStrategy::BatchLongest,
+        direction: PaddingDirection::Right,
+        pad_id: 3,
+        pad_type_id: 0,
+        pad_token: "[PAD]".into(),
+    }));
+    let inputs = vec!["hi", "hi hi", "hi hi hi", "hi hi hi hi", "hi"]
+        .into_iter()
+        .map(|s| EncodeInput::Single(s.into()))
+        .collect::<Vec<_>>();
+
+    let lengths = |tokenizer: &Tokenizer| {
+        tokenizer
+            .encode_batch(inputs.clone(), true)
+            .unwrap()
+            .iter()
+            .map(|e| e.get_ids().len())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(lengths(&tokenizer), [6, 6, 6, 6, 6]);
+
+    // Each chunk gets padded separately
+    tokenizer.with_max_in_flight(Some(2));
+    assert_eq!(lengths(&tokenizer), [4, 4, 6, 6, 3]);
+}
+
+#[test]
+fn added_token_serialization() {
+    let token = AddedToken {
+        content: "[MASK]".into(),
+        single_word: true,
+    };
+    let serialized = serde_json::to_string(&token).unwrap();
+    assert_eq!(serialized, r#"{"content":"[MASK]","single_word":true}"#);
+
+    let deserialized: AddedToken = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.content, "[MASK]");
+    assert!(deserialized.single_word);
+
+    // The options are optional, and the unknown ones ignored
+    let deserialized: AddedToken =
+        serde_json::from_str(r#"{"content":"[MASK]","lstrip":true}"#).unwrap();
+    assert!(!deserialized.single_word);
+    assert!(serde_json::from_str::<AddedToken>(r#"{"single_word":true}"#).is_err());
+}
+
+#[test]
+fn diff_vocab() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_tokens(&[AddedToken {
+        content: "hello".into(),
+        single_word: false,
+    }]);
+
+    let mut reference = tokenizer.get_vocab(true);
+    assert!(tokenizer.diff_vocab(&reference).is_empty());
+
+    reference.remove("hello");
+    reference.insert("world".into(), 11);
+    reference.insert("hi".into(), 42);
+    assert_eq!(
+        tokenizer.diff_vocab(&reference),
+        VocabDiff {
+            missing: vec!["world".into()],
+            extra: vec!["hello".into()],
+            id_mismatches: vec![("hi".into(), 8, 42)],
+        }
+    );
+}
+
+#[test]
+fn adjacent_added_tokens() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_tokens(&[
+        AddedToken {
+            content: "<a>".into(),
+            single_word: false,
+        },
+        AddedToken {
+            content: "<b>".into(),
+            single_word: false,
+        },
+    ]);
+
+    let tokens = |input: &str| {
+        tokenizer
+            .encode(EncodeInput::Single((*input).into()), false)
+            .unwrap()
+            .get_tokens()
+            .to_vec()
+    };
+    assert_eq!(tokens("<a><b>"), vec!["<a>", "<b>"]);
+    assert_eq!(tokens("<a><a>"), vec!["<a>", "<a>"]);
+    assert_eq!(tokens("<a>hi"), vec!["<a>", "hi"]);
+    assert_eq!(tokens("hi<b><a>!"), vec!["hi", "<b>", "<a>", "!"]);
+}
+
+fn encode_pair_windows(
+    first: &str,
+    second: &str,
+    strategy: TruncationStrategy,
+    max_length: usize,
+) -> Vec<Vec<String>> {
+    let mut tokenizer = get_bert();
+    tokenizer.with_truncation(Some(TruncationParams {
+        max_length,
+        strategy,
+        stride: 1,
+        respect_word_boundaries: false,
+    }));
+
+    let encoding = tokenizer
+        .encode(EncodeInput::Dual(first.into(), second.into()), true)
+        .unwrap();
+    std::iter::once(&encoding)
+        .chain(encoding.get_overflowing().iter())
+        .map(|e| e.get_tokens().to_vec())
+        .collect()
+}
+
+#[test]
+fn pair_overflowing() {
+    // The first sequence stays the same, while the second one slides with a stride of 1
+    let windows = encode_pair_windows("hi !", "unaffable hi !", TruncationStrategy::OnlySecond, 8);
+    assert_eq!(
+        windows,
+        vec![
+            vec!["[CLS]", "hi", "!", "[SEP]", "un", "##aff", "##able", "[SEP]"],
+            vec!["[CLS]", "hi", "!", "[SEP]", "##able", "hi", "!", "[SEP]"],
+        ]
+    );
+
+    // And the other way around
+    let windows = encode_pair_windows("unaffable hi !", "hi !", TruncationStrategy::OnlyFirst, 8);
+    assert_eq!(
+        windows,
+        vec![
+            vec!["[CLS]", "un", "##aff", "##able", "[SEP]", "hi", "!", "[SEP]"],
+            vec!["[CLS]", "##able", "hi", "!", "[SEP]", "hi", "!", "[SEP]"],
+        ]
+    );
+
+    // Only the longest sequence gets truncated here
+    let windows = encode_pair_windows(
+        "hi ! hi",
+        "unaffable hi !",
+        TruncationStrategy::LongestFirst,
+        8,
+    );
+    assert_eq!(
+        windows,
+        vec![
+            vec!["[CLS]", "hi", "!", "hi", "[SEP]", "un", "##aff", "[SEP]"],
+            vec!["[CLS]", "hi", "!", "hi", "[SEP]", "##aff", "##able", "[SEP]"],
+            vec!["[CLS]", "hi", "!", "hi", "[SEP]", "##able", "hi", "[SEP]"],
+            vec!["[CLS]", "hi", "!", "hi", "[SEP]", "hi", "!", "[SEP]"],
+        ]
+    );
+
+    // Both get truncated, so we have all the combinations of their windows
+    let windows = encode_pair_windows(
+        "hi ! hi !",
+        "unaffable hi !",
+        TruncationStrategy::LongestFirst,
+        7,
+    );
+    assert_eq!(windows.len(), 3 * 4);
+    assert!(windows.iter().all(|w| w.len() == 7));
+    assert!(windows.contains(
+        &vec!["[CLS]", "hi", "!", "[SEP]", "un", "##aff", "[SEP]"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    ));
+    assert!(windows.contains(
+        &vec!["[CLS]", "hi", "!", "[SEP]", "hi", "!", "[SEP]"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    ));
+}
+
+#[test]
+fn pair_overflowing_stride_too_large() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_truncation(Some(TruncationParams {
+        max_length: 5,
+        strategy: TruncationStrategy::LongestFirst,
+        stride: 1,
+        respect_word_boundaries: false,
+    }));
+
+    // Each sequence gets truncated to a single token, which can't have a stride of 1
+    assert!(tokenizer
+        .encode(
+            EncodeInput::Dual("hi".into(), "unaffable hi !".into()),
+            true
+        )
+        .is_err());
+}
+
+#[test]
+fn effective_max_length() {
+    let mut tokenizer = get_bert();
+    assert_eq!(tokenizer.effective_max_length(false), None);
+
+    tokenizer.with_truncation(Some(TruncationParams {
+        max_length: 10,
+        strategy: TruncationStrategy::LongestFirst,
+        stride: 0,
+        respect_word_boundaries: false,
+    }));
+    assert_eq!(tokenizer.effective_max_length(false), Some(8));
+    assert_eq!(tokenizer.effective_max_length(true), Some(7));
+
+    // A `max_length` of 0 disables the truncation
+    tokenizer.with_truncation(Some(TruncationParams {
+        max_length: 0,
+        strategy: TruncationStrategy::LongestFirst,
+        stride: 0,
+        respect_word_boundaries: false,
+    }));
+    assert_eq!(tokenizer.effective_max_length(false), None);
+}
+
+#[test]
+fn encode_borrowed_input() {
+    let tokenizer = get_bert();
+    let input = String::from("hi unaffable");
+
+    let borrowed = tokenizer.encode(input.as_str().into(), true).unwrap();
+    let owned = tokenizer.encode(input.clone().into(), true).unwrap();
+    assert_eq!(borrowed, owned);
+
+    let borrowed = tokenizer.encode(("hi", "unaffable").into(), true).unwrap();
+    let owned = tokenizer
+        .encode(
+            EncodeInput::Dual("hi".into(), input[3..].to_owned().into()),
+            true,
+        )
+        .unwrap();
+    assert_eq!(borrowed, owned);
+}
+
+#[test]
+fn encode_batch_shared() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_tokens(&[AddedToken {
+        content: "hello".into(),
+        single_word: false,
+    }]);
+    tokenizer.with_padding(Some(PaddingParams {
+        strategy: PaddingStrategy::BatchLongest,
+        direction: PaddingDirection::Right,
+        pad_id: 3,
+        pad_type_id: 0,
+        pad_token: "[PAD]".into(),
+    }));
+
+    let inputs = vec![
+        EncodeInput::Single("hi hello hi".into()),
+        EncodeInput::Single("hi".into()),
+    ];
+    let encodings = tokenizer.encode_batch(inputs.clone(), true).unwrap();
+    let shared = tokenizer.encode_batch_shared(inputs, true).unwrap();
+
+    for (encoding, shared) in encodings.iter().zip(shared.iter()) {
+        assert_eq!(encoding.get_ids(), shared.get_ids());
+        assert_eq!(encoding.get_offsets(), shared.get_offsets());
+        assert_eq!(encoding.get_attention_mask(), shared.get_attention_mask());
+        assert_eq!(
+            encoding.get_tokens(),
+            shared
+                .get_tokens()
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+    }
+
+    // The same tokens share the same string
+    let tokens = shared[0].get_tokens();
+    assert_eq!(&*tokens[1], "hi");
+    assert!(std::sync::Arc::ptr_eq(&tokens[1], &tokens[3]));
+    assert!(std::sync::Arc::ptr_eq(
+        &tokens[1],
+        &shared[1].get_tokens()[1]
+    ));
+
+    // Without padding, and across calls, the tokens are still shared with the same vocabulary
+    tokenizer.with_padding(None);
+    let input = || vec![EncodeInput::Single("hi".into())];
+    let unpadded = tokenizer.encode_batch_shared(input(), true).unwrap();
+    assert_eq!(unpadded[0].get_ids(), &[1, 8, 2]);
+    assert!(std::sync::Arc::ptr_eq(
+        &tokens[1],
+        &unpadded[0].get_tokens()[1]
+    ));
+
+    // The shared vocabulary gets rebuilt when the vocabulary changes
+    tokenizer.add_tokens(&[AddedToken {
+        content: "there".into(),
+        single_word: false,
+    }]);
+    let shared = tokenizer
+        .encode_batch_shared(vec![EncodeInput::Single("hi there".into())], true)
+        .unwrap();
+    assert_eq!(&*shared[0].get_tokens()[2], "there");
+    assert!(!std::sync::Arc::ptr_eq(
+        &tokens[1],
+        &shared[0].get_tokens()[1]
+    ));
+}
+
+#[test]
+fn contiguous_word_offsets() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_normalizer(Box::new(Map::new(
+        [('-', String::new())].iter().cloned().collect(),
+    )));
+
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("hi un-aff--able".into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["hi", "un", "##aff", "##able"]);
+    // The removed dashes belong to the token that follows them
+    assert_eq!(encoding.get_offsets(), &[(0, 2), (3, 5), (5, 9), (9, 15)]);
+    for window in encoding.get_offsets()[1..].windows(2) {
+        assert_eq!(window[0].1, window[1].0);
+    }
+}
+
+#[test]
+fn pipeline_hooks() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_pre_model_hook(Arc::new(|pre_tokens| {
+        pre_tokens.retain(|(pre_token, _)| pre_token != "!");
+    }));
+    let seen = Arc::new(AtomicUsize::new(0));
+    let counter = seen.clone();
+    tokenizer.with_post_model_hook(Arc::new(move |tokens| {
+        counter.fetch_add(tokens.len(), Ordering::SeqCst);
+    }));
+
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("hi unaffable!".into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["hi", "un", "##aff", "##able"]);
+    assert_eq!(seen.load(Ordering::SeqCst), 4);
+
+    tokenizer
+        .encode_batch(vec!["hi !".into(), "unaffable".into()], false)
+        .unwrap();
+    assert_eq!(seen.load(Ordering::SeqCst), 8);
+}
+
+#[test]
+fn encode_bytes() {
+    // Valid UTF-8 gets encoded as usual, but other bytes need a byte-level pipeline
+    let tokenizer = get_bert();
+    assert_eq!(
+        tokenizer.encode_bytes(b"hi!", false).unwrap().get_tokens(),
+        &["hi", "!"]
+    );
+    assert!(tokenizer.encode_bytes(b"hi \xff", false).is_err());
+
+    let vocab = [("<unk>", 0), ("Ġhi", 1), ("Ġ", 2), ("ÿþ", 3), ("Ġthere", 4)]
+        .iter()
+        .map(|(token, id)| (token.to_string(), *id))
+        .collect();
+    let mut tokenizer = Tokenizer::new(Box::new(WordLevelBuilder::new().vocab(vocab).build()));
+    tokenizer.with_pre_tokenizer(Box::new(ByteLevel::default()));
+    let encoding = tokenizer.encode_bytes(b"hi \xff\xfe there", false).unwrap();
+    assert_eq!(encoding.get_ids(), &[1, 2, 3, 4]);
+    assert_eq!(encoding.get_offsets(), &[(0, 2), (2, 3), (3, 5), (5, 11)]);
+}
+
+#[test]
+fn train_without_progress() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(b"hello world\nhello there\n").unwrap();
+
+    let trainer: Box<dyn Trainer> = Box::new(BpeTrainer::builder().show_progress(false).build());
+    let mut tokenizer = Tokenizer::new(Box::new(BPE::default()));
+    tokenizer.with_pre_tokenizer(Box::new(Whitespace::default()));
+    tokenizer
+        .train(&trainer, vec![file.path().to_str().unwrap().to_owned()])
+        .unwrap();
+    assert!(tokenizer.get_vocab_size(false) > 0);
+    assert!(tokenizer.token_to_id("hello").is_some());
+}
+
+#[test]
+fn added_tokens_info() {
+    let mut tokenizer = get_bert();
+    assert_eq!(tokenizer.added_tokens_info().0, 0);
+    assert!(tokenizer.added_tokens_info().1.is_empty());
+
+    // Already part of the model vocabulary
+    tokenizer.add_special_tokens(&["[CLS]"]);
+    assert_eq!(tokenizer.added_tokens_info().0, 0);
+
+    tokenizer.add_special_tokens(&["<s>", "</s>"]);
+    assert_eq!(tokenizer.added_tokens_info(), (2, 10..=11));
+}
+
+#[test]
+fn encode_both() {
+    let tokenizer = get_bert();
+    for input in &[
+        EncodeInput::Single("hi unaffable!".into()),
+        EncodeInput::Dual("hi".into(), "unaffable".into()),
+    ] {
+        let (with_special, without_special) = tokenizer.encode_both(input.clone()).unwrap();
+        assert_eq!(with_special, tokenizer.encode(input.clone(), true).unwrap());
+        assert_eq!(
+            without_special,
+            tokenizer.encode(input.clone(), false).unwrap()
+        );
+        assert_ne!(with_special.get_ids(), without_special.get_ids());
+    }
+}
+
+fn get_byte_fallback() -> Tokenizer {
+    let vocab = ["<unk>", "Hey", "<0xE2>", "<0x96>", "<0x81>", "!"]
+        .iter()
+        .enumerate()
+        .map(|(id, token)| (token.to_string(), id as u32))
+        .collect();
+    let mut tokenizer = Tokenizer::new(Box::new(WordLevelBuilder::new().vocab(vocab).build()));
+    tokenizer.with_decoder(Box::new(ByteFallback::new()));
+    tokenizer
+}
+
+#[test]
+fn decode_incomplete_chars() {
+    let tokenizer = get_byte_fallback();
+    assert_eq!(tokenizer.decode(vec![1, 2, 3, 4], false).unwrap(), "Hey▁");
+    // Ending in the middle of a char
+    assert_eq!(
+        tokenizer.decode(vec![1, 2, 3], false).unwrap(),
+        "Hey\u{FFFD}"
+    );
+}
+
+#[test]
+fn decode_stream() {
+    let tokenizer = get_byte_fallback();
+    let mut stream = tokenizer.decode_stream(false);
+    let steps = [1, 2, 3, 4, 5, 2, 3]
+        .iter()
+        .map(|id| stream.step(*id).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        steps,
+        vec![
+            Some("Hey".into()),
+            None,
+            None,
+            Some("▁".into()),
+            Some("!".into()),
+            None,
+            None
+        ]
+    );
+    // The generation stopped in the middle of a char
+    assert_eq!(stream.finish().unwrap(), Some("\u{FFFD}".into()));
+    assert_eq!(stream.finish().unwrap(), None);
+}
+
+#[test]
+fn decode_stream_invalid_bytes() {
+    let tokenizer = get_byte_fallback();
+    let mut stream = tokenizer.decode_stream(false);
+    // Continuation bytes that can never be completed
+    let steps = [4, 1, 4, 4, 4, 4, 5]
+        .iter()
+        .map(|id| stream.step(*id).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        steps,
+        vec![
+            None,
+            Some("\u{FFFD}Hey".into()),
+            None,
+            None,
+            None,
+            Some("\u{FFFD}".repeat(4)),
+            Some("!".into()),
+        ]
+    );
+    assert_eq!(stream.finish().unwrap(), None);
+}
+
+#[test]
+fn find_added_tokens() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_special_tokens(&["[CLS]", "<s>"]);
+    tokenizer.add_tokens(&[AddedToken {
+        content: "hé".into(),
+        single_word: false,
+    }]);
+
+    assert!(tokenizer.find_added_tokens("hi there").is_empty());
+    assert_eq!(
+        tokenizer.find_added_tokens("[CLS] é hé<s>"),
+        vec![
+            ("[CLS]".into(), 1, (0, 5)),
+            ("hé".into(), 11, (8, 10)),
+            ("<s>".into(), 10, (10, 13)),
+        ]
+    );
+}
+
+/// A `WordLevel` that works on the whole normalized string
+#[derive(Clone)]
+struct WholeInputModel(WordLevel);
+
+impl Model for WholeInputModel {
+    fn tokenize(
+        &self,
+        tokens: Vec<(String, Offsets)>,
+    ) -> tokenizers::tokenizer::Result<Vec<Token>> {
+        self.0.tokenize(tokens)
+    }
+    fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.0.token_to_id(token)
+    }
+    fn id_to_token(&self, id: u32) -> Option<String> {
+        self.0.id_to_token(id)
+    }
+    fn get_vocab(&self) -> &HashMap<String, u32> {
+        self.0.get_vocab()
+    }
+    fn get_vocab_size(&self) -> usize {
+        self.0.get_vocab_size()
+    }
+    fn save(
+        &self,
+        folder: &Path,
+        name: Option<&str>,
+    ) -> tokenizers::tokenizer::Result<Vec<PathBuf>> {
+        self.0.save(folder, name)
+    }
+    fn handles_pre_tokenization(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn model_handles_pre_tokenization() {
+    let vocab = [("<unk>", 0), ("hi there", 1)]
+        .iter()
+        .map(|(token, id)| (token.to_string(), *id))
+        .collect();
+    let mut tokenizer = Tokenizer::new(Box::new(WholeInputModel(
+        WordLevelBuilder::new().vocab(vocab).build(),
+    )));
+    tokenizer.with_pre_tokenizer(Box::new(Whitespace::default()));
+
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("hi there".into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_ids(), &[1]);
+    assert_eq!(encoding.get_offsets(), &[(0, 8)]);
+}
+
+#[test]
+fn encode_prenormalized() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_normalizer(Box::new(BertNormalizer::new(true, true, true, true)));
+
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("HI!".into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["hi", "!"]);
+
+    // Without the normalizer, "HI" is unknown
+    let encoding = tokenizer
+        .encode_prenormalized(EncodeInput::Single("HI!".into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["[UNK]", "!"]);
+    let encoding = tokenizer
+        .encode_prenormalized(EncodeInput::Single("hi!".into()), true)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["[CLS]", "hi", "!", "[SEP]"]);
+    assert_eq!(encoding.get_offsets()[1..3], [(0, 2), (2, 3)]);
+}
+
+#[test]
+fn truncation_respects_word_boundaries() {
+    let mut tokenizer = get_bert();
+    let mut params = TruncationParams {
+        max_length: 5,
+        strategy: TruncationStrategy::LongestFirst,
+        stride: 0,
+        respect_word_boundaries: false,
+    };
+    let input = || EncodeInput::Single("hi unaffable".into());
+
+    tokenizer.with_truncation(Some(params.clone()));
+    let encoding = tokenizer.encode(input(), true).unwrap();
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[CLS]", "hi", "un", "##aff", "[SEP]"]
+    );
+
+    // "unaffable" is split in 3 tokens, and gets removed entirely
+    params.respect_word_boundaries = true;
+    tokenizer.with_truncation(Some(params));
+    let encoding = tokenizer.encode(input(), true).unwrap();
+    assert_eq!(encoding.get_tokens(), &["[CLS]", "hi", "[SEP]"]);
+    assert_eq!(encoding.get_num_truncated(), 3);
+}
+
+#[test]
+fn clone_tokenizer() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_tokens(&[AddedToken {
+        content: "my-token".into(),
+        single_word: false,
+    }]);
+    let input = || EncodeInput::Single("hi my-token".into());
+
+    let mut cloned = tokenizer.clone();
+    assert_eq!(
+        cloned.encode(input(), true).unwrap(),
+        tokenizer.encode(input(), true).unwrap()
+    );
+
+    // Modifying the clone doesn't affect the original
+    cloned.with_normalizer(Box::new(BertNormalizer::new(true, true, true, true)));
+    assert_eq!(
+        cloned
+            .encode(EncodeInput::Single("HI".into()), false)
+            .unwrap()
+            .get_tokens(),
+        &["hi"]
+    );
+    assert_eq!(
+        tokenizer.encode(input(), true).unwrap().get_tokens(),
+        &["[CLS]", "hi", "my-token", "[SEP]"]
+    );
+}
+
+#[test]
+fn encode_normalized() {
+    let normalizer = BertNormalizer::new(true, true, true, true);
+    let mut normalized = NormalizedString::from("Héllo UNAFFABLE!");
+    normalizer.normalize(&mut normalized).unwrap();
+
+    let mut bert = get_bert();
+    bert.with_normalizer(Box::new(normalizer));
+    let expected = bert
+        .encode(EncodeInput::Single("Héllo UNAFFABLE!".into()), true)
+        .unwrap();
+    let encoding = get_bert()
+        .encode_normalized(normalized.clone(), true)
+        .unwrap();
+    assert_eq!(encoding, expected);
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[CLS]", "[UNK]", "un", "##aff", "##able", "!", "[SEP]"]
+    );
+    assert_eq!(encoding.get_offsets()[2], (6, 8));
+
+    // The same normalization with another pre-tokenizer
+    let mut whitespace = get_bert();
+    whitespace.with_pre_tokenizer(Box::new(WhitespaceSplit::default()));
+    let encoding = whitespace.encode_normalized(normalized, false).unwrap();
+    assert_eq!(encoding.get_tokens(), &["[UNK]", "[UNK]"]);
+    assert_eq!(encoding.get_offsets(), &[(0, 5), (6, 16)]);
+}
+
+#[test]
+fn unknown_flags() {
+    let tokenizer = get_bert();
+    assert_eq!(tokenizer.get_model().get_unk_id(), Some(0));
+
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("hi hello unaffable!".into()), true)
+        .unwrap();
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[CLS]", "hi", "[UNK]", "un", "##aff", "##able", "!", "[SEP]"]
+    );
+    assert_eq!(
+        encoding.get_unknown_flags(),
+        &[false, false, true, false, false, false, false, false]
+    );
+    // The unknown token spans the whole word
+    assert_eq!(encoding.get_offsets()[2], (3, 8));
+}
+
+#[test]
+fn is_lossless() {
+    let vocab = ByteLevel::alphabet()
+        .into_iter()
+        .enumerate()
+        .map(|(id, c)| (c.to_string(), id as u32))
+        .collect();
+    let bpe = BPE::builder()
+        .vocab_and_merges(vocab, HashMap::new())
+        .build()
+        .unwrap();
+    let mut byte_level = Tokenizer::new(Box::new(bpe));
+    byte_level.with_normalizer(Box::new(Lowercase));
+    byte_level.with_pre_tokenizer(Box::new(ByteLevel::new(false, true)));
+    byte_level.with_decoder(Box::new(ByteLevel::new(false, true)));
+    // Compared to the normalized text
+    assert!(byte_level.is_lossless("Hello  Wörld 👋!").unwrap());
+
+    let bert = get_bert();
+    assert!(bert.is_lossless("hi").unwrap());
+    assert!(!bert.is_lossless("hi unaffable!").unwrap());
+    assert!(!bert.is_lossless("hello").unwrap());
+}
+
+#[test]
+fn decode_separator() {
+    let vocab = ["<unk>", "你", "好", "!"]
+        .iter()
+        .enumerate()
+        .map(|(id, token)| (token.to_string(), id as u32))
+        .collect();
+    let mut tokenizer = Tokenizer::new(Box::new(WordLevelBuilder::new().vocab(vocab).build()));
+    assert_eq!(tokenizer.decode(vec![1, 2, 3], false).unwrap(), "你 好 !");
+
+    tokenizer.with_decode_separator(Some("".into()));
+    assert_eq!(tokenizer.decode(vec![1, 2, 3], false).unwrap(), "你好!");
+
+    // Ignored with a decoder
+    tokenizer.with_decoder(Box::new(ByteFallback::new()));
+    tokenizer.with_decode_separator(Some("-".into()));
+    assert_eq!(tokenizer.decode(vec![1, 2, 3], false).unwrap(), "你好!");
+}
+
+#[test]
+fn add_tokens_detailed() {
+    let mut tokenizer = get_bert();
+    let token = |content: &str| AddedToken {
+        content: content.into(),
+        single_word: false,
+    };
+
+    let result = tokenizer.add_tokens_detailed(&[token("new"), token("hi"), token("")]);
+    assert_eq!(
+        result,
+        AddResult {
+            added: vec!["new".into()],
+            ignored_existing: vec!["hi".into()],
+            ignored_empty: 1,
+        }
+    );
+    assert_eq!(tokenizer.token_to_id("new"), Some(10));
+
+    // Already added, or given twice
+    let result = tokenizer.add_tokens_detailed(&[token("new"), token("other"), token("other")]);
+    assert_eq!(result.added, vec![String::from("other")]);
+    assert_eq!(
+        result.ignored_existing,
+        vec![String::from("new"), String::from("other")]
+    );
+    assert_eq!(tokenizer.add_tokens(&[token("new"), token("last")]), 1);
+}
+
+#[test]
+fn encode_batch_in() {
+    let tokenizer = get_bert();
+    let inputs = || {
+        vec![
+            EncodeInput::Single("hi unaffable".into()),
+            EncodeInput::Dual("hi".into(), "hi !".into()),
+        ]
+    };
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        tokenizer.encode_batch_in(&pool, inputs(), true).unwrap(),
+        tokenizer.encode_batch(inputs(), true).unwrap()
+    );
+}
+
+#[test]
+fn encode_presplit() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_special_tokens(&["<ent>"]);
+    let text = "hi <ent> unaffable";
+
+    let splits = tokenizer
+        .split_on_added_tokens(text)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        splits,
+        vec![("hi ", None), ("<ent>", Some(10)), (" unaffable", None)]
+    );
+
+    let mut splits = splits
+        .into_iter()
+        .map(|(split, id)| (split.to_owned(), id))
+        .collect::<Vec<_>>();
+    let expected = tokenizer
+        .encode(EncodeInput::Single(text.into()), true)
+        .unwrap();
+    assert_eq!(
+        tokenizer.encode_presplit(splits.clone(), 0, true).unwrap(),
+        expected
+    );
+
+    // Only changing the last part
+    splits[2].0 = " hi!".into();
+    let encoding = tokenizer.encode_presplit(splits, 0, true).unwrap();
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[CLS]", "hi", "<ent>", "hi", "!", "[SEP]"]
+    );
+}
+
+#[test]
+fn decoder_for_model() {
+    let mut tokenizer = get_bert();
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("unaffable hi".into()), false)
+        .unwrap();
+    let ids = encoding.get_ids().to_vec();
+    assert_eq!(
+        tokenizer.decode(ids.clone(), true).unwrap(),
+        "un ##aff ##able hi"
+    );
+
+    let decoder = tokenizer.decoder_for_model().unwrap();
+    tokenizer.with_decoder(decoder);
+    assert_eq!(tokenizer.decode(ids, true).unwrap(), "unaffable hi");
+
+    // A model without any subword marker doesn't need a decoder
+    let tokenizer = Tokenizer::new(Box::new(WordLevel::default()));
+    assert!(tokenizer.decoder_for_model().is_none());
+}
+
+#[test]
+fn added_token_offsets() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_tokens(&[AddedToken {
+        content: "<tok>".into(),
+        single_word: false,
+    }]);
+
+    let text = "hi <tok> hi";
+    let encoding = tokenizer
+        .encode(EncodeInput::Single(text.into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["hi", "<tok>", "hi"]);
+    assert_eq!(encoding.get_offsets(), &[(0, 2), (3, 8), (9, 11)]);
+    for ((start, end), token) in encoding.get_offsets().iter().zip(encoding.get_tokens()) {
+        assert_eq!(&text[*start..*end], token);
+    }
+
+    // The offsets are in chars, even with multi-bytes chars
+    let mut tokenizer = get_bert();
+    tokenizer.add_tokens(&[AddedToken {
+        content: "é".into(),
+        single_word: false,
+    }]);
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("hi é  hi".into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_offsets(), &[(0, 2), (3, 4), (6, 8)]);
+
+    // The normalization of the parts before the added token changes their length, but the
+    // offsets still refer to the original input, in both sequences of a pair
+    let mut tokenizer = get_bert();
+    tokenizer.with_normalizer(Box::new(BertNormalizer::new(false, true, false, false)));
+    tokenizer.add_tokens(&[AddedToken {
+        content: "<tok>".into(),
+        single_word: false,
+    }]);
+    let encoding = tokenizer
+        .encode(
+            EncodeInput::Dual("你<tok>hi".into(), "hi 你 <tok>".into()),
+            true,
+        )
+        .unwrap();
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[CLS]", "[UNK]", "<tok>", "hi", "[SEP]", "hi", "[UNK]", "<tok>", "[SEP]"]
+    );
+    assert_eq!(
+        encoding.get_offsets(),
+        &[
+            (0, 0),
+            (0, 1),
+            (1, 6),
+            (6, 8),
+            (0, 0),
+            (0, 2),
+            (3, 4),
+            (5, 10),
+            (0, 0)
+        ]
+    );
+}
+
+#[test]
+fn vocab_search() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_tokens(&[AddedToken {
+        content: "##abc".into(),
+        single_word: false,
+    }]);
+
+    assert!(tokenizer.contains_token("##aff"));
+    assert!(tokenizer.contains_token("##abc"));
+    assert!(!tokenizer.contains_token("aff"));
+
+    assert_eq!(
+        tokenizer.tokens_with_prefix("##a"),
+        vec!["##abc", "##able", "##aff"]
+    );
+    assert_eq!(tokenizer.tokens_with_prefix("[S"), vec!["[SEP]"]);
+    assert!(tokenizer.tokens_with_prefix("xyz").is_empty());
+    assert_eq!(tokenizer.tokens_with_prefix("").len(), 11);
+}
+
+#[test]
+fn longest_and_shortest_tokens() {
+    let mut tokenizer = get_bert();
+    assert_eq!(tokenizer.longest_token(), Some(("[MASK]".into(), 4)));
+    assert_eq!(tokenizer.shortest_token(), Some(("!".into(), 9)));
+    assert_eq!(
+        tokenizer.tokens_longer_than(5),
+        vec![("[MASK]".into(), 4), ("##able".into(), 7)]
+    );
+
+    tokenizer.add_tokens(&[AddedToken {
+        content: "<longest>".into(),
+        single_word: false,
+    }]);
+    assert_eq!(tokenizer.longest_token(), Some(("<longest>".into(), 10)));
+    assert_eq!(
+        tokenizer.tokens_longer_than(5),
+        vec![
+            ("<longest>".into(), 10),
+            ("[MASK]".into(), 4),
+            ("##able".into(), 7)
+        ]
+    );
+    assert!(tokenizer.tokens_longer_than(9).is_empty());
+}
+
+#[test]
+fn empty_pre_tokens() {
+    let vocab = [("<unk>", 0), ("hi", 1)]
+        .iter()
+        .map(|(token, id)| (token.to_string(), *id))
+        .collect::<HashMap<_, _>>();
+    let mut tokenizer = Tokenizer::new(Box::new(WordLevelBuilder::new().vocab(vocab).build()));
+    tokenizer.with_normalizer(Box::new(Strip::new(true, true)));
+    tokenizer.add_special_tokens(&["<a>", "<b>"]);
+
+    // The part between the two added tokens is left empty by the normalizer
+    let input = "hi<a> <b>hi";
+    let encoding = tokenizer
+        .encode(EncodeInput::Single(input.into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["hi", "<a>", "<b>", "hi"]);
+
+    tokenizer.with_keep_empty_pre_tokens(true);
+    let encoding = tokenizer
+        .encode(EncodeInput::Single(input.into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["hi", "<a>", "", "<b>", "hi"]);
+}
+
+#[test]
+fn special_tokens_offsets() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_normalizer(Box::new(BertNormalizer::new(true, false, false, false)));
+    let encoding = tokenizer
+        .encode(
+            EncodeInput::Dual("\u{1}hello hi".into(), "\u{1}\u{1}unaffable".into()),
+            true,
+        )
+        .unwrap();
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[CLS]", "[UNK]", "hi", "[SEP]", "un", "##aff", "##able", "[SEP]"]
+    );
+    assert_eq!(
+        encoding.get_offsets(),
+        &[
+            (0, 0),
+            (1, 6),
+            (7, 9),
+            (0, 0),
+            (2, 4),
+            (4, 7),
+            (7, 11),
+            (0, 0)
+        ]
+    );
+    assert_eq!(
+        encoding.get_source_offsets(),
+        vec![
+            None,
+            Some((1, 6)),
+            Some((7, 9)),
+            None,
+            Some((2, 4)),
+            Some((4, 7)),
+            Some((7, 11)),
+            None
+        ]
+    );
+}
+
+#[test]
+fn train_incremental() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(b"hello hi\nhello there\n").unwrap();
+    let files = vec![file.path().to_str().unwrap().to_owned()];
+
+    let mut tokenizer = get_bert();
+    tokenizer.add_tokens(&[AddedToken {
+        content: "<tok>".into(),
+        single_word: false,
+    }]);
+    let vocab = tokenizer.get_vocab(true);
+
+    let trainer: Box<dyn Trainer> = Box::new(
+        WordPieceTrainer::builder()
+            .show_progress(false)
+            .min_frequency(2)
+            .build(),
+    );
+    tokenizer
+        .train_incremental(&trainer, files.clone())
+        .unwrap();
+
+    // The existing tokens keep their id, and the new ones don't use the added tokens ids
+    let new_vocab = tokenizer.get_vocab(true);
+    for (token, id) in &vocab {
+        assert_eq!(new_vocab.get(token), Some(id));
+    }
+    assert!(new_vocab.len() > vocab.len());
+    assert!(tokenizer.token_to_id("hello").unwrap() > 10);
+    assert_eq!(tokenizer.get_model().id_to_token(10), None);
+    assert_eq!(tokenizer.get_vocab_size(false), new_vocab.len() - 1);
+    assert_eq!(
+        tokenizer
+            .encode(EncodeInput::Single("hello <tok> hi".into()), false)
+            .unwrap()
+            .get_tokens(),
+        &["hello", "<tok>", "hi"]
+    );
+
+    // BPE can't extend its vocabulary without its merges
+    let trainer: Box<dyn Trainer> = Box::new(BpeTrainer::builder().show_progress(false).build());
+    let mut tokenizer = Tokenizer::new(Box::new(BPE::default()));
+    assert!(tokenizer.train_incremental(&trainer, files).is_err());
+}
+
+#[test]
+fn special_tokens_mask_convention() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_padding(Some(PaddingParams {
+        strategy: PaddingStrategy::BatchLongest,
+        direction: PaddingDirection::Right,
+        pad_id: 3,
+        pad_type_id: 0,
+        pad_token: "[PAD]".into(),
+    }));
+    let inputs = || {
+        vec![
+            EncodeInput::Single("hi".into()),
+            EncodeInput::Single("hi !".into()),
+        ]
+    };
+
+    let encodings = tokenizer.encode_batch(inputs(), true).unwrap();
+    assert_eq!(encodings[0].get_special_tokens_mask(), &[1, 0, 1, 1]);
+    assert_eq!(encodings[1].get_special_tokens_mask(), &[1, 0, 0, 1]);
+
+    tokenizer.with_special_tokens_mask_convention(SpecialMaskConvention::SpecialIsZero);
+    let encodings = tokenizer.encode_batch(inputs(), true).unwrap();
+    assert_eq!(
+        encodings[0].get_tokens(),
+        &["[CLS]", "hi", "[SEP]", "[PAD]"]
+    );
+    assert_eq!(
+        tokenizer.get_special_tokens_mask(&encodings[0]),
+        &[0, 1, 0, 0]
+    );
+    assert_eq!(
+        tokenizer.get_special_tokens_mask(&encodings[1]),
+        &[0, 1, 1, 0]
+    );
+
+    // The encodings themselves keep marking the special tokens with 1, so they can still be
+    // padded, and their source offsets skip the special tokens
+    let mut encoding = tokenizer
+        .encode(EncodeInput::Single("hi".into()), true)
+        .unwrap();
+    assert_eq!(encoding.get_special_tokens_mask(), &[1, 0, 1]);
+    encoding.pad(5, 3, 0, "[PAD]", PaddingDirection::Right);
+    assert_eq!(encoding.get_special_tokens_mask(), &[1, 0, 1, 1, 1]);
+    assert_eq!(
+        encoding.get_source_offsets(),
+        vec![None, Some((0, 2)), None, None, None]
+    );
+    assert_eq!(
+        tokenizer.get_special_tokens_mask(&encoding),
+        &[0, 1, 0, 0, 0]
+    );
+    assert_eq!(
+        tokenizer.get_special_tokens_mask(&encoding),
+        encoding.get_special_tokens_mask_with(SpecialMaskConvention::SpecialIsZero)
+    );
+}
+
+#[test]
+fn corpus_stats() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(b"hi unaffable\r\nhello hi!\n\n").unwrap();
+    let mut other = tempfile::NamedTempFile::new().unwrap();
+    other.write_all(b"hi").unwrap();
+
+    let stats = get_bert()
+        .corpus_stats(vec![
+            file.path().to_str().unwrap().to_owned(),
+            other.path().to_str().unwrap().to_owned(),
+        ])
+        .unwrap();
+    assert_eq!(stats.lines, 4);
+    assert_eq!(stats.chars, 23);
+    assert_eq!(stats.tokens, 8);
+    assert_eq!(stats.unknown_tokens, 1);
+    assert_eq!(stats.token_counts[&8], 3);
+    assert_eq!(stats.token_counts[&0], 1);
+    assert_eq!(stats.token_counts.values().sum::<usize>(), 8);
+    assert_eq!(stats.tokens_per_line(), 2.0);
+    assert_eq!(stats.unknown_rate(), 0.125);
+    assert!((stats.tokens_per_char() - 8.0 / 23.0).abs() < 1e-9);
+
+    assert!(get_bert().corpus_stats(vec!["missing.txt".into()]).is_err());
+}
+
+#[test]
+fn train_with_bom() {
+    let train = |content: &[u8]| {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+
+        let trainer: Box<dyn Trainer> =
+            Box::new(BpeTrainer::builder().show_progress(false).build());
+        let mut tokenizer = Tokenizer::new(Box::new(BPE::default()));
+        tokenizer.with_pre_tokenizer(Box::new(WhitespaceSplit::default()));
+        tokenizer
+            .train(&trainer, vec![file.path().to_str().unwrap().to_owned()])
+            .unwrap();
+        let mut vocab = tokenizer.get_vocab(true).into_keys().collect::<Vec<_>>();
+        vocab.sort();
+        vocab
+    };
+
+    let text = "hello world\nhello there\n";
+    let utf8 = train(&[&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat());
+    assert!(utf8.contains(&"hello".to_owned()));
+    assert!(!utf8.iter().any(|token| token.contains('\u{feff}')));
+    assert_eq!(utf8, train(text.as_bytes()));
+
+    // The UTF-16 files get transcoded
+    let utf16_le = std::iter::once(0xFEFF)
+        .chain(text.encode_utf16())
+        .flat_map(|u| u.to_le_bytes().to_vec())
+        .collect::<Vec<_>>();
+    assert_eq!(train(&utf16_le), utf8);
+    let utf16_be = std::iter::once(0xFEFF)
+        .chain(text.encode_utf16())
+        .flat_map(|u| u.to_be_bytes().to_vec())
+        .collect::<Vec<_>>();
+    assert_eq!(train(&utf16_be), utf8);
+}
+
+#[test]
+fn default_add_special_tokens() {
+    let mut tokenizer = get_bert();
+    let input = || EncodeInput::Single("hi".into());
+    assert_eq!(
+        tokenizer.encode_default(input()).unwrap(),
+        tokenizer.encode(input(), true).unwrap()
+    );
+
+    tokenizer.with_add_special_tokens(false);
+    assert_eq!(
+        tokenizer.encode_default(input()).unwrap().get_tokens(),
+        &["hi"]
+    );
+    let encodings = tokenizer
+        .encode_batch_default(vec![input(), input()])
+        .unwrap();
+    assert_eq!(encodings[1].get_tokens(), &["hi"]);
+    // The explicit flag still wins
+    assert_eq!(
+        tokenizer.encode(input(), true).unwrap().get_tokens(),
+        &["[CLS]", "hi", "[SEP]"]
+    );
+}
+
+#[test]
+fn encode_many_pre_tokens() {
+    // Enough pre-tokens to get them tokenized by batches
+    let tokenizer = get_bert();
+    let input = "unaffable hi! ".repeat(400);
+    let encoding = tokenizer
+        .encode(EncodeInput::Single(input.as_str().into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_ids().len(), 400 * 5);
+    for (i, chunk) in encoding.get_ids().chunks(5).enumerate() {
+        assert_eq!(chunk, &[5, 6, 7, 8, 9]);
+        let start = i * 14;
+        assert_eq!(
+            &encoding.get_offsets()[i * 5..(i + 1) * 5],
+            &[
+                (start, start + 2),
+                (start + 2, start + 5),
+                (start + 5, start + 9),
+                (start + 10, start + 12),
+                (start + 12, start + 13)
+            ]
+        );
+    }
+    let word_starts = encoding.get_word_starts();
+    assert_eq!(word_starts.iter().filter(|start| **start).count(), 1200);
+}
+
+#[test]
+fn truncation_too_small() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_truncation(Some(TruncationParams {
+        max_length: 1,
+        strategy: TruncationStrategy::LongestFirst,
+        stride: 0,
+        respect_word_boundaries: false,
+    }));
+    let err = tokenizer
+        .encode(EncodeInput::Single("hi hi".into()), true)
+        .unwrap_err();
+    assert!(err.to_string().contains("max_length 1"));
+    // A pair adds one more special token
+    tokenizer.with_truncation(Some(TruncationParams {
+        max_length: 3,
+        strategy: TruncationStrategy::LongestFirst,
+        stride: 0,
+        respect_word_boundaries: false,
+    }));
+    assert!(tokenizer
+        .encode(EncodeInput::Dual("hi".into(), "hi".into()), true)
+        .is_err());
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("hi hi".into()), true)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["[CLS]", "hi", "[SEP]"]);
+
+    // Without the special tokens, there is enough room
+    let encoding = tokenizer
+        .encode(EncodeInput::Dual("hi".into(), "hi".into()), false)
+        .unwrap();
+    assert_eq!(encoding.get_tokens(), &["hi", "hi"]);
+}
+
+#[test]
+fn truncation_max_length_zero() {
+    // A `max_length` of 0 disables the truncation, even with the special tokens
+    let mut tokenizer = get_bert();
+    tokenizer.with_truncation(Some(TruncationParams {
+        max_length: 0,
+        strategy: TruncationStrategy::LongestFirst,
+        stride: 0,
+        respect_word_boundaries: false,
+    }));
+    let encoding = tokenizer
+        .encode(EncodeInput::Dual("hi hi".into(), "hi".into()), true)
+        .unwrap();
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[CLS]", "hi", "hi", "[SEP]", "hi", "[SEP]"]
+    );
+}
+
+#[test]
+fn pad_and_truncate_to() {
+    let mut tokenizer = get_bert();
+    tokenizer.pad_and_truncate_to(6).unwrap();
+    let encodings = tokenizer
+        .encode_batch(
+            vec![
+                EncodeInput::Single("hi".into()),
+                EncodeInput::Single("hi hi hi hi hi".into()),
+                EncodeInput::Dual("hi hi hi".into(), "hi !".into()),
+            ],
+            true,
+        )
+        .unwrap();
+    let tokens = encodings
+        .iter()
+        .map(|encoding| encoding.get_tokens().to_vec())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            vec!["[CLS]", "hi", "[SEP]", "[PAD]", "[PAD]", "[PAD]"],
+            vec!["[CLS]", "hi", "hi", "hi", "hi", "[SEP]"],
+            vec!["[CLS]", "hi", "hi", "[SEP]", "hi", "[SEP]"],
+        ]
+    );
+    assert_eq!(encodings[0].get_ids()[3], 3);
+
+    // The existing padding token is kept
+    tokenizer.with_padding(Some(PaddingParams {
+        strategy: PaddingStrategy::BatchLongest,
+        direction: PaddingDirection::Left,
+        pad_id: 4,
+        pad_type_id: 0,
+        pad_token: "[MASK]".into(),
+    }));
+    tokenizer.pad_and_truncate_to(5).unwrap();
+    let encoding = tokenizer
+        .encode(EncodeInput::Single("hi".into()), true)
+        .unwrap();
+    assert_eq!(
+        encoding.get_tokens(),
+        &["[MASK]", "[MASK]", "[CLS]", "hi", "[SEP]"]
+    );
+
+    // The pair of sequences needs room for 3 special tokens
+    assert!(tokenizer.pad_and_truncate_to(3).is_err());
+
+    let mut tokenizer = Tokenizer::new(Box::new(
+        WordLevelBuilder::new()
+            .vocab([("<unk>".to_string(), 0)].iter().cloned().collect())
+            .build(),
+    ));
+    assert!(tokenizer.pad_and_truncate_to(4).is_err());
+}
+
+#[test]
+fn decode_batch_joined() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_special_tokens(&["[CLS]", "[SEP]"]);
+    let sentences = vec![vec![1, 8, 9, 2], vec![], vec![5, 6, 7]];
+    assert_eq!(
+        tokenizer
+            .decode_batch_joined(sentences.clone(), true, "\n")
+            .unwrap(),
+        "hi !\n\nun ##aff ##able"
+    );
+    assert_eq!(
+        tokenizer
+            .decode_batch_joined(sentences, false, " | ")
+            .unwrap(),
+        "[CLS] hi ! [SEP] |  | un ##aff ##able"
+    );
+    assert_eq!(
+        tokenizer.decode_batch_joined(vec![], true, "\n").unwrap(),
+        ""
+    );
+}
+
+#[test]
+fn encode_into() {
+    let mut tokenizer = get_bert();
+    tokenizer.add_special_tokens(&["[MASK]"]);
+    let inputs = vec![
+        EncodeInput::Single("unaffable hi!".into()),
+        EncodeInput::Dual("hi [MASK] unable".into(), "hi!".into()),
+        EncodeInput::Single("".into()),
+        EncodeInput::Single("hi".into()),
+    ];
+
+    let mut buffers = EncodeBuffers::new();
+    for input in inputs {
+        let encoding = tokenizer
+            .encode_into(input.clone(), true, &mut buffers)
+            .unwrap();
+        assert_eq!(encoding, tokenizer.encode(input, true).unwrap());
+        buffers.recycle(encoding);
+    }
+
+    // Without any post-processing, the recycled vectors end up in the next encoding
+    let vocab = [("<unk>", 0), ("hi", 1)]
+        .iter()
+        .map(|(token, id)| (token.to_string(), *id))
+        .collect::<HashMap<_, _>>();
+    let mut tokenizer = Tokenizer::new(Box::new(WordLevelBuilder::new().vocab(vocab).build()));
+    tokenizer.with_pre_tokenizer(Box::new(WhitespaceSplit::default()));
+    let encoding = tokenizer
+        .encode_into("hi hi hi".into(), true, &mut buffers)
+        .unwrap();
+    let ids = encoding.get_ids().as_ptr();
+    buffers.recycle(encoding);
+    let encoding = tokenizer
+        .encode_into("hi there".into(), true, &mut buffers)
+        .unwrap();
+    assert_eq!(encoding.get_ids(), &[1, 0]);
+    assert_eq!(encoding.get_ids().as_ptr(), ids);
+}
+
+#[test]
+fn add_tokens_checked() {
+    let mut tokenizer = get_bert();
+    let tokens = [
+        AddedToken {
+            content: "hi".into(),
+            single_word: false,
+        },
+        AddedToken {
+            content: "<new>".into(),
+            single_word: false,
+        },
+    ];
+    let err = tokenizer.add_tokens_checked(&tokens).unwrap_err();
+    assert!(err.to_string().contains("model vocabulary: [\"hi\"]"));
+    assert!(!tokenizer.contains_token("<new>"));
+
+    // The lenient version just ignores it
+    assert_eq!(tokenizer.add_tokens(&tokens), 1);
+    assert_eq!(tokenizer.token_to_id("<new>"), Some(10));
+    assert!(tokenizer.add_tokens_checked(&tokens[1..]).is_err());
+}
+
+#[test]
+fn many_added_token_splits() {
+    let mut tokenizer = get_bert();
+    tokenizer.with_normalizer(Box::new(BertNormalizer::new(false, true, false, false)));
+    tokenizer.add_tokens(&[AddedToken {
+        content: "<tok>".into(),
+        single_word: false,
+    }]);
+    let input = "hi<tok>你 ".repeat(1000);
+
+    // The splits are produced lazily, without finding all the added tokens first
+    let mut splits = tokenizer.split_on_added_tokens(&input);
+    assert_eq!(splits.next().unwrap().unwrap(), ("hi", None));
+    assert_eq!(splits.next().unwrap().unwrap(), ("<tok>", Some(10)));
+    assert_eq!(splits.next().unwrap().unwrap(), ("你 hi", None));
+    assert_eq!(splits.count(), 1998);
+    assert_eq!(
+        tokenizer
+            .split_on_added_tokens("")
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap(),
+        vec![("", None)]
+    );
+
+    let encoding = tokenizer.encode(input.as_str().into(), true).unwrap();
+    assert_eq!(encoding.get_ids().len(), 3002);
+    assert_eq!(
+        &encoding.get_offsets()[2998..],
+        &[(8991, 8993), (8993, 8998), (8998, 8999), (0, 0)]
+    );
+
+    // Encoding the splits by small chunks gives the same result
+    for max_splits in &[1, 3, 4] {
+        tokenizer.with_max_added_token_splits(*max_splits);
+        assert_eq!(
+            tokenizer.encode(input.as_str().into(), true).unwrap(),
+            encoding
+        );
+    }
+}